@@ -0,0 +1,23 @@
+#!/usr/bin/env python3
+"""外部引擎示例：在所有棋盘都为空的点中随机落子（不检查自杀与打劫）。
+
+登记方式：AI_ENGINES="random=python3 engines/random_engine.py"，
+建房时 ai_engine 传 "external:random"。协议见 src/engine.rs。
+"""
+import json
+import random
+import sys
+
+for line in sys.stdin:
+    state = json.loads(line)["state"]
+    occupied = set()
+    for board in state["boards"]:
+        occupied.update(board.keys())
+    empty = [
+        f"{x},{y}"
+        for x in range(1, state["width"] + 1)
+        for y in range(1, state["height"] + 1)
+        if f"{x},{y}" not in occupied
+    ]
+    reply = {"position": random.choice(empty) if empty else "none"}
+    print(json.dumps(reply), flush=True)
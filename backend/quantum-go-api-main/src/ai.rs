@@ -1,64 +1,438 @@
-use crate::entity::{Chessman, RoomInfo};
+use crate::board::{Board, Cell, Point, Stone, valid_dimensions};
+use crate::engine::{EngineChoice, MoveGenerator, MoveLimits};
+use crate::entity::RoomInfo;
+use crate::mcts::{Candidate, Mcts, SearchBudget, SearchTree, is_eye};
+use crate::rules::{
+    DEFAULT_BOARDS, KoRule, QuantumPosition, Variant, can_put_chess, first_to_move, parse_history, parse_room_boards,
+    position_after, position_hash, quantum_progress,
+};
+use crate::scoring::{RuleSet, score_position, score_with_dead_stones};
+use rand::distributions::{Distribution, WeightedIndex};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::error::Error;
+use std::time::Duration;
+use tracing::debug;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AIMove {
-    /// 合法坐标如 "3,4"；等待/无需落子时为 "waiting"/"none"
+    /// 合法坐标如 "3,4"，停一手为 "pass"；等待/无需落子时为 "waiting"/"none"
     pub position: String,
     /// "white"/"black"，或等待/无需落子时 "waiting"/"none"
     pub color: String,
     pub confidence: f64,
+    /// 量子落子：对方的下一手与之纠缠
+    #[serde(default)]
+    pub quantum: bool,
+    /// MCTS 本手的模拟次数，记录在落子中以便复现
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playouts: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 提示中的一个候选着法，胜率与领先目数站在行棋方的角度
+#[derive(Debug, Clone, Serialize)]
+pub struct MoveHint {
+    pub position: String,
+    pub visits: u32,
+    pub win_rate: f64,
+    pub score_lead: f64,        // 平均领先目数（数子法，含贴目）
+    pub variation: Vec<String>, // 主要变化，从该手开始，"pass" 为停一手
+}
+
+impl MoveHint {
+    fn from_candidate(candidate: &Candidate) -> Self {
+        Self {
+            position: candidate.position.to_string(),
+            visits: candidate.visits,
+            win_rate: candidate.win_rate,
+            score_lead: candidate.score_lead,
+            variation: candidate
+                .variation
+                .iter()
+                .map(|mv| mv.map_or_else(|| "pass".to_string(), |p| p.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl AIMove {
+    /// 不轮到 AI 执子：等待玩家
+    pub fn waiting() -> Self {
+        Self {
+            position: "waiting".to_string(),
+            color: "waiting".to_string(),
+            confidence: 0.0,
+            quantum: false,
+            playouts: None,
+        }
+    }
+
+    /// 无需落子（纠缠阶段或无点可下），color 为 "none" 或 AI 执子颜色
+    pub fn none(color: &str) -> Self {
+        Self {
+            position: "none".to_string(),
+            color: color.to_string(),
+            confidence: 0.0,
+            quantum: false,
+            playouts: None,
+        }
+    }
+
+    /// 停一手：没有有用的着法（无点可下或只剩自己的眼）
+    pub fn pass(color: &str) -> Self {
+        Self {
+            position: "pass".to_string(),
+            ..Self::none(color)
+        }
+    }
+
+    pub fn is_pass(&self) -> bool {
+        self.position == "pass"
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct QuantumBoardState {
-    pub board1: HashMap<String, Chessman>, // 第一个棋盘
-    pub board2: HashMap<String, Chessman>, // 第二个棋盘
-    pub current_player: String,            // "black" 或 "white"
-    pub quantum_phase: QuantumPhase,       // 量子阶段
-    pub model: i32,                        // 棋盘大小：9/13/19
+    pub boards: Vec<Board>,          // 叠加的各个棋盘，经典围棋只有一个
+    pub current_player: Stone,       // 当前行棋方
+    pub quantum_phase: QuantumPhase, // 量子阶段
+    pub width: i32,                  // 棋盘宽度
+    pub height: i32,                 // 棋盘高度（方形棋盘与宽度相同）
+    pub ko_rule: KoRule,             // 打劫规则
+    pub position_history: Vec<u64>,  // 历次局面哈希，最后一项为当前局面
+    pub variant: Variant,            // 对局变体
+    pub quantum_pairs: i32,          // 本局允许的量子对数（含开局一对）
+    pub pairs_used: i32,             // 已发起的量子落子数
+    pub pending_quantum: Option<Point>, // 等待纠缠的量子子，下一手将与之纠缠
+    pub moves: i32,                  // 已进行的手数
+    pub handicap: i32,               // 让子数
+    pub komi: f64,                   // 贴目，搜索时按数子法判定胜负
+}
+
+impl QuantumBoardState {
+    /// 转为规则引擎的局面，供搜索使用；手数的奇偶与当前行棋方不一致时以行棋方为准
+    pub fn to_position(&self) -> QuantumPosition {
+        let mut history = self.position_history.clone();
+        if history.is_empty() {
+            history.push(position_hash(&self.boards));
+        }
+        let mut position = QuantumPosition {
+            boards: self.boards.clone(),
+            moves: self.moves,
+            ko_rule: self.ko_rule,
+            history,
+            handicap: self.handicap,
+            variant: self.variant,
+            quantum_pairs: self.quantum_pairs,
+            pairs_used: self.pairs_used,
+            pending_quantum: self.pending_quantum,
+            ..QuantumPosition::with_boards(self.width, self.height, self.boards.len())
+        };
+        if position.to_move() != self.current_player {
+            position.moves += 1;
+        }
+        position
+    }
+
+    /// 由规则引擎的局面构造，供离线对局（如 selfplay）直接驱动 AI
+    pub fn from_position(position: &QuantumPosition, komi: f64) -> Self {
+        let current_player = position.to_move();
+        Self {
+            boards: position.boards.clone(),
+            current_player,
+            quantum_phase: match current_player {
+                Stone::Black => QuantumPhase::BlackQuantum,
+                Stone::White => QuantumPhase::WhiteQuantum,
+            },
+            width: position.width,
+            height: position.height,
+            ko_rule: position.ko_rule,
+            position_history: position.history.clone(),
+            variant: position.variant,
+            quantum_pairs: position.quantum_pairs,
+            pairs_used: position.pairs_used,
+            pending_quantum: position.pending_quantum,
+            moves: position.moves,
+            handicap: position.handicap,
+            komi,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum QuantumPhase {
-    BlackQuantum,   // 黑方量子阶段（玩家）
-    WhiteQuantum,   // 白方量子阶段（AI）
+    BlackQuantum,   // 黑方量子阶段
+    WhiteQuantum,   // 白方量子阶段
     Entanglement,   // 纠缠阶段（系统处理）
 }
 
 pub struct SimpleQuantumAI {
     pub difficulty: AIDifficulty,
+    pub color: Stone, // AI 执子颜色，默认执白
+    pub engine: AIEngine,
+    pub budget: SearchBudget, // 搜索预算，默认按难度设置
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 选点方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AIEngine {
+    /// 蒙特卡洛树搜索
+    #[default]
+    Mcts,
+    /// 不搜索：按启发式评分做 softmax 抽样，速度快、棋力较弱
+    Heuristic,
+}
+
+impl AIEngine {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mcts" => Some(AIEngine::Mcts),
+            "heuristic" => Some(AIEngine::Heuristic),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AIEngine::Mcts => "mcts",
+            AIEngine::Heuristic => "heuristic",
+        }
+    }
+}
+
+/// 对局类型（随房间保存）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameType {
+    /// 人人对战
+    #[default]
+    Pvp,
+    /// 与 AI 对战
+    Ai,
+}
+
+impl GameType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pvp" => Some(GameType::Pvp),
+            "ai" => Some(GameType::Ai),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameType::Pvp => "pvp",
+            GameType::Ai => "ai",
+        }
+    }
+}
+
+/// 房主的对手：人类玩家，或由服务端驱动的 AI
+#[derive(Debug, Clone, PartialEq)]
+pub enum Opponent {
+    Human,
+    Ai {
+        engine: EngineChoice,
+        difficulty: AIDifficulty,
+        color: Stone, // AI 执子颜色
+    },
+}
+
+impl Opponent {
+    /// 由房间的 game_type 与 AI 设置得出；AI 设置缺失时取默认值（MCTS、中级、执白）
+    pub fn from_room_info(room_info: &RoomInfo) -> Self {
+        match GameType::parse(&room_info.game_type).unwrap_or_default() {
+            GameType::Pvp => Opponent::Human,
+            GameType::Ai => Opponent::Ai {
+                engine: room_info.ai_engine.as_deref().and_then(EngineChoice::parse).unwrap_or_default(),
+                difficulty: room_info
+                    .ai_difficulty
+                    .as_deref()
+                    .and_then(AIDifficulty::parse)
+                    .unwrap_or(AIDifficulty::Intermediate),
+                color: room_info.ai_color.as_deref().and_then(Stone::parse).unwrap_or(Stone::White),
+            },
+        }
+    }
+}
+
+/// 离线工具（selfplay、gtp）的 AI 配置，格式为 `engine[:difficulty[:playouts]]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerSpec {
+    pub engine: AIEngine,
+    pub difficulty: AIDifficulty,
+    pub playouts: Option<u32>, // 覆盖难度默认的模拟次数，思考时间仍按难度
+}
+
+impl PlayerSpec {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut parts = value.split(':');
+        let engine = parts.next().unwrap_or_default();
+        let engine = AIEngine::parse(engine).ok_or_else(|| format!("unknown engine: {}", engine))?;
+        let difficulty = match parts.next() {
+            Some(difficulty) => {
+                AIDifficulty::parse(difficulty).ok_or_else(|| format!("unknown difficulty: {}", difficulty))?
+            }
+            None => AIDifficulty::Intermediate,
+        };
+        let playouts = match parts.next() {
+            Some(playouts) => Some(
+                playouts
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("invalid playouts: {}", playouts))?,
+            ),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return Err(format!("invalid player: {}", value));
+        }
+        Ok(Self { engine, difficulty, playouts })
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = format!("{}:{}", self.engine.as_str(), self.difficulty.as_str());
+        if let Some(playouts) = self.playouts {
+            label.push_str(&format!(":{}", playouts));
+        }
+        label
+    }
+
+    pub fn ai(&self, color: Stone) -> SimpleQuantumAI {
+        let mut budget = self.difficulty.search_budget();
+        if let Some(playouts) = self.playouts {
+            budget.playouts = playouts;
+        }
+        SimpleQuantumAI::new(self.difficulty)
+            .with_color(color)
+            .with_engine(self.engine)
+            .with_budget(budget)
+    }
+
+    /// 为轮到的一方选一手：返回 (落子点, 是否量子落子)，None 表示停一手
+    /// （选中自己的眼或无点可下时 AI 停一手，不能停一手时照常落子）
+    pub fn genmove(&self, position: &QuantumPosition, komi: f64) -> Result<Option<(Point, bool)>, Box<dyn Error + Send + Sync>> {
+        let state = QuantumBoardState::from_position(position, komi);
+        let ai_move = self.ai(position.to_move()).get_next_move(&state)?;
+        Ok(Point::parse(&ai_move.position).map(|p| (p, ai_move.quantum)))
+    }
+}
+
+// 启发式评分中气与叫吃相关的权重
+const CAPTURE_WEIGHT: f64 = 10.0; // 每提一子
+const ATARI_WEIGHT: f64 = 4.0; // 每叫吃对方一块棋
+const RESCUE_WEIGHT: f64 = 5.0; // 每救出己方一块被叫吃的棋
+const SELF_ATARI_PENALTY: f64 = 8.0; // 落子后自己只剩一口气
+
+// 按房间读秒限制思考时间时预留的余量与最短思考时间
+const CLOCK_MARGIN: Duration = Duration::from_secs(2);
+const MIN_THINK_TIME: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AIDifficulty {
     Beginner,
     Intermediate,
     Advanced,
 }
 
+impl AIDifficulty {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "beginner" => Some(AIDifficulty::Beginner),
+            "intermediate" => Some(AIDifficulty::Intermediate),
+            "advanced" => Some(AIDifficulty::Advanced),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AIDifficulty::Beginner => "beginner",
+            AIDifficulty::Intermediate => "intermediate",
+            AIDifficulty::Advanced => "advanced",
+        }
+    }
+
+    /// 各难度的默认搜索预算：模拟次数与思考时间越多棋力越强
+    pub fn search_budget(&self) -> SearchBudget {
+        let (playouts, millis) = match self {
+            AIDifficulty::Beginner => (100, 300),
+            AIDifficulty::Intermediate => (1000, 1500),
+            AIDifficulty::Advanced => (5000, 5000),
+        };
+        SearchBudget {
+            playouts,
+            time_limit: Duration::from_millis(millis),
+        }
+    }
+
+    /// 每手的思考时间：难度预算，且不超过房间的读秒（留出网络往返的余量）
+    pub fn move_time_limit(&self, countdown: i32) -> Duration {
+        let budget = self.search_budget().time_limit;
+        if countdown <= 0 {
+            return budget;
+        }
+        let clock = Duration::from_secs(countdown as u64).saturating_sub(CLOCK_MARGIN);
+        budget.min(clock.max(MIN_THINK_TIME))
+    }
+
+    /// 启发式选点的 softmax 温度：温度越高，越常选到评分较低的点
+    pub fn temperature(&self) -> f64 {
+        match self {
+            AIDifficulty::Beginner => 4.0,
+            AIDifficulty::Intermediate => 1.5,
+            AIDifficulty::Advanced => 0.3,
+        }
+    }
+}
+
 impl SimpleQuantumAI {
     pub fn new(difficulty: AIDifficulty) -> Self {
-        Self { difficulty }
+        Self {
+            budget: difficulty.search_budget(),
+            engine: AIEngine::default(),
+            color: Stone::White,
+            difficulty,
+        }
+    }
+
+    /// 指定 AI 执子颜色
+    pub fn with_color(self, color: Stone) -> Self {
+        Self { color, ..self }
+    }
+
+    /// 指定选点方式
+    pub fn with_engine(self, engine: AIEngine) -> Self {
+        Self { engine, ..self }
     }
 
-    /// 获取AI的下一步落子（AI 永远执白）
+    /// 自定义搜索预算
+    pub fn with_budget(self, budget: SearchBudget) -> Self {
+        Self { budget, ..self }
+    }
+
+    /// 获取AI的下一步落子：轮到 AI 执子的一方时选点，否则等待玩家
     pub fn get_next_move(&self, game_state: &QuantumBoardState) -> Result<AIMove, Box<dyn Error + Send + Sync>> {
+        self.get_next_move_within(game_state, &MoveLimits::default())
+    }
+
+    /// 同 get_next_move，搜索受 limits 的思考时间与取消信号约束
+    pub fn get_next_move_within(
+        &self,
+        game_state: &QuantumBoardState,
+        limits: &MoveLimits,
+    ) -> Result<AIMove, Box<dyn Error + Send + Sync>> {
         match game_state.quantum_phase {
-            QuantumPhase::BlackQuantum => {
+            QuantumPhase::BlackQuantum | QuantumPhase::WhiteQuantum if game_state.current_player != self.color => {
                 // 玩家阶段：AI 等待
-                Ok(AIMove {
-                    position: "waiting".to_string(),
-                    color: "waiting".to_string(),
-                    confidence: 0.0,
-                })
+                Ok(AIMove::waiting())
             }
-            QuantumPhase::WhiteQuantum => {
-                // AI 白方阶段：选择白棋点
-                self.white_quantum_move(game_state)
+            QuantumPhase::BlackQuantum | QuantumPhase::WhiteQuantum => {
+                // AI 阶段：选择落子点
+                self.quantum_move(game_state, limits)
             }
             QuantumPhase::Entanglement => {
                 // 纠缠阶段无需落子（系统自动处理）
@@ -66,124 +440,190 @@ impl SimpleQuantumAI {
                     position: "none".to_string(),
                     color: "none".to_string(),
                     confidence: self.get_confidence_for_difficulty(),
+                    quantum: false,
+                    playouts: None,
                 })
             }
         }
     }
 
-    /// 白方量子阶段：选择最佳落子位置（AI 执白）
-    fn white_quantum_move(&self, game_state: &QuantumBoardState) -> Result<AIMove, Box<dyn Error + Send + Sync>> {
+    /// AI 的量子阶段：选择最佳落子位置
+    fn quantum_move(&self, game_state: &QuantumBoardState, limits: &MoveLimits) -> Result<AIMove, Box<dyn Error + Send + Sync>> {
         let available_positions = self.get_available_positions(game_state);
         
-        println!("AI white_quantum_move: available_positions={:?}", available_positions);
+        debug!("AI quantum_move: available_positions={:?}", available_positions);
 
+        // 没有有用的着法时停一手；量子开局与等待纠缠时不能停一手
+        let root = game_state.to_position();
+        let can_pass = root.pass(self.color.as_str()).is_ok();
+        if available_positions.is_empty() && can_pass {
+            return Ok(AIMove::pass(self.color.as_str()));
+        }
         if available_positions.is_empty() {
             // 无点可下，返回无需落子（上层不应写盘）
             return Ok(AIMove {
                 position: "none".to_string(),
-                color: "white".to_string(),
+                color: self.color.as_str().to_string(),
                 confidence: self.get_confidence_for_difficulty(),
+                quantum: false,
+                playouts: None,
             });
         }
 
-        // 可选策略：尽量避免与黑子位置重复（考虑双盘）
-        let mut candidate_positions = Vec::new();
-        'outer: for pos in &available_positions {
-            // 若该点在任一盘被黑子占据，则尽量跳过
-            for ch in game_state.board1.values() {
-                if ch.color == "black" && ch.position == *pos {
-                    continue 'outer;
-                }
+        let (best_position, playouts) = match self.engine {
+            AIEngine::Mcts => {
+                let (position, playouts) = self.search_position(game_state, &available_positions, limits);
+                (position, Some(playouts))
             }
-            for ch in game_state.board2.values() {
-                if ch.color == "black" && ch.position == *pos {
-                    continue 'outer;
-                }
-            }
-            candidate_positions.push(pos.clone());
-        }
-        if candidate_positions.is_empty() {
-            candidate_positions = available_positions;
+            AIEngine::Heuristic => (self.heuristic_position(game_state, &available_positions, limits), None),
+        };
+        if can_pass && is_eye(&root, best_position, self.color) {
+            debug!("AI quantum_move: {} fills an own eye, passing", best_position);
+            return Ok(AIMove { playouts, ..AIMove::pass(self.color.as_str()) });
         }
+        // 量子围棋的开局第一手总是量子落子（AI 执黑时由 AI 下出）
+        let opening = game_state.variant == Variant::Quantum && game_state.moves == 0;
+        let quantum = opening || self.should_play_quantum(game_state, best_position);
         
-        println!("AI white_quantum_move: candidate_positions={:?}", candidate_positions);
-
-        let best_position = self.greedy_position_selection(game_state, &candidate_positions, "white");
-        
-        println!("AI white_quantum_move: selected_position={}", best_position);
+        debug!("AI quantum_move: selected_position={}, quantum={}", best_position, quantum);
 
         Ok(AIMove {
-            position: best_position,
-            color: "white".to_string(),
+            position: best_position.to_string(),
+            color: self.color.as_str().to_string(),
             confidence: self.get_confidence_for_difficulty(),
+            quantum,
+            playouts,
         })
     }
 
-    /// 获取可用的落子位置（两个棋盘该点都未被占用）
-    fn get_available_positions(&self, game_state: &QuantumBoardState) -> Vec<String> {
+    /// 是否以量子落子下在 position：开局之后还有量子对可用，且没有等待纠缠的量子子；
+    /// 量子子在第二个棋盘上会变成对方颜色，因此只在两盘周围都没有棋子的点使用
+    fn should_play_quantum(&self, game_state: &QuantumBoardState, position: Point) -> bool {
+        game_state.variant == Variant::Quantum
+            && game_state.pairs_used > 0
+            && game_state.pairs_used < game_state.quantum_pairs
+            && game_state.pending_quantum.is_none()
+            && neighbor_count(game_state, position, Stone::Black) == 0
+            && neighbor_count(game_state, position, Stone::White) == 0
+    }
+
+    /// 获取可用的落子位置（所有棋盘该点都未被占用且不是自杀点）
+    fn get_available_positions(&self, game_state: &QuantumBoardState) -> Vec<Point> {
         let mut positions = Vec::new();
-        let model = game_state.model;
-
-        println!("AI get_available_positions: board1_size={}, board2_size={}", 
-                 game_state.board1.len(), game_state.board2.len());
-        println!("AI get_available_positions: board1 keys: {:?}", game_state.board1.keys().collect::<Vec<_>>());
-        println!("AI get_available_positions: board2 keys: {:?}", game_state.board2.keys().collect::<Vec<_>>());
-
-        for x in 1..=model {
-            for y in 1..=model {
-                let pos = format!("{x},{y}");
-                
-                // 检查该位置是否被任何棋子占用（黑子或白子）
-                let board1_occupied = game_state.board1.contains_key(&pos);
-                let board2_occupied = game_state.board2.contains_key(&pos);
-                
-                // 检查该位置是否已经有任何颜色的棋子
-                let has_any_chess = board1_occupied || board2_occupied;
-                
-                if !has_any_chess {
-                    positions.push(pos);
-                } else {
-                    println!("AI get_available_positions: position {} is occupied (board1: {}, board2: {})", 
-                             pos, board1_occupied, board2_occupied);
-                    if board1_occupied {
-                        println!("  Board1 chessman: {:?}", game_state.board1.get(&pos));
-                    }
-                    if board2_occupied {
-                        println!("  Board2 chessman: {:?}", game_state.board2.get(&pos));
-                    }
-                }
+
+        let sizes: Vec<usize> = game_state.boards.iter().map(Board::len).collect();
+        debug!("AI get_available_positions: board_sizes={:?}", sizes);
+        debug!("AI get_available_positions: boards: {:?}", game_state.boards);
+
+        for pos in game_state.boards[0].points() {
+            // 检查该位置在各个棋盘上是否被占用（黑子或白子）
+            let occupied: Vec<bool> = game_state.boards.iter().map(|board| board.is_occupied(pos)).collect();
+
+            // 检查该位置是否已经有任何颜色的棋子
+            let has_any_chess = occupied.contains(&true);
+
+            // 所有棋盘都不能是自杀点
+            let legal = !has_any_chess
+                && game_state
+                    .boards
+                    .iter()
+                    .all(|board| can_put_chess(board, pos, game_state.current_player))
+                && !self.violates_ko(game_state, pos);
+
+            if legal {
+                positions.push(pos);
+            } else {
+                debug!("AI get_available_positions: position {} is unavailable (occupied: {:?})", 
+                       pos, occupied);
             }
         }
         
-        println!("AI get_available_positions: available_positions={:?}", positions);
+        debug!("AI get_available_positions: available_positions={:?}", positions);
         positions
     }
 
-    /// 贪心策略选择位置（简化版本，确保AI能正常下棋）
-    fn greedy_position_selection(&self, game_state: &QuantumBoardState, positions: &[String], color: &str) -> String {
-        if positions.is_empty() {
-            // 理论不会触发，上游已处理
-            let center = (game_state.model + 1) / 2;
-            return format!("{},{}", center, center);
-        }
+    /// 落子后的各盘局面是否违反打劫规则
+    fn violates_ko(&self, game_state: &QuantumBoardState, position: Point) -> bool {
+        let boards = position_after(
+            &game_state.boards,
+            position,
+            game_state.current_player,
+            game_state.pending_quantum,
+        );
+        game_state
+            .ko_rule
+            .forbids(&game_state.position_history, position_hash(&boards))
+    }
 
-        // 简化：随机选择一个可用位置，避免总是选择同一个位置
-        let random_index = (positions.len() + game_state.board1.len() + game_state.board2.len()) % positions.len();
-        let selected_position = &positions[random_index];
+    /// 蒙特卡洛树搜索选择位置，返回选点与本次的模拟次数，搜索不到结果时取第一个候选点；
+    /// 思考时间取预算与 limits 中较短的一个。limits 中有可复用的树时在其上继续搜索，搜索后放回
+    fn search_position(&self, game_state: &QuantumBoardState, positions: &[Point], limits: &MoveLimits) -> (Point, u32) {
+        let mut budget = self.budget;
+        if let Some(time_limit) = limits.time_limit {
+            budget.time_limit = budget.time_limit.min(time_limit);
+        }
+        let mcts = Mcts::new(budget, game_state.komi).with_cancel(limits.cancel.clone());
+        let root = game_state.to_position();
+        let mut tree = match limits.tree.take().and_then(|tree| tree.advance(&root)) {
+            Some(tree) => {
+                debug!("AI search_position: reusing {:?}", tree);
+                tree
+            }
+            None => SearchTree::new(&root),
+        };
+        let result = mcts.search_from(&mut tree, positions, &mut limits.rng());
+        limits.tree.put(tree);
+        match result {
+            Some(result) => {
+                debug!("AI search_position: selected {} (win rate {:.3}, {}/{} visits)",
+                       result.position, result.win_rate, result.visits, result.playouts);
+                (result.position, result.playouts)
+            }
+            None => (positions[0], 0),
+        }
+    }
 
-        println!("AI greedy_position_selection: available positions: {:?}", positions);
-        println!("AI greedy_position_selection: selected position: {} (random index: {})", 
-                 selected_position, random_index);
+    /// 提示：为当前行棋方分析最多 count 个候选着法（总是使用 MCTS），按访问次数排序
+    pub fn analyze(&self, game_state: &QuantumBoardState, count: usize, limits: &MoveLimits) -> Vec<MoveHint> {
+        let mut budget = self.budget;
+        if let Some(time_limit) = limits.time_limit {
+            budget.time_limit = budget.time_limit.min(time_limit);
+        }
+        let positions = self.get_available_positions(game_state);
+        Mcts::new(budget, game_state.komi)
+            .with_cancel(limits.cancel.clone())
+            .analyze(&game_state.to_position(), &positions, count, &mut limits.rng())
+            .iter()
+            .map(MoveHint::from_candidate)
+            .collect()
+    }
 
-        selected_position.clone()
+    /// 启发式选点：按评分做 softmax 抽样，温度随难度变化，抽样使用 limits 的种子
+    fn heuristic_position(&self, game_state: &QuantumBoardState, positions: &[Point], limits: &MoveLimits) -> Point {
+        let color = game_state.current_player;
+        let scores: Vec<f64> = positions
+            .iter()
+            .map(|&p| self.evaluate_position(game_state, p, color))
+            .collect();
+        let best = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let temperature = self.difficulty.temperature();
+        let weights: Vec<f64> = scores.iter().map(|score| ((score - best) / temperature).exp()).collect();
+
+        let index = match WeightedIndex::new(&weights) {
+            Ok(distribution) => distribution.sample(&mut limits.rng()),
+            Err(_) => 0,
+        };
+        debug!("AI heuristic_position: selected {} (score {:.2}, best {:.2})",
+               positions[index], scores[index], best);
+        positions[index]
     }
 
     /// 评估某个位置的分数
-    fn evaluate_position(&self, game_state: &QuantumBoardState, position: &str, color: &str) -> f64 {
+    fn evaluate_position(&self, game_state: &QuantumBoardState, position: Point, color: Stone) -> f64 {
         let mut score = 0.0;
 
         // 1) 中心奖励
-        score += self.center_bonus(position, game_state.model);
+        score += self.center_bonus(position, game_state.width, game_state.height);
 
         // 2) 连接奖励
         score += self.connection_bonus(game_state, position, color);
@@ -194,142 +634,100 @@ impl SimpleQuantumAI {
         // 4) 攻击奖励
         score += self.attack_bonus(game_state, position, color);
 
-        // 5) 量子策略奖励（双盘）
+        // 5) 量子策略奖励（多盘）
         score += self.quantum_strategy_bonus(game_state, position, color);
 
+        // 6) 气与叫吃
+        score += self.liberty_bonus(game_state, position, color);
+
         score
     }
 
     /// 中心位置奖励：越靠近中心分越高
-    fn center_bonus(&self, position: &str, model: i32) -> f64 {
-        let parts: Vec<&str> = position.split(',').collect();
-        if parts.len() != 2 {
-            return 0.0;
-        }
-        let x: i32 = parts[0].parse().unwrap_or(0);
-        let y: i32 = parts[1].parse().unwrap_or(0);
+    fn center_bonus(&self, position: Point, width: i32, height: i32) -> f64 {
+        let (x, y) = (position.x as i32, position.y as i32);
 
-        let center = (model + 1) / 2;
-        let distance_from_center = ((x - center).abs() + (y - center).abs()) as f64;
+        let center_x = (width + 1) / 2;
+        let center_y = (height + 1) / 2;
+        let distance_from_center = ((x - center_x).abs() + (y - center_y).abs()) as f64;
 
         // 简单线性：离中心越近分越高
-        (model as f64 - distance_from_center) * 0.5
+        (width.max(height) as f64 - distance_from_center) * 0.5
     }
 
     /// 连接奖励：相邻同色
-    fn connection_bonus(&self, game_state: &QuantumBoardState, position: &str, color: &str) -> f64 {
-        let mut bonus = 0.0;
-        let neighbors = self.get_neighbors(position, game_state.model);
-
-        for neighbor in neighbors {
-            if let Some(ch) = game_state.board1.get(&neighbor) {
-                if ch.color == color {
-                    bonus += 2.0;
-                }
-            }
-            if let Some(ch) = game_state.board2.get(&neighbor) {
-                if ch.color == color {
-                    bonus += 2.0;
-                }
-            }
-        }
-        bonus
+    fn connection_bonus(&self, game_state: &QuantumBoardState, position: Point, color: Stone) -> f64 {
+        2.0 * neighbor_count(game_state, position, color) as f64
     }
 
     /// 防守奖励：相邻己方
-    fn defense_bonus(&self, game_state: &QuantumBoardState, position: &str, color: &str) -> f64 {
-        let mut bonus = 0.0;
-        let neighbors = self.get_neighbors(position, game_state.model);
-
-        for neighbor in neighbors {
-            if let Some(ch) = game_state.board1.get(&neighbor) {
-                if ch.color == color {
-                    bonus += 1.5;
-                }
-            }
-            if let Some(ch) = game_state.board2.get(&neighbor) {
-                if ch.color == color {
-                    bonus += 1.5;
-                }
-            }
-        }
-        bonus
+    fn defense_bonus(&self, game_state: &QuantumBoardState, position: Point, color: Stone) -> f64 {
+        1.5 * neighbor_count(game_state, position, color) as f64
     }
 
     /// 攻击奖励：相邻对方
-    fn attack_bonus(&self, game_state: &QuantumBoardState, position: &str, color: &str) -> f64 {
-        let mut bonus = 0.0;
-        let enemy_color = if color == "black" { "white" } else { "black" };
-        let neighbors = self.get_neighbors(position, game_state.model);
-
-        for neighbor in neighbors {
-            if let Some(ch) = game_state.board1.get(&neighbor) {
-                if ch.color == enemy_color {
-                    bonus += 2.5;
-                }
-            }
-            if let Some(ch) = game_state.board2.get(&neighbor) {
-                if ch.color == enemy_color {
-                    bonus += 2.5;
-                }
-            }
-        }
-        bonus
+    fn attack_bonus(&self, game_state: &QuantumBoardState, position: Point, color: Stone) -> f64 {
+        2.5 * neighbor_count(game_state, position, color.opponent()) as f64
     }
 
-    /// 量子策略奖励：分别评估双盘并取 max，若两盘都>0 额外加分
-    fn quantum_strategy_bonus(&self, game_state: &QuantumBoardState, position: &str, color: &str) -> f64 {
-        let b1 = self.evaluate_board_position(&game_state.board1, position, color, game_state.model);
-        let b2 = self.evaluate_board_position(&game_state.board2, position, color, game_state.model);
-        let mut score = b1.max(b2);
-        if b1 > 0.0 && b2 > 0.0 {
+    /// 量子策略奖励：分别评估各盘并取 max，若各盘都>0 额外加分
+    fn quantum_strategy_bonus(&self, game_state: &QuantumBoardState, position: Point, color: Stone) -> f64 {
+        let scores: Vec<f64> = game_state
+            .boards
+            .iter()
+            .map(|board| self.evaluate_board_position(board, position, color))
+            .collect();
+        let mut score = scores.iter().copied().fold(0.0, f64::max);
+        if scores.iter().all(|&s| s > 0.0) {
             score += 1.0;
         }
         score
     }
 
-    /// 评估单盘中的位置（邻接性）
-    fn evaluate_board_position(
-        &self,
-        board: &HashMap<String, Chessman>,
-        position: &str,
-        color: &str,
-        model: i32,
-    ) -> f64 {
+    /// 气与叫吃：提子、叫吃对方、救出己方被叫吃的棋加分，落子后自己只剩一口气扣分。
+    /// 按落子（含纠缠与提子）之后的各盘局面分别计算，取各盘平均
+    fn liberty_bonus(&self, game_state: &QuantumBoardState, position: Point, color: Stone) -> f64 {
+        let after = position_after(&game_state.boards, position, color, game_state.pending_quantum);
         let mut score = 0.0;
-        let neighbors = self.get_neighbors(position, model);
-        for neighbor in neighbors {
-            if let Some(chess) = board.get(&neighbor) {
-                if chess.color == color {
-                    score += 1.0;
-                } else {
-                    score += 0.5; // 与异色相邻，可能形成战术机会
-                }
+        for (before, after) in game_state.boards.iter().zip(&after) {
+            let captured = (before.len() + 1).saturating_sub(after.len());
+            score += CAPTURE_WEIGHT * captured as f64;
+
+            // 纠缠后该点可能换成了对方的棋子，只评估仍为己方的情形
+            if after.stone(position) != Some(color) {
+                continue;
+            }
+            let liberties = after.liberties(&after.group(position));
+            if liberties == 1 {
+                score -= SELF_ATARI_PENALTY;
+            } else {
+                let rescued = adjacent_groups(before, position, color)
+                    .iter()
+                    .filter(|group| before.liberties(group) == 1)
+                    .count();
+                score += RESCUE_WEIGHT * rescued as f64;
             }
-        }
-        score
-    }
 
-    /// 获取相邻的上下左右
-    fn get_neighbors(&self, position: &str, model: i32) -> Vec<String> {
-        let parts: Vec<&str> = position.split(',').collect();
-        if parts.len() != 2 {
-            return Vec::new();
+            let ataris = adjacent_groups(after, position, color.opponent())
+                .iter()
+                .filter(|group| after.liberties(group) == 1)
+                .count();
+            score += ATARI_WEIGHT * ataris as f64;
         }
-        let x: i32 = parts[0].parse().unwrap_or(0);
-        let y: i32 = parts[1].parse().unwrap_or(0);
-
-        let mut neighbors = Vec::new();
-        let directions = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+        score / game_state.boards.len() as f64
+    }
 
-        for (dx, dy) in directions {
-            let nx = x + dx;
-            let ny = y + dy;
-            if nx >= 1 && nx <= model && ny >= 1 && ny <= model {
-                neighbors.push(format!("{nx},{ny}"));
+    /// 评估单盘中的位置（邻接性）
+    fn evaluate_board_position(&self, board: &Board, position: Point, color: Stone) -> f64 {
+        let mut score = 0.0;
+        for neighbor in board.neighbors(position) {
+            match board.stone(neighbor) {
+                Some(stone) if stone == color => score += 1.0,
+                Some(_) => score += 0.5, // 与异色相邻，可能形成战术机会
+                None => {}
             }
         }
-        neighbors
+        score
     }
 
     /// 难度对应置信度
@@ -342,48 +740,63 @@ impl SimpleQuantumAI {
     }
 }
 
-/// 从 RoomInfo 转为 QuantumBoardState
-pub fn room_info_to_quantum_board_state(room_info: &RoomInfo) -> QuantumBoardState {
-    // 解析 board 字段
-    let board_data = &room_info.board;
-    let mut board1 = HashMap::new();
-    let mut board2 = HashMap::new();
-
-    println!("Converting room_info to quantum board state...");
-    println!("Raw board data: {:?}", board_data);
-
-    if let Some(board_obj) = board_data.as_object() {
-        println!("Board is an object with {} entries", board_obj.len());
-        for (_pos, chessman_data) in board_obj {
-            if let Some(chessman) = chessman_data.as_object() {
-                // 兼容 color / type 两种字段名
-                let position_opt = chessman.get("position").and_then(|v| v.as_str());
-                let color_opt = chessman
-                    .get("color")
-                    .and_then(|v| v.as_str())
-                    .or_else(|| chessman.get("type").and_then(|v| v.as_str()));
-                let brother_opt = chessman.get("brother").and_then(|v| v.as_str());
-
-                if let (Some(position), Some(color), Some(brother)) = (position_opt, color_opt, brother_opt) {
-                    let chessman1 = Chessman {
-                        position: position.to_string(),
-                        color: color.to_string(),
-                        brother: brother.to_string(),
-                    };
-                    let chessman2 = Chessman {
-                        position: brother.to_string(),
-                        color: color.to_string(),
-                        brother: position.to_string(),
-                    };
-                    board1.insert(position.to_string(), chessman1);
-                    board2.insert(brother.to_string(), chessman2);
-                }
-            }
+/// 与 position 相邻的 color 棋块（去重）
+fn adjacent_groups(board: &Board, position: Point, color: Stone) -> Vec<Vec<Point>> {
+    let mut groups: Vec<Vec<Point>> = Vec::new();
+    for neighbor in board.neighbors(position) {
+        if board.stone(neighbor) == Some(color) && !groups.iter().any(|group| group.contains(&neighbor)) {
+            groups.push(board.group(neighbor));
         }
     }
+    groups
+}
+
+/// 所有棋盘上与 position 相邻的 color 棋子数
+fn neighbor_count(game_state: &QuantumBoardState, position: Point, color: Stone) -> usize {
+    game_state
+        .boards
+        .iter()
+        .map(|board| {
+            board
+                .neighbors(position)
+                .filter(|&neighbor| board.stone(neighbor) == Some(color))
+                .count()
+        })
+        .sum()
+}
 
-    println!("Final board1: {:?}", board1);
-    println!("Final board2: {:?}", board2);
+/// 对方刚停一手，按当前盘面数子（不移除死子）color 已经获胜：AI 也停一手，进入数子
+pub fn wins_by_passing(position: &QuantumPosition, color: Stone, rule_set: RuleSet, komi: f64) -> bool {
+    position.passes > 0
+        && position.pass(color.as_str()).is_ok()
+        && score_position(position, rule_set, komi).winner == color.as_str()
+}
+
+/// AI 是否同意对方标记的死子：按 AI 自己的判断（不移除死子直接数子）本该获胜时，
+/// 只有标记后仍然获胜才同意
+pub fn accepts_dead_stones(
+    position: &QuantumPosition,
+    dead_stones: &[String],
+    color: Stone,
+    rule_set: RuleSet,
+    komi: f64,
+) -> bool {
+    let marked = score_with_dead_stones(position, dead_stones, rule_set, komi);
+    let estimate = score_position(position, rule_set, komi);
+    estimate.winner != color.as_str() || marked.winner == color.as_str()
+}
+
+/// 从 RoomInfo 转为 QuantumBoardState
+pub fn room_info_to_quantum_board_state(room_info: &RoomInfo) -> QuantumBoardState {
+    // 解析 board 字段（兼容版本 2 的 boards、board1/board2、条目数组与平铺格式）
+    debug!("Converting room_info to quantum board state...");
+    debug!("Raw board data: {:?}", room_info.board);
+    let (width, height) = normalize_size(room_info.board_size());
+    let variant = Variant::parse(&room_info.variant).unwrap_or_default();
+    let boards = parse_room_boards(&room_info.board, width, height, variant);
+    let (pairs_used, pending_quantum) = quantum_progress(&room_info.chessman_records, variant);
+
+    debug!("Final boards: {:?}", boards);
 
     // 与"AI 执白"的规则对齐：
     // 优先使用存储的 phase 字段，如果没有则通过 moves % 2 推导
@@ -395,45 +808,47 @@ pub fn room_info_to_quantum_board_state(room_info: &RoomInfo) -> QuantumBoardSta
             "Entanglement" => QuantumPhase::Entanglement,
             _ => {
                 // 如果存储的phase无效，回退到moves%2推导
-                if room_info.moves % 2 == 0 {
-                    QuantumPhase::BlackQuantum
-                } else {
-                    QuantumPhase::WhiteQuantum
-                }
+                phase_from_moves(room_info.moves, room_info.handicap)
             }
         }
     } else {
         // 没有存储的phase，使用moves%2推导
-        if room_info.moves % 2 == 0 {
-            QuantumPhase::BlackQuantum
-        } else {
-            QuantumPhase::WhiteQuantum
-        }
+        phase_from_moves(room_info.moves, room_info.handicap)
     };
 
     // 添加调试信息
-    println!("Quantum phase calculation: stored_phase={:?}, moves={}, moves%2={}, final_phase={:?}", 
-             room_info.phase, room_info.moves, room_info.moves % 2, quantum_phase);
+    debug!("Quantum phase calculation: stored_phase={:?}, moves={}, moves%2={}, final_phase={:?}", 
+           room_info.phase, room_info.moves, room_info.moves % 2, quantum_phase);
     
     // 添加更详细的调试信息
-    println!("Quantum phase details: room_info.phase={:?}, room_info.moves={}, room_info.round={}", 
-             room_info.phase, room_info.moves, room_info.round);
+    debug!("Quantum phase details: room_info.phase={:?}, room_info.moves={}, room_info.round={}", 
+           room_info.phase, room_info.moves, room_info.round);
 
     // 当前轮到谁：与 phase 对齐
     let current_player = match quantum_phase {
-        QuantumPhase::BlackQuantum => "black".to_string(),
-        QuantumPhase::WhiteQuantum => "white".to_string(),
-        QuantumPhase::Entanglement => room_info.round.clone(), // 如果从外部载入就尊重原值
+        QuantumPhase::BlackQuantum => Stone::Black,
+        QuantumPhase::WhiteQuantum => Stone::White,
+        // 如果从外部载入就尊重原值
+        QuantumPhase::Entanglement => Stone::parse(&room_info.round).unwrap_or(Stone::Black),
     };
 
-    println!("Current player determined: {:?}", current_player);
+    debug!("Current player determined: {:?}", current_player);
 
     QuantumBoardState {
-        board1,
-        board2,
+        boards,
         current_player,
         quantum_phase,
-        model: normalize_model(room_info.model),
+        width,
+        height,
+        ko_rule: KoRule::parse(&room_info.ko_rule).unwrap_or_default(),
+        position_history: parse_history(&room_info.position_history),
+        variant,
+        quantum_pairs: room_info.quantum_pairs,
+        pairs_used,
+        pending_quantum,
+        moves: room_info.moves,
+        handicap: room_info.handicap,
+        komi: room_info.komi,
     }
 }
 
@@ -442,68 +857,15 @@ pub fn create_quantum_state_from_board_state(
     board_state: &serde_json::Value,
     room_info: &RoomInfo,
 ) -> QuantumBoardState {
-    let mut board1 = HashMap::new();
-    let mut board2 = HashMap::new();
-
-    println!("Creating quantum state from provided board state: {:?}", board_state);
-
-    if let Some(board_state_obj) = board_state.as_object() {
-        // 解析 board1
-        if let Some(board1_data) = board_state_obj.get("board1") {
-            if let Some(board1_obj) = board1_data.as_object() {
-                for (pos, chessman_data) in board1_obj {
-                    if let Some(chessman) = chessman_data.as_object() {
-                        let position = pos.clone();
-                        let color = chessman
-                            .get("type")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("black")
-                            .to_string();
-                        let brother = chessman
-                            .get("brother")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or(pos)
-                            .to_string();
-
-                        let chessman = Chessman {
-                            position: position.clone(),
-                            color: color.clone(),
-                            brother: brother.clone(),
-                        };
-                        board1.insert(position, chessman);
-                    }
-                }
-            }
-        }
+    debug!("Creating quantum state from provided board state: {:?}", board_state);
 
-        // 解析 board2
-        if let Some(board2_data) = board_state_obj.get("board2") {
-            if let Some(board2_obj) = board2_data.as_object() {
-                for (pos, chessman_data) in board2_obj {
-                    if let Some(chessman) = chessman_data.as_object() {
-                        let position = pos.clone();
-                        let color = chessman
-                            .get("type")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("black")
-                            .to_string();
-                        let brother = chessman
-                            .get("brother")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or(pos)
-                            .to_string();
-
-                        let chessman = Chessman {
-                            position: position.clone(),
-                            color: color.clone(),
-                            brother: brother.clone(),
-                        };
-                        board2.insert(position, chessman);
-                    }
-                }
-            }
-        }
+    let (width, height) = normalize_size(room_info.board_size());
+    let variant = Variant::parse(&room_info.variant).unwrap_or_default();
+    let boards = parse_room_boards(board_state, width, height, variant);
+    // 旧客户端只提交 board1/board2，与多盘房间的棋盘数不符时以数据库为准
+    let room_boards = parse_room_boards(&room_info.board, width, height, variant);
 
+    if let Some(board_state_obj) = board_state.as_object().filter(|_| boards.len() == room_boards.len()) {
         // 获取其他状态信息
         let sub_status = board_state_obj
             .get("subStatus")
@@ -515,97 +877,116 @@ pub fn create_quantum_state_from_board_state(
             .and_then(|v| v.as_i64())
             .unwrap_or(0) as i32;
 
-        println!("Parsed board state - board1: {:?}, board2: {:?}, subStatus: {}, moves: {}", 
-                 board1, board2, sub_status, moves);
+        debug!("Parsed board state - boards: {:?}, subStatus: {}, moves: {}", 
+               boards, sub_status, moves);
 
-        // 根据subStatus和moves确定量子阶段
+        // 根据subStatus和moves确定量子阶段（经典围棋没有纠缠阶段）
+        let (pairs_used, pending_quantum) = quantum_progress(&room_info.chessman_records, variant);
         let quantum_phase = match sub_status {
             "black" => QuantumPhase::BlackQuantum,
             "white" => QuantumPhase::WhiteQuantum,
-            "common" => QuantumPhase::Entanglement,
-            _ => {
-                if moves % 2 == 0 {
-                    QuantumPhase::BlackQuantum
-                } else {
-                    QuantumPhase::WhiteQuantum
-                }
-            }
+            "common" if variant == Variant::Quantum => QuantumPhase::Entanglement,
+            _ => phase_from_moves(moves, room_info.handicap),
         };
 
         let current_player = match quantum_phase {
-            QuantumPhase::BlackQuantum => "black".to_string(),
-            QuantumPhase::WhiteQuantum => "white".to_string(),
-            QuantumPhase::Entanglement => "black".to_string(), // 纠缠阶段后轮到黑方
+            QuantumPhase::BlackQuantum => Stone::Black,
+            QuantumPhase::WhiteQuantum => Stone::White,
+            QuantumPhase::Entanglement => Stone::Black, // 纠缠阶段后轮到黑方
         };
 
         QuantumBoardState {
-            board1,
-            board2,
+            boards,
             current_player,
             quantum_phase,
-            model: normalize_model(room_info.model),
+            width,
+            height,
+            ko_rule: KoRule::parse(&room_info.ko_rule).unwrap_or_default(),
+            position_history: parse_history(&room_info.position_history),
+            variant,
+            quantum_pairs: room_info.quantum_pairs,
+            pairs_used,
+            pending_quantum,
+            moves: room_info.moves,
+            handicap: room_info.handicap,
+            komi: room_info.komi,
         }
     } else {
         // 如果无法解析，回退到数据库状态
-        println!("Failed to parse board state, falling back to database state");
+        debug!("Failed to parse board state, falling back to database state");
         room_info_to_quantum_board_state(room_info)
     }
 }
 
-/// 仅允许 9/13/19，非法值回退为 19
-fn normalize_model(model: i32) -> i32 {
-    match model {
-        9 | 13 | 19 => model,
-        _ => 19,
+/// 按手数推导量子阶段（让子局白方先行）
+fn phase_from_moves(moves: i32, handicap: i32) -> QuantumPhase {
+    let first = first_to_move(handicap);
+    let to_move = if moves % 2 == 0 { first } else { first.opponent() };
+    match to_move {
+        Stone::Black => QuantumPhase::BlackQuantum,
+        Stone::White => QuantumPhase::WhiteQuantum,
     }
 }
 
+/// 支持范围内的任意长宽（含矩形）原样保留，超出范围的旧数据回退为 19 路
+fn normalize_size((width, height): (i32, i32)) -> (i32, i32) {
+    if valid_dimensions(width, height) { (width, height) } else { (19, 19) }
+}
+
 /// AI 对战房间
+#[allow(dead_code)]
 pub struct AIRoom {
     pub room_id: Uuid,
-    pub ai_player: SimpleQuantumAI,
+    pub ai_player: Box<dyn MoveGenerator>,
     pub human_player_id: Uuid,
     pub game_state: QuantumBoardState,
 }
 
+#[allow(dead_code)]
 impl AIRoom {
     /// 旧构造：默认 19x19
     pub fn new(room_id: Uuid, human_player_id: Uuid, difficulty: AIDifficulty) -> Self {
         Self::new_with_model(room_id, human_player_id, difficulty, 19)
     }
 
-    /// 新构造：可选 9/13/19
+    /// 新构造：方形棋盘，边长在支持范围内任选
     pub fn new_with_model(
         room_id: Uuid,
         human_player_id: Uuid,
         difficulty: AIDifficulty,
         model: i32,
     ) -> Self {
-        let model = normalize_model(model);
+        let (width, height) = normalize_size((model, model));
         Self {
             room_id,
-            ai_player: SimpleQuantumAI::new(difficulty),
+            ai_player: Box::new(SimpleQuantumAI::new(difficulty)),
             human_player_id,
             game_state: QuantumBoardState {
-                board1: HashMap::new(),
-                board2: HashMap::new(),
-                current_player: "black".to_string(), // 开局黑方先手（玩家）
+                boards: vec![Board::new(width, height); DEFAULT_BOARDS],
+                current_player: Stone::Black, // 开局黑方先手（玩家）
                 quantum_phase: QuantumPhase::BlackQuantum,
-                model,
+                width,
+                height,
+                ko_rule: KoRule::default(),
+                position_history: Vec::new(),
+                variant: Variant::default(),
+                quantum_pairs: 1,
+                pairs_used: 0,
+                pending_quantum: None,
+                moves: 0,
+                handicap: 0,
+                komi: RuleSet::default().default_komi(),
             },
         }
     }
 
     /// 让 AI 行动（只在 WhiteQuantum 或 Entanglement 有意义）
     pub fn make_ai_move(&mut self) -> Result<AIMove, Box<dyn Error + Send + Sync>> {
-        let ai_move = self.ai_player.get_next_move(&self.game_state)?;
+        let ai_move = self.ai_player.generate(&self.game_state, &MoveLimits::default())?;
 
         // 只有当坐标为合法点，且颜色为 "white"/"black" 时才写盘
-        let is_real_move = (ai_move.color == "white" || ai_move.color == "black")
-            && ai_move.position.contains(',');
-
-        if is_real_move {
-            self.apply_ai_move(&ai_move);
+        if let (Some(position), Some(color)) = (Point::parse(&ai_move.position), Stone::parse(&ai_move.color)) {
+            self.apply_ai_move(position, color);
         }
         // 无论是否写盘，都推进阶段（纠缠阶段也要推进回黑方）
         self.update_quantum_phase();
@@ -613,64 +994,55 @@ impl AIRoom {
         Ok(ai_move)
     }
 
-    /// 应用 AI 落子（确保两个棋盘完全同步）
-    fn apply_ai_move(&mut self, ai_move: &AIMove) {
-        // 创建两个完全相同的棋子，确保两个棋盘同步
-        let chessman1 = Chessman {
-            position: ai_move.position.clone(),
-            color: ai_move.color.clone(),
-            brother: ai_move.position.clone(), // AI落子时，brother设置为自身位置
-        };
-        
-        let chessman2 = Chessman {
-            position: ai_move.position.clone(),
-            color: ai_move.color.clone(),
-            brother: ai_move.position.clone(), // AI落子时，brother设置为自身位置
-        };
+    /// 应用 AI 落子（确保所有棋盘完全同步）
+    fn apply_ai_move(&mut self, position: Point, color: Stone) {
+        // AI落子时，brother设置为自身位置
+        let cell = Cell { stone: color, brother: position };
 
-        // 在两个棋盘的相同位置插入相同的棋子，确保完全同步
-        self.game_state
-            .board1
-            .insert(ai_move.position.clone(), chessman1);
-        self.game_state
-            .board2
-            .insert(ai_move.position.clone(), chessman2);
+        // 在每个棋盘的相同位置插入相同的棋子，确保完全同步
+        for board in &mut self.game_state.boards {
+            board.set(position, cell);
+        }
             
-        println!("AI move applied: position={}, color={}, both boards synchronized", 
-                 ai_move.position, ai_move.color);
+        debug!("AI move applied: position={}, color={}, all boards synchronized", 
+               position, color.as_str());
     }
 
     /// 推进量子阶段（黑方 → 白方 → 纠缠 → 黑方）
     fn update_quantum_phase(&mut self) {
         let old_phase = self.game_state.quantum_phase.clone();
-        let old_player = self.game_state.current_player.clone();
+        let old_player = self.game_state.current_player;
         
         self.game_state.quantum_phase = match self.game_state.quantum_phase {
             QuantumPhase::BlackQuantum => {
-                self.game_state.current_player = "white".to_string();
+                self.game_state.current_player = Stone::White;
                 QuantumPhase::WhiteQuantum
             }
+            QuantumPhase::WhiteQuantum if self.game_state.variant == Variant::Classical => {
+                // 经典围棋没有纠缠阶段，直接轮到黑方
+                self.game_state.current_player = Stone::Black;
+                QuantumPhase::BlackQuantum
+            }
             QuantumPhase::WhiteQuantum => {
                 // 白方下完进入纠缠阶段
-                self.game_state.current_player = "white".to_string();
+                self.game_state.current_player = Stone::White;
                 QuantumPhase::Entanglement
             }
             QuantumPhase::Entanglement => {
                 // 纠缠阶段完毕，轮到黑方
-                self.game_state.current_player = "black".to_string();
+                self.game_state.current_player = Stone::Black;
                 QuantumPhase::BlackQuantum
             }
         };
         
-        println!("AIRoom: Quantum phase updated: {:?} -> {:?}, player: {} -> {}", 
-                 old_phase, self.game_state.quantum_phase, old_player, self.game_state.current_player);
+        debug!("AIRoom: Quantum phase updated: {:?} -> {:?}, player: {} -> {}", 
+               old_phase, self.game_state.quantum_phase, old_player.as_str(), self.game_state.current_player.as_str());
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
 
     #[test]
     fn test_ai_creation() {
@@ -682,27 +1054,44 @@ mod tests {
     #[test]
     fn test_quantum_board_state_creation() {
         let state = QuantumBoardState {
-            board1: HashMap::new(),
-            board2: HashMap::new(),
-            current_player: "black".to_string(),
+            boards: vec![Board::new(9, 9); 2],
+            current_player: Stone::Black,
             quantum_phase: QuantumPhase::BlackQuantum,
-            model: 9,
+            width: 9,
+            height: 9,
+            ko_rule: KoRule::Simple,
+            position_history: Vec::new(),
+            variant: Variant::Quantum,
+            quantum_pairs: 1,
+            pairs_used: 0,
+            pending_quantum: None,
+            moves: 0,
+            handicap: 0,
+            komi: 7.5,
         };
-        assert_eq!(state.current_player, "black");
-        assert_eq!(state.model, 9);
-        assert_eq!(state.board1.len(), 0);
-        assert_eq!(state.board2.len(), 0);
+        assert_eq!(state.current_player, Stone::Black);
+        assert_eq!(state.width, 9);
+        assert!(state.boards.iter().all(|board| board.is_empty()));
     }
 
     #[test]
     fn test_ai_get_next_move_waits_on_black_phase() {
         let ai = SimpleQuantumAI::new(AIDifficulty::Beginner);
         let state = QuantumBoardState {
-            board1: HashMap::new(),
-            board2: HashMap::new(),
-            current_player: "black".to_string(),
+            boards: vec![Board::new(9, 9); 2],
+            current_player: Stone::Black,
             quantum_phase: QuantumPhase::BlackQuantum,
-            model: 9,
+            width: 9,
+            height: 9,
+            ko_rule: KoRule::Simple,
+            position_history: Vec::new(),
+            variant: Variant::Quantum,
+            quantum_pairs: 1,
+            pairs_used: 0,
+            pending_quantum: None,
+            moves: 0,
+            handicap: 0,
+            komi: 7.5,
         };
 
         let result = ai.get_next_move(&state).unwrap();
@@ -715,11 +1104,20 @@ mod tests {
     fn test_ai_get_next_move_white_phase_returns_white_move() {
         let ai = SimpleQuantumAI::new(AIDifficulty::Beginner);
         let state = QuantumBoardState {
-            board1: HashMap::new(),
-            board2: HashMap::new(),
-            current_player: "white".to_string(),
+            boards: vec![Board::new(9, 9); 2],
+            current_player: Stone::White,
             quantum_phase: QuantumPhase::WhiteQuantum,
-            model: 9,
+            width: 9,
+            height: 9,
+            ko_rule: KoRule::Simple,
+            position_history: Vec::new(),
+            variant: Variant::Quantum,
+            quantum_pairs: 1,
+            pairs_used: 0,
+            pending_quantum: None,
+            moves: 0,
+            handicap: 0,
+            komi: 7.5,
         };
 
         let mv = ai.get_next_move(&state).unwrap();
@@ -732,11 +1130,20 @@ mod tests {
     fn test_entanglement_phase_no_move() {
         let ai = SimpleQuantumAI::new(AIDifficulty::Advanced);
         let state = QuantumBoardState {
-            board1: HashMap::new(),
-            board2: HashMap::new(),
-            current_player: "white".to_string(),
+            boards: vec![Board::new(13, 13), Board::new(9, 9)],
+            current_player: Stone::White,
             quantum_phase: QuantumPhase::Entanglement,
-            model: 13,
+            width: 13,
+            height: 13,
+            ko_rule: KoRule::Simple,
+            position_history: Vec::new(),
+            variant: Variant::Quantum,
+            quantum_pairs: 1,
+            pairs_used: 0,
+            pending_quantum: None,
+            moves: 0,
+            handicap: 0,
+            komi: 7.5,
         };
 
         let mv = ai.get_next_move(&state).unwrap();
@@ -751,16 +1158,17 @@ mod tests {
         let human = Uuid::new_v4();
 
         let r9 = AIRoom::new_with_model(room_id, human, AIDifficulty::Beginner, 9);
-        assert_eq!(r9.game_state.model, 9);
+        assert_eq!(r9.game_state.width, 9);
 
         let r13 = AIRoom::new_with_model(room_id, human, AIDifficulty::Beginner, 13);
-        assert_eq!(r13.game_state.model, 13);
+        assert_eq!(r13.game_state.width, 13);
 
-        let r19 = AIRoom::new_with_model(room_id, human, AIDifficulty::Beginner, 19);
-        assert_eq!(r19.game_state.model, 19);
+        // 教学用小棋盘保留原尺寸
+        let r5 = AIRoom::new_with_model(room_id, human, AIDifficulty::Beginner, 5);
+        assert_eq!((r5.game_state.width, r5.game_state.height), (5, 5));
 
-        let r_bad = AIRoom::new_with_model(room_id, human, AIDifficulty::Beginner, 7);
-        assert_eq!(r_bad.game_state.model, 19); // 回退
+        let r_bad = AIRoom::new_with_model(room_id, human, AIDifficulty::Beginner, 40);
+        assert_eq!(r_bad.game_state.width, 19); // 回退
     }
 
     #[test]
@@ -788,6 +1196,311 @@ mod tests {
         assert_eq!(room.game_state.quantum_phase, QuantumPhase::BlackQuantum);
     }
 
+    #[test]
+    fn test_classical_phase_cycle_skips_entanglement() {
+        let mut room = AIRoom::new_with_model(Uuid::new_v4(), Uuid::new_v4(), AIDifficulty::Beginner, 9);
+        room.game_state.variant = Variant::Classical;
+
+        room.make_ai_move().unwrap();
+        let mv = room.make_ai_move().unwrap();
+        assert_eq!(mv.color, "white");
+        assert_eq!(room.game_state.quantum_phase, QuantumPhase::BlackQuantum);
+        assert_eq!(room.game_state.current_player, Stone::Black);
+    }
+
+    #[test]
+    fn test_ai_plays_quantum_on_quiet_point() {
+        let ai = SimpleQuantumAI::new(AIDifficulty::Intermediate);
+        let mut room = AIRoom::new_with_model(Uuid::new_v4(), Uuid::new_v4(), AIDifficulty::Beginner, 9);
+        let state = &mut room.game_state;
+        for board in &mut state.boards {
+            board.set(Point::new(5, 5), Cell { stone: Stone::Black, brother: Point::new(5, 5) });
+        }
+        state.quantum_pairs = 2;
+        state.pairs_used = 1;
+
+        assert!(ai.should_play_quantum(state, Point::new(2, 2)));
+        assert!(!ai.should_play_quantum(state, Point::new(5, 6)));
+
+        // 等待纠缠或量子对用完时只能普通落子
+        state.pending_quantum = Some(Point::new(5, 5));
+        assert!(!ai.should_play_quantum(state, Point::new(2, 2)));
+        state.pending_quantum = None;
+        state.pairs_used = 2;
+        assert!(!ai.should_play_quantum(state, Point::new(2, 2)));
+    }
+
+    #[test]
+    fn test_available_positions_skip_ko_recapture() {
+        let mut position = crate::rules::QuantumPosition::new(9, 9);
+        for mv in ["9,9", "9,8", "2,1", "3,1", "1,2", "2,2", "2,3", "3,3", "8,8", "4,2", "3,2"] {
+            let color = position.next_color();
+            position = position.play(mv, color).unwrap().position;
+        }
+
+        let ai = SimpleQuantumAI::new(AIDifficulty::Intermediate);
+        let state = QuantumBoardState {
+            boards: position.boards.clone(),
+            current_player: Stone::White,
+            quantum_phase: QuantumPhase::WhiteQuantum,
+            width: 9,
+            height: 9,
+            ko_rule: KoRule::Simple,
+            position_history: position.history.clone(),
+            variant: Variant::Quantum,
+            quantum_pairs: 1,
+            pairs_used: 0,
+            pending_quantum: None,
+            moves: position.moves,
+            handicap: 0,
+            komi: 7.5,
+        };
+
+        let positions = ai.get_available_positions(&state);
+        assert!(!positions.contains(&Point::new(2, 2)));
+        assert!(positions.contains(&Point::new(7, 7)));
+    }
+
+    #[test]
+    fn test_available_positions_on_rectangular_board() {
+        let ai = SimpleQuantumAI::new(AIDifficulty::Beginner);
+        let state = QuantumBoardState {
+            boards: vec![Board::new(7, 5); 2],
+            current_player: Stone::White,
+            quantum_phase: QuantumPhase::WhiteQuantum,
+            width: 7,
+            height: 5,
+            ko_rule: KoRule::Simple,
+            position_history: Vec::new(),
+            variant: Variant::Quantum,
+            quantum_pairs: 1,
+            pairs_used: 0,
+            pending_quantum: None,
+            moves: 0,
+            handicap: 0,
+            komi: 7.5,
+        };
+
+        let positions = ai.get_available_positions(&state);
+        assert_eq!(positions.len(), 35);
+        assert!(positions.iter().all(|p| p.x <= 7 && p.y <= 5));
+    }
+
+    fn classical_state(stones: &[(u8, u8, Stone)]) -> QuantumBoardState {
+        let mut board = Board::new(9, 9);
+        for &(x, y, stone) in stones {
+            let p = Point::new(x, y);
+            board.set(p, Cell { stone, brother: p });
+        }
+        QuantumBoardState {
+            boards: vec![board],
+            current_player: Stone::White,
+            quantum_phase: QuantumPhase::WhiteQuantum,
+            width: 9,
+            height: 9,
+            ko_rule: KoRule::Simple,
+            position_history: Vec::new(),
+            variant: Variant::Classical,
+            quantum_pairs: 0,
+            pairs_used: 0,
+            pending_quantum: None,
+            moves: 1,
+            handicap: 0,
+            komi: 7.5,
+        }
+    }
+
+    #[test]
+    fn test_heuristic_captures_and_avoids_self_atari() {
+        // 黑子 (5,5) 只剩 (5,6) 一口气
+        let state = classical_state(&[
+            (5, 5, Stone::Black),
+            (4, 5, Stone::White),
+            (6, 5, Stone::White),
+            (5, 4, Stone::White),
+            // 白子下在 (1,2) 会被 (1,1)/(2,2)/(1,3) 的黑子叫吃
+            (2, 2, Stone::Black),
+            (1, 3, Stone::Black),
+        ]);
+        let ai = SimpleQuantumAI::new(AIDifficulty::Advanced).with_engine(AIEngine::Heuristic);
+        assert!(ai.liberty_bonus(&state, Point::new(5, 6), Stone::White) >= CAPTURE_WEIGHT);
+        assert!(ai.liberty_bonus(&state, Point::new(1, 2), Stone::White) < 0.0);
+
+        for _ in 0..20 {
+            let mv = ai.get_next_move(&state).unwrap();
+            assert_eq!(mv.position, "5,6");
+        }
+    }
+
+    #[test]
+    fn test_heuristic_spread_depends_on_difficulty() {
+        let state = classical_state(&[]);
+        let positions = SimpleQuantumAI::new(AIDifficulty::Beginner).get_available_positions(&state);
+        let distinct_picks = |difficulty: AIDifficulty| {
+            let ai = SimpleQuantumAI::new(difficulty).with_engine(AIEngine::Heuristic);
+            let mut picks: Vec<Point> = (0..40).map(|_| ai.heuristic_position(&state, &positions, &MoveLimits::default())).collect();
+            picks.sort_by_key(|p| (p.x, p.y));
+            picks.dedup();
+            picks.len()
+        };
+        assert!(distinct_picks(AIDifficulty::Beginner) > distinct_picks(AIDifficulty::Advanced));
+    }
+
+    #[test]
+    fn test_ai_playing_black_makes_opening_quantum_move() {
+        let mut state = classical_state(&[]);
+        state.variant = Variant::Quantum;
+        state.boards = vec![Board::new(9, 9); 2];
+        state.quantum_pairs = 1;
+        state.moves = 0;
+        state.current_player = Stone::Black;
+        state.quantum_phase = QuantumPhase::BlackQuantum;
+
+        let ai = SimpleQuantumAI::new(AIDifficulty::Beginner)
+            .with_engine(AIEngine::Heuristic)
+            .with_color(Stone::Black);
+        let mv = ai.get_next_move(&state).unwrap();
+        assert_eq!(mv.color, "black");
+        assert!(mv.quantum);
+        let position = state.to_position().play_quantum(&mv.position, &mv.color).unwrap().position;
+        assert_eq!(position.pending_quantum, Point::parse(&mv.position));
+
+        // 轮到白方（玩家）时执黑的 AI 等待
+        state.current_player = Stone::White;
+        state.quantum_phase = QuantumPhase::WhiteQuantum;
+        assert_eq!(ai.get_next_move(&state).unwrap().color, "waiting");
+    }
+
+    /// 5×5 经典局面：白棋占满全盘，只留 (1,1) 与 (5,5) 两个眼，轮到黑方
+    fn white_with_two_eyes() -> QuantumPosition {
+        let mut position = QuantumPosition { variant: Variant::Classical, quantum_pairs: 0, ..QuantumPosition::with_boards(5, 5, 1) };
+        for p in position.boards[0].points().collect::<Vec<_>>() {
+            if p != Point::new(1, 1) && p != Point::new(5, 5) {
+                position.boards[0].set(p, Cell { stone: Stone::White, brother: p });
+            }
+        }
+        position.history = vec![position_hash(&position.boards)];
+        position.moves = 2;
+        position
+    }
+
+    #[test]
+    fn test_ai_passes_without_useful_moves() {
+        // 黑方无点可下，白方只剩自己的眼
+        let position = white_with_two_eyes();
+        for engine in [AIEngine::Mcts, AIEngine::Heuristic] {
+            let black = SimpleQuantumAI::new(AIDifficulty::Beginner).with_engine(engine).with_color(Stone::Black);
+            let mv = black.get_next_move(&QuantumBoardState::from_position(&position, 0.5)).unwrap();
+            assert!(mv.is_pass(), "{:?}", mv);
+
+            let after_pass = position.pass("black").unwrap().position;
+            let white = SimpleQuantumAI::new(AIDifficulty::Beginner).with_engine(engine).with_color(Stone::White);
+            let mv = white.get_next_move(&QuantumBoardState::from_position(&after_pass, 0.5)).unwrap();
+            assert!(mv.is_pass(), "{:?}", mv);
+        }
+    }
+
+    #[test]
+    fn test_wins_by_passing_after_opponent_pass() {
+        let position = white_with_two_eyes();
+        assert!(!wins_by_passing(&position, Stone::Black, RuleSet::Area, 0.5));
+        let after_pass = position.pass("black").unwrap().position;
+        assert!(wins_by_passing(&after_pass, Stone::White, RuleSet::Area, 0.5));
+        // 按盘面数子落后时不停一手
+        assert!(!wins_by_passing(&after_pass, Stone::White, RuleSet::Area, -30.0));
+    }
+
+    #[test]
+    fn test_ai_disputes_its_live_stones_marked_dead() {
+        let mut position = QuantumPosition { variant: Variant::Classical, quantum_pairs: 0, ..QuantumPosition::with_boards(9, 9, 1) };
+        for mv in ["1,1", "5,5", "1,2", "5,6"] {
+            let color = position.next_color();
+            position = position.play(mv, color).unwrap().position;
+        }
+        // 不移除死子时白方靠贴目获胜，把白棋标成死子就成了黑胜
+        let white: Vec<String> = vec!["5,5".to_string(), "5,6".to_string()];
+        assert_eq!(score_with_dead_stones(&position, &white, RuleSet::Area, 7.5).winner, "black");
+        assert!(!accepts_dead_stones(&position, &white, Stone::White, RuleSet::Area, 7.5));
+        // 不改变胜负、或者对 AI 有利的标记照常同意
+        let black: Vec<String> = vec!["1,1".to_string(), "1,2".to_string()];
+        assert!(accepts_dead_stones(&position, &[], Stone::White, RuleSet::Area, 7.5));
+        assert!(accepts_dead_stones(&position, &black, Stone::White, RuleSet::Area, 7.5));
+        assert!(accepts_dead_stones(&position, &white, Stone::Black, RuleSet::Area, 7.5));
+    }
+
+    #[test]
+    fn test_difficulty_round_trip() {
+        for difficulty in [AIDifficulty::Beginner, AIDifficulty::Intermediate, AIDifficulty::Advanced] {
+            assert_eq!(AIDifficulty::parse(difficulty.as_str()).map(|d| d.as_str()), Some(difficulty.as_str()));
+        }
+        assert!(AIDifficulty::parse("expert").is_none());
+    }
+
+    #[test]
+    fn test_move_time_limit_respects_room_clock() {
+        let advanced = AIDifficulty::Advanced;
+        assert_eq!(advanced.move_time_limit(0), advanced.search_budget().time_limit);
+        assert_eq!(advanced.move_time_limit(30), advanced.search_budget().time_limit);
+        assert_eq!(advanced.move_time_limit(5), Duration::from_secs(3));
+        assert_eq!(advanced.move_time_limit(1), MIN_THINK_TIME);
+        assert_eq!(AIDifficulty::Beginner.move_time_limit(5), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_parse_player_spec() {
+        let spec = PlayerSpec::parse("mcts:advanced:2000").unwrap();
+        assert_eq!(spec.engine, AIEngine::Mcts);
+        assert_eq!(spec.difficulty, AIDifficulty::Advanced);
+        assert_eq!(spec.ai(Stone::Black).budget.playouts, 2000);
+        assert_eq!(spec.label(), "mcts:advanced:2000");
+
+        assert_eq!(PlayerSpec::parse("heuristic").unwrap().difficulty, AIDifficulty::Intermediate);
+        assert!(PlayerSpec::parse("random").is_err());
+        assert!(PlayerSpec::parse("mcts:advanced:0").is_err());
+        assert!(PlayerSpec::parse("mcts:advanced:10:1").is_err());
+    }
+
+    #[test]
+    fn test_opponent_from_room_info() {
+        let pvp = RoomInfo { game_type: "pvp".to_string(), visitor_id: Some(Uuid::new_v4()), ..RoomInfo::default() };
+        assert_eq!(Opponent::from_room_info(&pvp), Opponent::Human);
+
+        let ai = RoomInfo {
+            game_type: "ai".to_string(),
+            ai_engine: Some("heuristic".to_string()),
+            ai_difficulty: Some("advanced".to_string()),
+            ai_color: Some("black".to_string()),
+            ..RoomInfo::default()
+        };
+        let opponent = Opponent::from_room_info(&ai);
+        assert_eq!(
+            opponent,
+            Opponent::Ai {
+                engine: EngineChoice::Builtin(AIEngine::Heuristic),
+                difficulty: AIDifficulty::Advanced,
+                color: Stone::Black,
+            }
+        );
+        assert_eq!(ai.owner_color(), "white");
+
+        // 迁移后缺少设置的 AI 房间取默认值
+        let legacy = RoomInfo { game_type: "ai".to_string(), ..RoomInfo::default() };
+        assert_eq!(
+            Opponent::from_room_info(&legacy),
+            Opponent::Ai {
+                engine: EngineChoice::default(),
+                difficulty: AIDifficulty::Intermediate,
+                color: Stone::White,
+            }
+        );
+
+        let external = RoomInfo { ai_engine: Some("external:research".to_string()), ..legacy };
+        assert!(matches!(
+            Opponent::from_room_info(&external),
+            Opponent::Ai { engine: EngineChoice::External(name), .. } if name == "research"
+        ));
+    }
+
     #[test]
     fn test_quantum_phase_enum_ser_de() {
         let black = QuantumPhase::BlackQuantum;
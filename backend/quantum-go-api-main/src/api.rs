@@ -1,9 +1,20 @@
-use crate::entity::{RoomInfo, User, LeaderboardEntry};
-use crate::ai::{SimpleQuantumAI, AIDifficulty, room_info_to_quantum_board_state};
+use crate::entity::{RoomInfo, User, LeaderboardEntry, WsSender};
+use crate::ai::{AIDifficulty, AIMove, GameType, Opponent, wins_by_passing};
+use crate::bots::is_reserved_username;
+use crate::board::{MAX_SIZE, MIN_SIZE, Point, Stone, valid_dimensions};
+use crate::collapse::{CollapseRule, Seed};
+use crate::engine::{EngineChoice, EngineRegistry, MoveLimits, TreeSlot, move_seed, ponder_seed};
+use crate::hints::room_hints;
+use crate::rules::{AiDecision, DEFAULT_BOARDS, MAX_BOARDS, MAX_QUANTUM_PAIRS, HandicapPlacement, KoRule, MoveOutcome, QuantumPosition, RuleError, Variant, first_to_move, fixed_handicap_points};
+use crate::mcts::SearchTree;
+use crate::rating::rating_pool;
+use crate::reviewer::spawn_review;
+use crate::scoring::RuleSet;
+use crate::worker::JobError;
 use axum::{Json, extract::State, http::StatusCode};
 use serde::Deserialize;
+use tracing::{debug, info};
 use uuid::Uuid;
-use crate::ai::QuantumPhase;
 
 type ApiResult<T> = Result<(StatusCode, Json<T>), (StatusCode, Json<serde_json::Value>)>;
 
@@ -22,9 +33,24 @@ pub struct LoginRequest {
 #[derive(Deserialize)]
 pub struct CreateRoom {
     user_id: Uuid,
-    model: i32,
+    model: i32,                // 棋盘宽度（方形棋盘即边长）
+    height: Option<i32>,       // 矩形棋盘的高度，不传则与 model 相同
     countdown: i32,
     game_mode: Option<String>, // 设为可选字段，保持向后兼容
+    ko_rule: Option<String>,   // "simple"（默认）或 "positional_superko"
+    rule_set: Option<String>,  // "area"（默认）或 "territory"
+    komi: Option<f64>,         // 不传则按计分规则取默认贴目
+    handicap: Option<i32>,     // 让子数：0/1（让先）或 2~9
+    handicap_placement: Option<String>, // "fixed"（默认，星位）或 "free"
+    handicap_stones: Option<Vec<String>>, // 自由摆放时的让子坐标
+    variant: Option<String>,   // "quantum"（默认）或 "classical"
+    quantum_pairs: Option<i32>, // 量子对数（含开局一对），默认 1，仅量子围棋
+    boards: Option<i32>,       // 叠加的棋盘数，默认 2，仅量子围棋
+    collapse: Option<String>,  // 坍缩规则："none"（默认）、"end" 或 "measure"，仅量子围棋
+    ai_engine: Option<String>, // AI 引擎："mcts"（默认）、"heuristic" 或 "external:<name>"，仅 AI 对战
+    ai_difficulty: Option<String>, // AI 难度："beginner"、"intermediate"（默认）或 "advanced"，仅 AI 对战
+    ai_color: Option<String>,  // AI 执子颜色："white"（默认）或 "black"，仅 AI 对战
+    hints: Option<bool>,       // 对局中能否请求提示，默认 AI 对战允许；人人对战与机器人账号对局（计分）关闭
 }
 
 #[derive(Deserialize)]
@@ -35,6 +61,8 @@ pub struct GetGameInfo {
 #[derive(Deserialize)]
 pub struct GetLeaderboardRequest {
     model: i32,
+    height: Option<i32>, // 与 model 一起按棋盘大小换算评分池
+    variant: Option<String>, // 量子与经典围棋分别排名，默认 "quantum"
     limit: Option<i32>,
 }
 
@@ -44,6 +72,14 @@ pub async fn register(
     State(state): State<crate::ws::AppState>,
     Json(req): Json<RegisterRequest>,
 ) -> ApiResult<User> {
+    if is_reserved_username(&req.username) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "Usernames starting with bot- are reserved for AI bots"
+            })),
+        ));
+    }
     match state.db.create_user(&req.username, &req.password).await {
         Ok(user) => Ok((StatusCode::CREATED, Json(user))),
         Err(err) => Err((
@@ -80,31 +116,202 @@ pub async fn create_room(
     let room_id = Uuid::new_v4();
     
     // Set visitor_id based on game mode (default to PVP if not specified)
-    let game_mode = req.game_mode.as_deref().unwrap_or("pvp");
-    let visitor_id = if game_mode == "ai" {
-        Some(Uuid::new_v4()) // Create a virtual AI player ID
-    } else {
-        None
+    let game_type = match req.game_mode.as_deref() {
+        None => GameType::default(),
+        Some(value) => match GameType::parse(value) {
+            Some(game_type) => game_type,
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "Invalid game_mode. Must be pvp or ai"
+                    })),
+                ));
+            }
+        },
     };
-    
+    let game_mode = game_type.as_str();
+
+    let height = req.height.unwrap_or(req.model);
+    if !valid_dimensions(req.model, height) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": invalid_size_message()
+            })),
+        ));
+    }
+
+    let ko_rule = match req.ko_rule.as_deref() {
+        None => KoRule::default(),
+        Some(value) => match KoRule::parse(value) {
+            Some(rule) => rule,
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "Invalid ko_rule. Must be simple or positional_superko"
+                    })),
+                ));
+            }
+        },
+    };
+    let rule_set = match req.rule_set.as_deref() {
+        None => RuleSet::default(),
+        Some(value) => match RuleSet::parse(value) {
+            Some(rule_set) => rule_set,
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "Invalid rule_set. Must be area or territory"
+                    })),
+                ));
+            }
+        },
+    };
+    let variant = parse_variant(req.variant.as_deref())?;
+    let quantum_pairs = match (variant, req.quantum_pairs) {
+        (Variant::Classical, None) => 0,
+        (Variant::Quantum, None) => 1,
+        (Variant::Quantum, Some(pairs)) if (1..=MAX_QUANTUM_PAIRS).contains(&pairs) => pairs,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("Invalid quantum_pairs. Must be between 1 and {} in the quantum variant", MAX_QUANTUM_PAIRS)
+                })),
+            ));
+        }
+    };
+    let board_count = match (variant, req.boards) {
+        (Variant::Classical, None) => 1,
+        (Variant::Quantum, None) => DEFAULT_BOARDS,
+        (Variant::Quantum, Some(count)) if (2..=MAX_BOARDS as i32).contains(&count) => count as usize,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("Invalid boards. Must be between 2 and {} in the quantum variant", MAX_BOARDS)
+                })),
+            ));
+        }
+    };
+    let collapse_rule = match (variant, req.collapse.as_deref().map(CollapseRule::parse)) {
+        (_, None) => CollapseRule::None,
+        (Variant::Quantum, Some(Some(rule))) => rule,
+        (Variant::Classical, Some(Some(CollapseRule::None))) => CollapseRule::None,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "Invalid collapse. Must be none, end or measure in the quantum variant"
+                })),
+            ));
+        }
+    };
+    let opponent = match parse_opponent(&req, game_type, &state.engines) {
+        Ok(opponent) => opponent,
+        Err(message) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": message
+                })),
+            ));
+        }
+    };
+    // 内置引擎以对应的机器人账号为访客，对局计入评分；外部引擎没有账号，使用虚拟玩家 ID
+    let visitor_id = match &opponent {
+        Opponent::Human => None,
+        Opponent::Ai { engine, difficulty, .. } => {
+            Some(state.bots.bot_for(engine, *difficulty).unwrap_or_else(Uuid::new_v4))
+        }
+    };
+
     println!("Creating room with game_mode: {}, visitor_id: {:?}", game_mode, visitor_id);
+
+    // 坍缩抽取的随机种子：建房时只公布承诺，抽取后公开
+    let seed = (collapse_rule != CollapseRule::None).then(Seed::generate);
+    let empty = QuantumPosition {
+        variant,
+        quantum_pairs,
+        ..QuantumPosition::with_boards(req.model, height, board_count)
+    };
+    let position = match build_handicap_position(&req, empty) {
+        Ok(position) => position,
+        Err(message) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": message
+                })),
+            ));
+        }
+    };
+    // 与机器人账号的对局计入评分，机器人的评分固定，不能靠改贴目或提示刷分
+    let rated_bot = visitor_id.is_some_and(|id| state.bots.is_bot(id));
+    let (komi, hints) = match game_settings(&req, game_type, rule_set, rated_bot) {
+        Ok(settings) => settings,
+        Err(message) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": message
+                })),
+            ));
+        }
+    };
+    let handicap = req.handicap.unwrap_or(0);
+    let first = first_to_move(position.handicap);
     
     let room_info = RoomInfo {
         id: 0,
         room_id,
         owner_id: req.user_id,
         visitor_id,
-        status: if game_mode == "ai" { "playing".to_string() } else { "waiting".to_string() },
-        round: "black".to_string(),
+        status: if game_type == GameType::Ai { "playing".to_string() } else { "waiting".to_string() },
+        round: first.as_str().to_string(),
         winner: None,
-        board: serde_json::Value::Object(serde_json::Map::new()),
+        board: position.board_value(),
         countdown: req.countdown,
         moves: 0,
         black_lost: 0,
         white_lost: 0,
         model: req.model,
         chessman_records: serde_json::Value::Array(vec![]),
-        phase: Some("BlackQuantum".to_string()), // 新增：设置初始量子阶段
+        phase: Some(quantum_phase(first).to_string()), // 新增：设置初始量子阶段
+        ko_rule: ko_rule.as_str().to_string(),
+        position_history: position.history_value(),
+        komi,
+        rule_set: rule_set.as_str().to_string(),
+        scoring_state: serde_json::json!({}),
+        handicap,
+        height: req.height,
+        variant: variant.as_str().to_string(),
+        quantum_pairs,
+        collapse_rule: collapse_rule.as_str().to_string(),
+        seed_commitment: seed.as_ref().map(|seed| seed.commitment.clone()),
+        collapse_seed: seed.map(|seed| seed.seed),
+        revealed_seed: None,
+        collapsed_board: None,
+        game_type: game_mode.to_string(),
+        ai_engine: None,
+        ai_difficulty: None,
+        ai_color: None,
+        hints,
+        ai_seed: None,
+    };
+    let room_info = match &opponent {
+        Opponent::Human => room_info,
+        Opponent::Ai { engine, difficulty, color } => RoomInfo {
+            ai_engine: Some(engine.as_string()),
+            ai_difficulty: Some(difficulty.as_str().to_string()),
+            ai_color: Some(color.as_str().to_string()),
+            // AI 每一手的随机种子由房间种子与手数导出，落子可以复现
+            ai_seed: Some(Seed::generate().seed),
+            ..room_info
+        },
     };
     
     println!("Room info created: {:?}", room_info);
@@ -116,7 +323,9 @@ pub async fn create_room(
                 StatusCode::CREATED,
                 Json(serde_json::json!({ 
                     "room_id": room_id,
-                    "game_mode": game_mode 
+                    "game_mode": game_mode,
+                    "ai_color": created_room.ai_color,
+                    "seed_commitment": created_room.seed_commitment
                 })),
             ))
         },
@@ -155,17 +364,19 @@ pub async fn get_leaderboard(
     Json(req): Json<GetLeaderboardRequest>,
 ) -> ApiResult<Vec<LeaderboardEntry>> {
     let limit = req.limit.unwrap_or(50);
+    let variant = parse_variant(req.variant.as_deref())?;
     
-    if ![9, 13, 19].contains(&req.model) {
+    let height = req.height.unwrap_or(req.model);
+    if !valid_dimensions(req.model, height) {
         return Err((
             StatusCode::BAD_REQUEST,
             Json(serde_json::json!({
-                "error": "Invalid model. Must be 9, 13, or 19"
+                "error": invalid_size_message()
             })),
         ));
     }
 
-    match state.db.get_leaderboard(req.model, limit).await {
+    match state.db.get_leaderboard(rating_pool(req.model, height), variant.as_str(), limit).await {
         Ok(leaderboard) => Ok((StatusCode::OK, Json(leaderboard))),
         Err(err) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -181,9 +392,12 @@ pub async fn get_leaderboard(
 pub struct UpdatePlayerMoveRequest {
     pub room_id: Uuid,
     pub user_id: Uuid,
-    pub position: String,
+    pub position: String, // "x,y"，或 "pass" 停一手
+    #[allow(dead_code)]
     pub game_mode: Option<String>,
     pub board: Option<serde_json::Value>, // 新增：棋盘状态
+    #[serde(default)]
+    pub quantum: bool, // 量子落子：AI 的下一手与之纠缠
 }
 
 #[axum::debug_handler]
@@ -205,39 +419,73 @@ pub async fn update_player_move(
     };
 
     println!("Updating player move - room_info: {:?}", room_info);
-    
-    // 更新房间信息中的moves计数、量子阶段和棋盘状态
-    let mut updated_room_info = room_info.clone();
-    updated_room_info.moves = room_info.moves + 1;
-    
-    // 如果moves变为奇数，说明轮到AI（白方）
-    if updated_room_info.moves % 2 == 1 {
-        updated_room_info.phase = Some("WhiteQuantum".to_string());
-    } else {
-        updated_room_info.phase = Some("BlackQuantum".to_string());
+
+    // 人人对战的落子走 WebSocket，此接口只用于 AI 对战
+    if Opponent::from_room_info(&room_info) == Opponent::Human {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "This is not an AI battle room"
+            })),
+        ));
     }
-    
-    // 更新棋盘状态（如果提供）
-    let board_updated = req.board.is_some();
-    if let Some(board) = req.board {
-        updated_room_info.board = board;
-        println!("Board state updated with new data");
+    check_ai_room_access(&room_info, req.user_id)?;
+
+    // 由服务端规则引擎计算落子结果，非法或被篡改的落子直接拒绝
+    let position = QuantumPosition::from_room_info(&room_info);
+    let color = room_info.owner_color();
+    let played = if req.position == "pass" {
+        position.pass(color)
+    } else if req.quantum {
+        position.play_quantum(&req.position, color)
+    } else {
+        position.play(&req.position, color)
+    };
+    let outcome = match played {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            println!("Player move rejected: {}", err);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": err.to_string()
+                })),
+            ));
+        }
+    };
+    if let Some(board) = &req.board {
+        if let Err(err) = outcome.position.verify_submitted(board) {
+            println!("Player move rejected: {}", err);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": err.to_string()
+                })),
+            ));
+        }
     }
-    
-    println!("Player move will update: moves={}, phase={:?}, board_updated={}", 
-             updated_room_info.moves, updated_room_info.phase, board_updated);
+
+    // 更新房间信息中的moves计数、量子阶段和棋盘状态
+    let updated_room_info = apply_outcome(&room_info, &outcome);
+
+    println!("Player move will update: moves={}, phase={:?}",
+             updated_room_info.moves, updated_room_info.phase);
     
     // 保存到数据库
     match state.db.update_room(&updated_room_info).await {
-        Ok(_) => {
+        Ok(updated_room) => {
             println!("Player move updated successfully: moves={}, phase={:?}", 
                      updated_room_info.moves, updated_room_info.phase);
+            // 经房间的落子流通知 AI 的后台思考
+            state.ponderer.played(room_info.room_id, &outcome.position);
+            let updated_room = end_ai_game_if_both_passed(&state, updated_room).await;
             Ok((
                 StatusCode::OK,
                 Json(serde_json::json!({
                     "message": "Player move updated successfully",
-                    "moves": updated_room_info.moves,
-                    "phase": updated_room_info.phase
+                    "moves": updated_room.moves,
+                    "phase": updated_room.phase,
+                    "status": updated_room.status
                 })),
             ))
         }
@@ -253,13 +501,272 @@ pub async fn update_player_move(
     }
 }
 
+fn invalid_size_message() -> String {
+    format!("Invalid board size. Width and height must be between {} and {}", MIN_SIZE, MAX_SIZE)
+}
+
+/// 解析对手：AI 对战的选点方式、难度与 AI 执子颜色，人人对战不能设置
+fn parse_opponent(req: &CreateRoom, game_type: GameType, engines: &EngineRegistry) -> Result<Opponent, String> {
+    if game_type == GameType::Pvp {
+        if req.ai_engine.is_some() || req.ai_difficulty.is_some() || req.ai_color.is_some() {
+            return Err("ai_engine, ai_difficulty and ai_color are only valid in AI games".to_string());
+        }
+        return Ok(Opponent::Human);
+    }
+    let engine = match req.ai_engine.as_deref() {
+        None => EngineChoice::default(),
+        Some(value) => EngineChoice::parse(value)
+            .ok_or("Invalid ai_engine. Must be mcts, heuristic or external:<name>")?,
+    };
+    if !engines.contains(&engine) {
+        return Err(format!("AI engine {} is not available on this server", engine.as_string()));
+    }
+    let difficulty = match req.ai_difficulty.as_deref() {
+        None => AIDifficulty::Intermediate,
+        Some(value) => AIDifficulty::parse(value)
+            .ok_or("Invalid ai_difficulty. Must be beginner, intermediate or advanced")?,
+    };
+    let color = match req.ai_color.as_deref() {
+        None => Stone::White,
+        Some(value) => Stone::parse(value).ok_or("Invalid ai_color. Must be black or white")?,
+    };
+    Ok(Opponent::Ai { engine, difficulty, color })
+}
+
+/// 按请求摆放让子，返回开局局面；参数非法时返回错误信息
+/// 贴目与提示开关；与机器人账号的计分对局只能用默认贴目，且不能请求提示
+fn game_settings(req: &CreateRoom, game_type: GameType, rule_set: RuleSet, rated_bot: bool) -> Result<(f64, bool), String> {
+    let handicap = req.handicap.unwrap_or(0);
+    let default_komi = if handicap > 0 { rule_set.handicap_komi(handicap) } else { rule_set.default_komi() };
+    if !rated_bot {
+        return Ok((req.komi.unwrap_or(default_komi), req.hints.unwrap_or(game_type == GameType::Ai)));
+    }
+    if req.komi.is_some_and(|komi| komi != default_komi) {
+        return Err(format!("Rated games against bots use the default komi of {}", default_komi));
+    }
+    if req.hints == Some(true) {
+        return Err("Hints are not available in rated games against bots".to_string());
+    }
+    Ok((default_komi, false))
+}
+
+fn build_handicap_position(req: &CreateRoom, empty: QuantumPosition) -> Result<QuantumPosition, String> {
+    let (width, height) = (req.model, req.height.unwrap_or(req.model));
+    let handicap = req.handicap.unwrap_or(0);
+    if !(0..=9).contains(&handicap) {
+        return Err("Invalid handicap. Must be between 0 and 9".to_string());
+    }
+    // 让先只影响贴目，不摆子
+    if handicap < 2 {
+        return Ok(empty);
+    }
+
+    let placement = match req.handicap_placement.as_deref() {
+        None => HandicapPlacement::default(),
+        Some(value) => HandicapPlacement::parse(value)
+            .ok_or_else(|| "Invalid handicap_placement. Must be fixed or free".to_string())?,
+    };
+    let stones = match placement {
+        HandicapPlacement::Fixed => fixed_handicap_points(width, height, handicap)
+            .ok_or_else(|| format!("No fixed handicap layout for {} stones on this board", handicap))?,
+        HandicapPlacement::Free => {
+            let stones = req.handicap_stones.as_deref().unwrap_or_default();
+            if stones.len() != handicap as usize {
+                return Err(format!("Free placement requires exactly {} handicap stones", handicap));
+            }
+            stones
+                .iter()
+                .map(|pos| Point::parse(pos).ok_or_else(|| format!("Invalid position: {}", pos)))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    empty.with_handicap(&stones).map_err(|err| err.to_string())
+}
+
+/// 解析对局变体，不传时为量子围棋
+fn parse_variant(value: Option<&str>) -> Result<Variant, (StatusCode, Json<serde_json::Value>)> {
+    match value {
+        None => Ok(Variant::default()),
+        Some(value) => Variant::parse(value).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "Invalid variant. Must be quantum or classical"
+                })),
+            )
+        }),
+    }
+}
+
+/// 轮到某一方时房间的量子阶段（经典围棋只表示轮到哪一方）
+fn quantum_phase(to_move: Stone) -> &'static str {
+    match to_move {
+        Stone::Black => "BlackQuantum",
+        Stone::White => "WhiteQuantum",
+    }
+}
+
+/// 将规则引擎的落子结果写回房间信息
+fn apply_outcome(room_info: &RoomInfo, outcome: &MoveOutcome) -> RoomInfo {
+    let mut chessman_records = room_info.chessman_records.as_array().cloned().unwrap_or_default();
+    chessman_records.push(serde_json::to_value(&outcome.record).unwrap_or(serde_json::Value::Null));
+
+    let mut updated_room_info = room_info.clone();
+    updated_room_info.board = outcome.position.board_value();
+    updated_room_info.moves = outcome.position.moves;
+    updated_room_info.black_lost = outcome.position.black_lost;
+    updated_room_info.white_lost = outcome.position.white_lost;
+    updated_room_info.round = outcome.position.next_color().to_string();
+    updated_room_info.chessman_records = serde_json::Value::Array(chessman_records);
+    updated_room_info.position_history = outcome.position.history_value();
+    updated_room_info.phase = Some(quantum_phase(outcome.position.to_move()).to_string());
+    updated_room_info
+}
+
+/// AI 对战中双方连续停一手后进入数子阶段，结果通知房主的连接（如有）；返回最新的房间
+async fn end_ai_game_if_both_passed(state: &crate::ws::AppState, room_info: RoomInfo) -> RoomInfo {
+    let owner_tx = state.rooms.lock().await.get(&room_info.room_id).and_then(|room| room.user1.clone());
+    let txs: Vec<&WsSender> = owner_tx.iter().collect();
+    crate::ws::end_if_both_passed(&txs, state, &room_info).await.unwrap_or(room_info)
+}
+
+/// AI 对战接口的权限与状态检查：只有房主可以操作，对局结束或数子阶段不再落子
+fn check_ai_room_access(room_info: &RoomInfo, user_id: Uuid) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if user_id != room_info.owner_id {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "Only the room owner can submit moves here"
+            })),
+        ));
+    }
+    if room_info.status == "finished" || room_info.status == "scoring" {
+        let err = if room_info.status == "finished" { RuleError::GameFinished } else { RuleError::Scoring };
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": err.to_string()
+            })),
+        ));
+    }
+    Ok(())
+}
+
+// 复盘接口：终局后的逐手评估
+#[derive(Deserialize)]
+pub struct GetReviewRequest {
+    room_id: Uuid,
+}
+
+#[axum::debug_handler]
+pub async fn get_review(
+    State(state): State<crate::ws::AppState>,
+    Json(req): Json<GetReviewRequest>,
+) -> ApiResult<serde_json::Value> {
+    let room_info = match state.db.get_room_by_room_id(req.room_id).await {
+        Ok(info) => info,
+        Err(_) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": "Room not found"
+                })),
+            ));
+        }
+    };
+    if room_info.status != "finished" {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "Game is not finished"
+            })),
+        ));
+    }
+
+    let record = match state.db.get_review(req.room_id).await {
+        Ok(record) => record,
+        Err(err) => {
+            info!("Failed to load review: {}", err);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": format!("Failed to load review: {}", err)
+                })),
+            ));
+        }
+    };
+    match record {
+        // 早于复盘功能结束的对局，首次请求时生成
+        None => {
+            spawn_review(&state, room_info);
+            Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "status": "pending" }))))
+        }
+        Some(record) if record.status == "pending" => {
+            Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "status": "pending" }))))
+        }
+        Some(record) => Ok((
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "status": record.status,
+                "review": record.review,
+                "error": record.error
+            })),
+        )),
+    }
+}
+
+// 提示接口：当前局面的候选着法
+#[derive(Deserialize)]
+pub struct HintRequest {
+    room_id: Uuid,
+    user_id: Uuid,
+    count: Option<usize>, // 候选着法数，默认 3，最多 10
+    board_state: Option<serde_json::Value>, // 与 AI 落子接口相同，不传时使用房间保存的局面
+}
+
+#[axum::debug_handler]
+pub async fn get_hints(
+    State(state): State<crate::ws::AppState>,
+    Json(req): Json<HintRequest>,
+) -> ApiResult<serde_json::Value> {
+    let room_info = match state.db.get_room_by_room_id(req.room_id).await {
+        Ok(info) => info,
+        Err(_) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": "Room not found"
+                })),
+            ));
+        }
+    };
+
+    match room_hints(&state, &room_info, req.user_id, req.count, req.board_state.as_ref()).await {
+        Ok(hints) => {
+            debug!("Hints for room {}: {:?}", req.room_id, hints);
+            Ok((StatusCode::OK, Json(serde_json::json!({ "hints": hints }))))
+        }
+        Err((status, message)) => {
+            info!("Hint request rejected: {}", message);
+            Err((
+                status,
+                Json(serde_json::json!({
+                    "error": message
+                })),
+            ))
+        }
+    }
+}
+
 // 新增：AI对战接口
 #[derive(Deserialize)]
 pub struct AIMoveRequest {
     room_id: Uuid,
     user_id: Uuid,
-    game_mode: Option<String>, // 添加游戏模式参数
-    board_state: Option<serde_json::Value>, // 新增：传递的棋盘状态
+    #[allow(dead_code)]
+    game_mode: Option<String>, // 旧客户端传递的游戏模式，以房间保存的对局类型为准
+    #[allow(dead_code)]
+    board_state: Option<serde_json::Value>, // 旧客户端传递的棋盘状态，以房间保存的局面为准
 }
 
 #[axum::debug_handler]
@@ -280,28 +787,13 @@ pub async fn ai_move(
         }
     };
 
-    // 检查是否是AI对战房间
     println!("AI move request - room_info: {:?}", room_info);
-    println!("AI move request - visitor_id: {:?}", room_info.visitor_id);
     println!("AI move request - current board: {:?}", room_info.board);
     println!("AI move request - current moves: {}", room_info.moves);
-    
-    // 检查是否是AI对战房间
-    // 方法1: 通过visitor_id判断
-    let is_ai_by_visitor = room_info.visitor_id.is_some();
-    
-    // 方法2: 通过请求参数判断
-    let is_ai_by_request = req.game_mode.as_deref() == Some("ai");
-    
-    // 方法3: 通过房间状态判断（临时方案）
-    let is_ai_by_status = room_info.status == "playing" && room_info.moves == 0;
-    
-    let is_ai_room = is_ai_by_visitor || is_ai_by_request || is_ai_by_status;
-    
-    println!("AI room check - visitor: {}, request: {}, status: {}, final: {}", 
-             is_ai_by_visitor, is_ai_by_request, is_ai_by_status, is_ai_room);
-    
-    if !is_ai_room {
+
+    // 以房间保存的对手设置为准，只有 AI 对战房间的房主可以请求 AI 落子
+    let opponent = Opponent::from_room_info(&room_info);
+    let Opponent::Ai { engine, difficulty, color } = &opponent else {
         println!("AI move rejected: not an AI room");
         return Err((
             StatusCode::BAD_REQUEST,
@@ -309,37 +801,89 @@ pub async fn ai_move(
                 "error": "This is not an AI battle room"
             })),
         ));
-    }
-    
-    println!("AI move request approved: room identified as AI room");
+    };
+    check_ai_room_access(&room_info, req.user_id)?;
+    // 房间选用的外部引擎可能已从服务端配置中移除
+    let ai_player = match state.engines.player(engine, *difficulty, *color) {
+        Ok(player) => player,
+        Err(message) => {
+            println!("AI move rejected: {}", message);
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "error": message
+                })),
+            ));
+        }
+    };
 
-    // 创建AI玩家（默认中级难度）
-    let ai_player = SimpleQuantumAI::new(AIDifficulty::Intermediate);
+    println!("AI move request approved: opponent={:?}", opponent);
     
     // 转换游戏状态
     println!("Converting room_info to quantum board state...");
     println!("Room info details: moves={}, round={}, model={}, status={}", 
              room_info.moves, room_info.round, room_info.model, room_info.status);
     
-    // 优先使用传递的棋盘状态，如果没有则使用数据库中的状态
-    let quantum_state = if let Some(board_state) = &req.board_state {
-        println!("Using provided board state: {:?}", board_state);
-        // 从传递的棋盘状态创建量子状态
-        crate::ai::create_quantum_state_from_board_state(board_state, &room_info)
-    } else {
-        println!("Using database board state");
-        crate::ai::room_info_to_quantum_board_state(&room_info)
-    };
+    // 以房间保存的局面为准，落子记录按这个局面复现；不采信客户端传递的棋盘状态
+    let quantum_state = crate::ai::room_info_to_quantum_board_state(&room_info);
     
     println!("Quantum state created: {:?}", quantum_state);
     
     // 获取AI的下一步落子
     println!("Getting AI's next move...");
     println!("Current quantum phase: {:?}, moves: {}", quantum_state.quantum_phase, room_info.moves);
-    println!("AI will call get_next_move with quantum_phase: {:?}", quantum_state.quantum_phase);
-    println!("Current board state - board1: {:?}, board2: {:?}", quantum_state.board1, quantum_state.board2);
+    println!("AI {} will generate a move with quantum_phase: {:?}", ai_player.name(), quantum_state.quantum_phase);
+    println!("Current board state - boards: {:?}", quantum_state.boards);
     
-    match ai_player.get_next_move(&quantum_state) {
+    // 轮到 AI 时取回玩家回合中思考得到的搜索树（只有 MCTS 引擎会思考）
+    let pondered = if quantum_state.current_player == *color {
+        state.ponderer.take(room_info.room_id, &quantum_state.to_position()).await
+    } else {
+        None
+    };
+    debug!("AI pondered tree: {:?}", pondered);
+    let (reused, pondered, tree) = match pondered {
+        Some(pondered) => (pondered.tree.visits(), pondered.playouts, TreeSlot::new(Some(pondered.tree))),
+        None => (0, Vec::new(), TreeSlot::default()),
+    };
+
+    // 本手的随机种子由房间种子与手数导出，随落子记录以便复现
+    let seed = room_info.ai_seed.as_deref().map(|room_seed| move_seed(room_seed, room_info.moves));
+    let (engine_name, engine_version) = (ai_player.name(), ai_player.version());
+
+    // 搜索耗时较长（高级难度可达数秒），放到 AI 线程池中执行；
+    // 思考时间不超过房间读秒，玩家断开或对局结束时搜索被取消
+    let time_limit = difficulty.move_time_limit(room_info.countdown);
+    debug!("AI time limit: {:?}", time_limit);
+    let limits = MoveLimits { time_limit: Some(time_limit), tree: tree.clone(), seed, ..MoveLimits::default() };
+    // 玩家停一手后 AI 按盘面数子已经领先：不必搜索，也停一手进入数子
+    let rule_set = RuleSet::parse(&room_info.rule_set).unwrap_or_default();
+    let settled = wins_by_passing(&QuantumPosition::from_room_info(&room_info), *color, rule_set, room_info.komi);
+    let search = if settled {
+        Ok(Ok(AIMove::pass(color.as_str())))
+    } else {
+        state
+            .ai_workers
+            .run(room_info.room_id, move |cancel| {
+                ai_player.generate(&quantum_state, &MoveLimits { cancel, ..limits })
+            })
+            .await
+    };
+    let next_move = match search {
+        Ok(result) => result,
+        Err(JobError::Cancelled) => {
+            info!("AI move cancelled");
+            return Err((
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": "AI move was cancelled"
+                })),
+            ));
+        }
+        Err(JobError::Failed) => Err("AI worker failed".into()),
+    };
+
+    match next_move {
         Ok(ai_move) => {
             // 检查AI是否在等待状态
             if ai_move.position == "waiting" {
@@ -361,49 +905,73 @@ pub async fn ai_move(
             println!("AI move generated: {:?}", ai_move);
             
             // 如果AI要下棋，我们需要推进量子阶段并更新数据库
+            let mut room_status = room_info.status.clone();
             if ai_move.position != "none" && ai_move.color != "none" && ai_move.position != "waiting" {
-                // 计算新的量子阶段
-                let new_phase = match quantum_state.quantum_phase {
-                    QuantumPhase::BlackQuantum => "BlackQuantum", // 不应该发生
-                    QuantumPhase::WhiteQuantum => "Entanglement",  // 白方下完进入纠缠
-                    QuantumPhase::Entanglement => "BlackQuantum",  // 纠缠完毕轮到黑方
+                // 思考期间房间可能已变化（对局结束、玩家离开或已有新的落子），以最新状态为准
+                let room_info = match state.db.get_room_by_room_id(room_info.room_id).await {
+                    Ok(latest) if latest.moves == room_info.moves => latest,
+                    _ => {
+                        info!("AI move discarded: room changed while thinking");
+                        return Err((
+                            StatusCode::CONFLICT,
+                            Json(serde_json::json!({
+                                "error": "Room changed while the AI was thinking"
+                            })),
+                        ));
+                    }
                 };
-                
-                println!("AI move will advance quantum phase from {:?} to {}", 
-                         quantum_state.quantum_phase, new_phase);
-                
-                // 更新房间信息中的量子阶段和moves计数
-                let mut updated_room_info = room_info.clone();
-                updated_room_info.phase = Some(new_phase.to_string());
-                updated_room_info.moves = room_info.moves + 1; // 增加moves计数
-                
-                // 重要：更新棋盘状态，添加AI的落子
-                if let Some(board_obj) = updated_room_info.board.as_object_mut() {
-                    let ai_chessman = serde_json::json!({
-                        "position": ai_move.position,
-                        "type": ai_move.color,
-                        "brother": ai_move.position
-                    });
-                    board_obj.insert(ai_move.position.clone(), ai_chessman);
-                    println!("Updated board with AI move: {:?}", updated_room_info.board);
+                check_ai_room_access(&room_info, req.user_id)?;
+
+                // 通过规则引擎应用AI落子（含纠缠与各盘提子）
+                let position = QuantumPosition::from_room_info(&room_info);
+                let played = if ai_move.is_pass() {
+                    position.pass(&ai_move.color)
+                } else if ai_move.quantum {
+                    position.play_quantum(&ai_move.position, &ai_move.color)
                 } else {
-                    // 如果board不是对象，创建一个新的对象
-                    let mut new_board = serde_json::Map::new();
-                    let ai_chessman = serde_json::json!({
-                        "position": ai_move.position,
-                        "type": ai_move.color,
-                        "brother": ai_move.position
-                    });
-                    new_board.insert(ai_move.position.clone(), ai_chessman);
-                    updated_room_info.board = serde_json::Value::Object(new_board);
-                    println!("Created new board with AI move: {:?}", updated_room_info.board);
-                }
+                    position.play(&ai_move.position, &ai_move.color)
+                };
+                let mut outcome = match played {
+                    Ok(outcome) => outcome,
+                    Err(err) => {
+                        println!("AI move rejected by rules: {}", err);
+                        return Err((
+                            StatusCode::CONFLICT,
+                            Json(serde_json::json!({
+                                "error": format!("AI move rejected: {}", err)
+                            })),
+                        ));
+                    }
+                };
+
+                // 按数子领先停一手不经引擎选点，无需复现
+                outcome.record.ai = seed.filter(|_| !settled).map(|seed| AiDecision {
+                    pondered,
+                    ..AiDecision::new(engine_name, engine_version, seed, ai_move.playouts, reused)
+                });
+
+                // 更新房间信息中的量子阶段、moves计数和棋盘状态
+                let updated_room_info = apply_outcome(&room_info, &outcome);
+                let new_phase = updated_room_info.phase.clone().unwrap_or_default();
+                println!("Updated board with AI move: {:?}", updated_room_info.board);
                 
                 // 保存到数据库
-                if let Err(err) = state.db.update_room(&updated_room_info).await {
-                    println!("Failed to update room phase and moves: {}", err);
-                } else {
-                    println!("Room updated successfully: phase={}, moves={}", new_phase, updated_room_info.moves);
+                match state.db.update_room(&updated_room_info).await {
+                    Err(err) => info!("Failed to update room phase and moves: {}", err),
+                    Ok(updated_room) => {
+                        debug!("Room updated successfully: phase={}, moves={}", new_phase, updated_room_info.moves);
+                        // 双方连续停一手后进入数子，AI 不再思考
+                        let updated_room = end_ai_game_if_both_passed(&state, updated_room).await;
+                        room_status = updated_room.status;
+                        // 在玩家回合继续思考，搜索树沿 AI 这一手下行
+                        if let Some(searched) = tree.take().filter(|_| room_status == "playing") {
+                            let searched = searched
+                                .advance(&outcome.position)
+                                .unwrap_or_else(|| SearchTree::new(&outcome.position));
+                            let ponder_seed = seed.map(ponder_seed);
+                            state.ponderer.start(room_info.room_id, searched, *difficulty, room_info.komi, ponder_seed);
+                        }
+                    }
                 }
             }
             
@@ -414,9 +982,11 @@ pub async fn ai_move(
                     "ai_move": {
                         "position": ai_move.position,
                         "type": ai_move.color,  // 转换为前端期望的type字段
-                        "brother": ai_move.position
+                        "brother": ai_move.position,
+                        "quantum": ai_move.quantum
                     },
-                    "message": "AI move generated successfully"
+                    "message": "AI move generated successfully",
+                    "status": room_status
                 })),
             ))
         }
@@ -0,0 +1,512 @@
+//! GTP（Go Text Protocol 第 2 版）前端：经标准输入输出驱动服务端的规则引擎与 AI，
+//! 可接入围棋图形界面、twogtp 一类的对局工具和分析工具。
+//!
+//! ```text
+//! cargo run --release --bin gtp -- --player mcts:advanced --variant quantum
+//! ```
+//!
+//! 标准命令之外的量子扩展：
+//! - `quantum-play COLOR VERTEX`：量子落子，对方的下一手与之纠缠成对
+//! - `quantum-genmove COLOR`：与 genmove 相同，量子落子时在坐标后附 `quantum`
+//! - `quantum-boards`：各棋盘上的黑白棋子
+//! - `quantum-state`：变体、棋盘数、量子对用量与等待纠缠的量子子
+//! - `quantum-variant quantum|classical`、`quantum-pairs N`：修改设置并清空棋盘
+//!
+//! 坐标为 GTP 格式（列 A~Z 跳过 I，行号自下而上），对应服务端 "x,y" 中 y = 1 的一行在最上方。
+
+use quantum_go_api::ai::PlayerSpec;
+use quantum_go_api::board::{MAX_SIZE, Point, Stone, valid_dimensions};
+use quantum_go_api::rules::{
+    DEFAULT_BOARDS, KoRule, MAX_BOARDS, MAX_QUANTUM_PAIRS, QuantumPosition, Variant, fixed_handicap_points,
+};
+use quantum_go_api::scoring::{RuleSet, score_position};
+use std::io::{self, BufRead, Write};
+use std::process;
+
+/// GTP 的列字母，没有 I
+const COLUMNS: &[u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+const KNOWN_COMMANDS: &[&str] = &[
+    "protocol_version",
+    "name",
+    "version",
+    "known_command",
+    "list_commands",
+    "quit",
+    "boardsize",
+    "clear_board",
+    "komi",
+    "fixed_handicap",
+    "play",
+    "genmove",
+    "undo",
+    "final_score",
+    "showboard",
+    "quantum-play",
+    "quantum-genmove",
+    "quantum-boards",
+    "quantum-state",
+    "quantum-variant",
+    "quantum-pairs",
+];
+
+const USAGE: &str = "usage: gtp [options]
+  --player SPEC          engine[:difficulty[:playouts]] (default mcts:intermediate)
+  --variant V            quantum | classical (default quantum)
+  --boards N             boards in superposition for quantum games (default 2)
+  --pairs N              quantum pairs per game including the opening pair (default 1)
+  --rule-set R           area | territory (default area)
+  --ko-rule R            simple | positional_superko (default simple)";
+
+/// GTP 会话：当前局面与悔棋栈
+struct Gtp {
+    player: PlayerSpec,
+    width: i32,
+    height: i32,
+    variant: Variant,
+    boards: usize,
+    quantum_pairs: i32,
+    rule_set: RuleSet,
+    ko_rule: KoRule,
+    komi: f64,
+    position: QuantumPosition,
+    undo: Vec<QuantumPosition>,
+}
+
+type Response = Result<String, String>;
+
+impl Gtp {
+    fn new(player: PlayerSpec) -> Self {
+        let mut gtp = Self {
+            player,
+            width: 19,
+            height: 19,
+            variant: Variant::Quantum,
+            boards: DEFAULT_BOARDS,
+            quantum_pairs: 1,
+            rule_set: RuleSet::Area,
+            ko_rule: KoRule::default(),
+            komi: RuleSet::Area.default_komi(),
+            position: QuantumPosition::new(19, 19),
+            undo: Vec::new(),
+        };
+        gtp.clear_board();
+        gtp
+    }
+
+    fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut gtp = Self::new(PlayerSpec::parse("mcts:intermediate")?);
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+            let invalid = || format!("invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--player" => gtp.player = PlayerSpec::parse(value)?,
+                "--variant" => gtp.variant = Variant::parse(value).ok_or_else(invalid)?,
+                "--boards" => {
+                    gtp.boards = value
+                        .parse()
+                        .ok()
+                        .filter(|n| (DEFAULT_BOARDS..=MAX_BOARDS).contains(n))
+                        .ok_or_else(invalid)?
+                }
+                "--pairs" => {
+                    gtp.quantum_pairs = value
+                        .parse()
+                        .ok()
+                        .filter(|n| (1..=MAX_QUANTUM_PAIRS).contains(n))
+                        .ok_or_else(invalid)?
+                }
+                "--rule-set" => {
+                    gtp.rule_set = RuleSet::parse(value).ok_or_else(invalid)?;
+                    gtp.komi = gtp.rule_set.default_komi();
+                }
+                "--ko-rule" => gtp.ko_rule = KoRule::parse(value).ok_or_else(invalid)?,
+                _ => return Err(format!("unknown option: {}", flag)),
+            }
+        }
+        gtp.clear_board();
+        Ok(gtp)
+    }
+
+    fn clear_board(&mut self) {
+        let (boards, quantum_pairs) = match self.variant {
+            Variant::Quantum => (self.boards, self.quantum_pairs),
+            Variant::Classical => (1, 0),
+        };
+        self.position = QuantumPosition {
+            variant: self.variant,
+            ko_rule: self.ko_rule,
+            quantum_pairs,
+            ..QuantumPosition::with_boards(self.width, self.height, boards)
+        };
+        self.undo.clear();
+    }
+
+    /// 执行一条命令（不含编号），quit 由调用方处理
+    fn execute(&mut self, command: &str, args: &[&str]) -> Response {
+        match command {
+            "protocol_version" => Ok("2".to_string()),
+            "name" => Ok("Quantum Go".to_string()),
+            "version" => Ok(env!("CARGO_PKG_VERSION").to_string()),
+            "known_command" => Ok(KNOWN_COMMANDS.contains(&arg(args, 0)?).to_string()),
+            "list_commands" => Ok(KNOWN_COMMANDS.join("\n")),
+            "quit" => Ok(String::new()),
+            "boardsize" => {
+                let size: i32 = arg(args, 0)?.parse().map_err(|_| "boardsize not an integer".to_string())?;
+                if !valid_dimensions(size, size) {
+                    return Err("unacceptable size".to_string());
+                }
+                (self.width, self.height) = (size, size);
+                self.clear_board();
+                Ok(String::new())
+            }
+            "clear_board" => {
+                self.clear_board();
+                Ok(String::new())
+            }
+            "komi" => {
+                self.komi = arg(args, 0)?.parse().map_err(|_| "komi not a float".to_string())?;
+                Ok(String::new())
+            }
+            "fixed_handicap" => self.fixed_handicap(arg(args, 0)?),
+            "play" => self.play(arg(args, 0)?, arg(args, 1)?, false),
+            "quantum-play" => self.play(arg(args, 0)?, arg(args, 1)?, true),
+            "genmove" => self.genmove(arg(args, 0)?).map(|(vertex, _)| vertex),
+            "quantum-genmove" => self
+                .genmove(arg(args, 0)?)
+                .map(|(vertex, quantum)| if quantum { format!("{} quantum", vertex) } else { vertex }),
+            "undo" => match self.undo.pop() {
+                Some(position) => {
+                    self.position = position;
+                    Ok(String::new())
+                }
+                None => Err("cannot undo".to_string()),
+            },
+            "final_score" => {
+                let score = score_position(&self.position, self.rule_set, self.komi);
+                let margin = (score.black_score - score.white_score).abs();
+                Ok(match score.winner.as_str() {
+                    "black" => format!("B+{}", margin),
+                    "white" => format!("W+{}", margin),
+                    _ => "0".to_string(),
+                })
+            }
+            "showboard" => Ok(self.showboard()),
+            "quantum-boards" => Ok(self.boards_text()),
+            "quantum-state" => Ok(self.state_text()),
+            "quantum-variant" => {
+                self.variant = Variant::parse(arg(args, 0)?).ok_or_else(|| "unknown variant".to_string())?;
+                self.clear_board();
+                Ok(String::new())
+            }
+            "quantum-pairs" => {
+                self.quantum_pairs = arg(args, 0)?
+                    .parse()
+                    .ok()
+                    .filter(|n| (1..=MAX_QUANTUM_PAIRS).contains(n))
+                    .ok_or_else(|| "unacceptable number of pairs".to_string())?;
+                self.clear_board();
+                Ok(String::new())
+            }
+            _ => Err("unknown command".to_string()),
+        }
+    }
+
+    fn fixed_handicap(&mut self, count: &str) -> Response {
+        let count: i32 = count.parse().map_err(|_| "handicap not an integer".to_string())?;
+        if self.position.moves > 0 || !self.position.boards[0].is_empty() {
+            return Err("board not empty".to_string());
+        }
+        let points =
+            fixed_handicap_points(self.width, self.height, count).ok_or_else(|| "invalid number of stones".to_string())?;
+        self.position = self.position.clone().with_handicap(&points).map_err(|e| e.to_string())?;
+        Ok(points.iter().map(|&p| vertex(p, self.height)).collect::<Vec<_>>().join(" "))
+    }
+
+    fn play(&mut self, color: &str, vertex: &str, quantum: bool) -> Response {
+        let stone = parse_color(color)?;
+        let outcome = match parse_vertex(vertex, self.width, self.height)? {
+            Some(p) => self.position.play_at(p, stone, quantum),
+            None if quantum => return Err("cannot pass with a quantum move".to_string()),
+            None => self.position.pass(stone.as_str()),
+        };
+        let outcome = outcome.map_err(|e| format!("illegal move: {}", e))?;
+        self.undo.push(std::mem::replace(&mut self.position, outcome.position));
+        Ok(String::new())
+    }
+
+    /// AI 为 color 选一手并落下，返回 (坐标, 是否量子落子)；无法落子也不能停一手时认输
+    fn genmove(&mut self, color: &str) -> Result<(String, bool), String> {
+        let stone = parse_color(color)?;
+        if stone != self.position.to_move() {
+            return Err(format!("it is {}'s turn", self.position.next_color()));
+        }
+        let chosen = self.player.genmove(&self.position, self.komi).map_err(|e| e.to_string())?;
+        let played = chosen.and_then(|(p, quantum)| Some((p, quantum, self.position.play_at(p, stone, quantum).ok()?)));
+        let (outcome, vertex, quantum) = match played {
+            Some((p, quantum, outcome)) => (outcome, vertex(p, self.height), quantum),
+            None => match self.position.pass(stone.as_str()) {
+                Ok(outcome) => (outcome, "pass".to_string(), false),
+                Err(_) => return Ok(("resign".to_string(), false)),
+            },
+        };
+        self.undo.push(std::mem::replace(&mut self.position, outcome.position));
+        Ok((vertex, quantum))
+    }
+
+    /// 各棋盘并排显示，X 为黑、O 为白，等待纠缠的量子子用小写
+    fn showboard(&self) -> String {
+        let header: String = COLUMNS[..self.width as usize].iter().map(|&c| format!(" {}", c as char)).collect();
+        let mut lines = vec![String::new()];
+        lines.push(
+            (1..=self.position.boards.len())
+                .map(|k| format!("   {:<width$}", format!("board {}", k), width = header.len()))
+                .collect::<Vec<_>>()
+                .join("   "),
+        );
+        lines.push(vec![format!("   {}", header); self.position.boards.len()].join("   "));
+        for y in 1..=self.height {
+            let row = self.height + 1 - y;
+            let rows: Vec<String> = self
+                .position
+                .boards
+                .iter()
+                .map(|board| {
+                    let cells: String = (1..=self.width)
+                        .map(|x| {
+                            let p = Point::new(x as u8, y as u8);
+                            let pending = self.position.pending_quantum == Some(p);
+                            let mark = match (board.stone(p), pending) {
+                                (Some(Stone::Black), false) => 'X',
+                                (Some(Stone::White), false) => 'O',
+                                (Some(Stone::Black), true) => 'x',
+                                (Some(Stone::White), true) => 'o',
+                                (None, _) => '.',
+                            };
+                            format!(" {}", mark)
+                        })
+                        .collect();
+                    format!("{:>2}{} {:<2}", row, cells, row)
+                })
+                .collect();
+            lines.push(rows.join(" "));
+        }
+        lines.push(format!(
+            "{} to move, captured: black {} white {}",
+            self.position.next_color(),
+            self.position.black_lost,
+            self.position.white_lost
+        ));
+        lines.join("\n")
+    }
+
+    /// 每个棋盘一行：board k black ... white ...
+    fn boards_text(&self) -> String {
+        self.position
+            .boards
+            .iter()
+            .enumerate()
+            .map(|(k, board)| {
+                let stones = |stone: Stone| {
+                    board
+                        .stones()
+                        .filter(|(_, cell)| cell.stone == stone)
+                        .map(|(p, _)| format!(" {}", vertex(p, self.height)))
+                        .collect::<String>()
+                };
+                format!("board {} black{} white{}", k + 1, stones(Stone::Black), stones(Stone::White))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn state_text(&self) -> String {
+        let position = &self.position;
+        [
+            format!("variant {}", position.variant.as_str()),
+            format!("boards {}", position.boards.len()),
+            format!("to_move {}", position.next_color()),
+            format!("moves {}", position.moves),
+            format!("quantum_pairs {}", position.quantum_pairs),
+            format!("pairs_used {}", position.pairs_used),
+            format!(
+                "pending {}",
+                position.pending_quantum.map_or("none".to_string(), |p| vertex(p, self.height))
+            ),
+            format!("can_play_quantum {}", position.can_play_quantum()),
+        ]
+        .join("\n")
+    }
+}
+
+fn arg<'a>(args: &[&'a str], index: usize) -> Result<&'a str, String> {
+    args.get(index).copied().ok_or_else(|| "missing argument".to_string())
+}
+
+fn parse_color(value: &str) -> Result<Stone, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "b" | "black" => Ok(Stone::Black),
+        "w" | "white" => Ok(Stone::White),
+        _ => Err("invalid color".to_string()),
+    }
+}
+
+/// GTP 坐标转为服务端坐标，pass 为 None
+fn parse_vertex(value: &str, width: i32, height: i32) -> Result<Option<Point>, String> {
+    let value = value.to_ascii_uppercase();
+    if value == "PASS" {
+        return Ok(None);
+    }
+    let invalid = || "invalid coordinate".to_string();
+    let column = value.bytes().next().ok_or_else(invalid)?;
+    let x = COLUMNS.iter().position(|&c| c == column).ok_or_else(invalid)? as i32 + 1;
+    let row: i32 = value[1..].parse().map_err(|_| invalid())?;
+    if x > width || !(1..=height).contains(&row) {
+        return Err(invalid());
+    }
+    Ok(Some(Point::new(x as u8, (height + 1 - row) as u8)))
+}
+
+fn vertex(p: Point, height: i32) -> String {
+    format!("{}{}", COLUMNS[p.x as usize - 1] as char, height + 1 - p.y as i32)
+}
+
+/// 预处理一行输入：去掉注释与控制字符，制表符换成空格
+fn preprocess(line: &str) -> String {
+    let line = line.split('#').next().unwrap_or_default();
+    line.chars()
+        .filter_map(|c| match c {
+            '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn main() {
+    const _: () = assert!(COLUMNS.len() >= MAX_SIZE as usize);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        eprintln!("{}", USAGE);
+        return;
+    }
+    let mut gtp = match Gtp::from_args(&args) {
+        Ok(gtp) => gtp,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let line = preprocess(&line);
+        let mut words = line.split_whitespace().peekable();
+        let id = words.next_if(|word| word.bytes().all(|b| b.is_ascii_digit())).unwrap_or_default();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+
+        let response = match gtp.execute(command, &args) {
+            Ok(text) => format!("={} {}", id, text),
+            Err(text) => format!("?{} {}", id, text),
+        };
+        let written = write!(stdout, "{}\n\n", response.trim_end()).and_then(|_| stdout.flush());
+        if written.is_err() || command == "quit" {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Gtp {
+        let mut gtp = Gtp::new(PlayerSpec::parse("heuristic:advanced").unwrap());
+        gtp.execute("boardsize", &["5"]).unwrap();
+        gtp
+    }
+
+    #[test]
+    fn test_vertex_round_trip() {
+        assert_eq!(parse_vertex("a1", 19, 19), Ok(Some(Point::new(1, 19))));
+        assert_eq!(parse_vertex("J19", 19, 19), Ok(Some(Point::new(9, 1))));
+        assert_eq!(parse_vertex("PASS", 19, 19), Ok(None));
+        assert!(parse_vertex("I5", 19, 19).is_err());
+        assert!(parse_vertex("F1", 5, 5).is_err());
+        assert!(parse_vertex("A6", 5, 5).is_err());
+        for p in [Point::new(1, 1), Point::new(8, 3), Point::new(9, 19)] {
+            assert_eq!(parse_vertex(&vertex(p, 19), 19, 19), Ok(Some(p)));
+        }
+    }
+
+    #[test]
+    fn test_preprocess_strips_comments_and_control_characters() {
+        assert_eq!(preprocess("  play\tb D4 # comment\r"), "play b D4");
+        assert_eq!(preprocess("# only a comment"), "");
+    }
+
+    #[test]
+    fn test_play_genmove_undo_and_score() {
+        let mut gtp = session();
+        assert_eq!(gtp.execute("boardsize", &["30"]), Err("unacceptable size".to_string()));
+        assert_eq!(gtp.execute("play", &["b", "C3"]), Ok(String::new()));
+        // 开局第一手是量子子，白方的回应与之纠缠
+        assert!(gtp.execute("quantum-state", &[]).unwrap().contains("pending C3"));
+        assert!(gtp.execute("play", &["b", "D4"]).is_err());
+
+        let (vertex, _) = gtp.genmove("white").unwrap();
+        let point = parse_vertex(&vertex, 5, 5).unwrap().unwrap();
+        assert_eq!(gtp.position.boards[1].stone(Point::new(3, 3)), Some(Stone::White));
+        assert_eq!(gtp.position.boards[1].stone(point), Some(Stone::Black));
+        assert!(gtp.execute("quantum-boards", &[]).unwrap().starts_with("board 1 black C3 white"));
+
+        assert_eq!(gtp.execute("undo", &[]), Ok(String::new()));
+        assert_eq!(gtp.position.moves, 1);
+        assert!(gtp.execute("play", &["w", "pass"]).unwrap_err().starts_with("illegal move"));
+
+        gtp.execute("clear_board", &[]).unwrap();
+        assert_eq!(gtp.execute("undo", &[]), Err("cannot undo".to_string()));
+        assert_eq!(gtp.execute("final_score", &[]), Ok("W+7.5".to_string()));
+    }
+
+    #[test]
+    fn test_quantum_play_extension() {
+        let mut gtp = session();
+        for (color, vertex) in [("b", "C3"), ("w", "B2")] {
+            gtp.execute("play", &[color, vertex]).unwrap();
+        }
+        // 量子对只有开局一对
+        assert!(gtp.execute("quantum-play", &["b", "D4"]).is_err());
+
+        gtp.execute("quantum-pairs", &["2"]).unwrap();
+        for (color, vertex) in [("b", "C3"), ("w", "B2")] {
+            gtp.execute("play", &[color, vertex]).unwrap();
+        }
+        gtp.execute("quantum-play", &["b", "D4"]).unwrap();
+        gtp.execute("play", &["w", "B4"]).unwrap();
+        assert_eq!(gtp.position.pairs_used, 2);
+        assert_eq!(gtp.position.boards[1].stone(parse_vertex("D4", 5, 5).unwrap().unwrap()), Some(Stone::White));
+        assert!(gtp.showboard().contains("board 2"));
+    }
+
+    #[test]
+    fn test_classical_variant_has_one_board() {
+        let mut gtp = session();
+        gtp.execute("quantum-variant", &["classical"]).unwrap();
+        gtp.execute("play", &["b", "C3"]).unwrap();
+        assert!(gtp.execute("quantum-state", &[]).unwrap().contains("boards 1\n"));
+        assert_eq!(gtp.execute("known_command", &["quantum-play"]), Ok("true".to_string()));
+        assert_eq!(gtp.execute("fixed_handicap", &["2"]), Err("board not empty".to_string()));
+    }
+}
@@ -0,0 +1,424 @@
+//! 复现 AI 落子：读取房间记录（getGameInfo 的应答，或数据库中 room_infos 的一行导出为 JSON），
+//! 按每一手 AI 落子记录的引擎、种子与模拟次数在同一局面上重新选点，与记录中的一手比较。
+//! 完全离线，不连接数据库。
+//!
+//! 用法：
+//!
+//! ```text
+//! cargo run --release --bin replay -- --room room.json --move 12
+//! ```
+//!
+//! 复用了搜索树（记录中 reused 不为 0）的一手，按记录的模拟次数重做上一手的搜索与之后的后台思考，
+//! 重建的树与记录的访问次数一致时可以精确复现；没有思考记录的旧落子只能近似复现。
+//! 外部引擎的落子无法在这里复现。有可以精确复现的一手与记录不同时以状态码 1 退出。
+
+use quantum_go_api::ai::{AIDifficulty, AIEngine, QuantumBoardState, SimpleQuantumAI};
+use quantum_go_api::engine::{ENGINE_VERSION, MoveGenerator, MoveLimits, TreeSlot, ponder_seed};
+use quantum_go_api::entity::RoomInfo;
+use quantum_go_api::mcts::{Mcts, SearchBudget, SearchTree};
+use quantum_go_api::review::replay;
+use quantum_go_api::rules::{AiDecision, ChessmanRecord, MoveAction, QuantumPosition};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::io::Read;
+use std::process;
+use std::time::Duration;
+
+/// 按记录的模拟次数重新搜索，思考时间不设实际限制
+const REPLAY_TIME_LIMIT: Duration = Duration::from_secs(3600);
+
+const USAGE: &str = "usage: replay --room PATH [--move N]
+  --room PATH            room JSON as returned by /getGameInfo, - for stdin
+  --move N               only report move N (1-based index into chessman_records);
+                         earlier AI moves are still replayed to rebuild reused search trees";
+
+/// 复现设置
+#[derive(Debug, Clone, Default)]
+struct Config {
+    room: String,
+    number: Option<usize>,
+}
+
+impl Config {
+    fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = Config::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+            let invalid = || format!("invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--room" => config.room = value.clone(),
+                "--move" => config.number = Some(value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?),
+                _ => return Err(format!("unknown option: {}", flag)),
+            }
+        }
+        if config.room.is_empty() {
+            return Err("missing --room".to_string());
+        }
+        Ok(config)
+    }
+}
+
+/// 一手 AI 落子的复现结果
+#[derive(Debug, Clone)]
+struct Replayed {
+    number: usize,
+    decision: AiDecision,
+    recorded: String,
+    replayed: Result<String, String>,
+    restored: bool, // 没有复用搜索树，或复用的树已按记录重建
+}
+
+impl Replayed {
+    /// 记录的一手能否精确复现：内置引擎、同一版本、复用的搜索树已重建
+    fn exact(&self) -> bool {
+        self.restored && self.decision.version == ENGINE_VERSION && self.replayed.is_ok()
+    }
+
+    fn matches(&self) -> bool {
+        self.replayed.as_ref() == Ok(&self.recorded)
+    }
+
+    fn line(&self) -> String {
+        let d = &self.decision;
+        let mut line = format!(
+            "move {} {}@{} seed {} playouts {}: recorded {}",
+            self.number,
+            d.engine,
+            d.version,
+            d.seed,
+            d.playouts.map_or_else(|| "-".to_string(), |n| n.to_string()),
+            self.recorded
+        );
+        match &self.replayed {
+            Ok(replayed) => {
+                line += &format!(", replayed {} ", replayed);
+                line += match (self.matches(), self.exact()) {
+                    (true, _) => "ok",
+                    (false, true) => "MISMATCH",
+                    (false, false) => "differs",
+                };
+            }
+            Err(reason) => line += &format!(", not replayed: {}", reason),
+        }
+        if d.version != ENGINE_VERSION {
+            line += &format!(" (engine version is {})", ENGINE_VERSION);
+        }
+        if d.reused > 0 && self.restored {
+            line += &format!(" (rebuilt {} reused visits)", d.reused);
+        } else if d.reused > 0 {
+            line += &format!(" (reused {} pondered visits, not exactly reproducible)", d.reused);
+        }
+        line
+    }
+}
+
+/// 记录中的一手，如 "3,4"、"3,4 quantum" 或 "pass"
+fn label(position: &str, quantum: bool) -> String {
+    if quantum { format!("{} quantum", position) } else { position.to_string() }
+}
+
+/// 上一手 AI 落子之后的搜索树，以及那一手的记录序号与种子
+struct Carried {
+    tree: SearchTree,
+    number: usize,
+    seed: u64,
+}
+
+/// 重做上一手之后的后台思考（played 为期间玩家各手之后的局面），重建这一手复用的搜索树；
+/// 缺少记录或访问次数与记录不符时返回 None
+fn restore_tree(
+    carried: Option<Carried>,
+    decision: &AiDecision,
+    played: &[&QuantumPosition],
+    before: &QuantumPosition,
+    komi: f64,
+) -> Option<SearchTree> {
+    let Carried { mut tree, seed, .. } = carried?;
+    if decision.pondered.len() != played.len() + 1 {
+        return None;
+    }
+    let mut rng = StdRng::seed_from_u64(ponder_seed(seed));
+    for (segment, &playouts) in decision.pondered.iter().enumerate() {
+        if let Some(position) = segment.checked_sub(1).map(|previous| played[previous]) {
+            tree = tree.advance(position).unwrap_or_else(|| SearchTree::new(position));
+        }
+        Mcts::new(SearchBudget { playouts, time_limit: REPLAY_TIME_LIMIT }, komi).ponder(&mut tree, &mut rng);
+    }
+    tree.advance(before).filter(|tree| tree.visits() == decision.reused)
+}
+
+/// 按记录的引擎、种子与模拟次数重新选点，tree 为复用的搜索树，搜索后放回
+fn regenerate(decision: &AiDecision, before: &QuantumPosition, komi: f64, tree: &TreeSlot) -> Result<String, String> {
+    let (engine, difficulty) = decision
+        .engine
+        .split_once(':')
+        .and_then(|(engine, difficulty)| AIEngine::parse(engine).zip(AIDifficulty::parse(difficulty)))
+        .ok_or_else(|| format!("engine {} cannot be replayed here", decision.engine))?;
+    let seed = decision.seed().ok_or_else(|| format!("invalid seed {}", decision.seed))?;
+
+    let mut player = SimpleQuantumAI::new(difficulty).with_engine(engine).with_color(before.to_move());
+    if let Some(playouts) = decision.playouts {
+        player = player.with_budget(SearchBudget { playouts, time_limit: REPLAY_TIME_LIMIT });
+    }
+    let state = QuantumBoardState::from_position(before, komi);
+    let limits = MoveLimits { seed: Some(seed), tree: tree.clone(), ..MoveLimits::default() };
+    let ai_move = player.generate(&state, &limits).map_err(|e| e.to_string())?;
+    Ok(label(&ai_move.position, ai_move.quantum))
+}
+
+/// 复现房间中的 AI 落子，number 不为空时只报告这一手（之前的 AI 落子仍要复现，以重建复用的搜索树）
+fn replay_room(room: &RoomInfo, number: Option<usize>) -> Result<Vec<Replayed>, String> {
+    let (initial, steps) = replay(room).map_err(|e| e.to_string())?;
+    let records: Vec<ChessmanRecord> = serde_json::from_value(room.chessman_records.clone()).map_err(|e| e.to_string())?;
+
+    let mut replayed = Vec::new();
+    let mut carried: Option<Carried> = None;
+    for (index, record) in records.iter().enumerate() {
+        let current = index + 1;
+        let Some(decision) = &record.ai else {
+            continue;
+        };
+        if number.is_some_and(|number| current > number) {
+            break;
+        }
+        if records[..index].iter().any(|earlier| earlier.action == Some(MoveAction::Resign)) {
+            return Err(format!("move {} comes after a resignation", current));
+        }
+        // 这一手之前的局面：重放到前一条记录为止
+        let before = steps
+            .iter()
+            .rev()
+            .find(|step| step.number < current)
+            .map_or(&initial, |step| &step.position);
+        let recorded = match record.action {
+            Some(MoveAction::Pass) => "pass".to_string(),
+            _ => record
+                .add
+                .first()
+                .map(|added| label(&added.position, record.quantum))
+                .ok_or_else(|| format!("move {} has no stone", current))?,
+        };
+
+        let since = carried.as_ref().map_or(current, |carried| carried.number);
+        let played: Vec<&QuantumPosition> = steps
+            .iter()
+            .filter(|step| step.number > since && step.number < current)
+            .map(|step| &step.position)
+            .collect();
+        let tree = match decision.reused {
+            0 => None,
+            _ => restore_tree(carried.take(), decision, &played, before, room.komi),
+        };
+        let restored = decision.reused == 0 || tree.is_some();
+        let slot = TreeSlot::new(tree);
+        let result = regenerate(decision, before, room.komi, &slot);
+
+        // 复现与记录一致时，搜索树沿记录的一手下行，供之后重做后台思考
+        let after = steps.iter().find(|step| step.number == current).map(|step| &step.position);
+        carried = match (slot.take(), after, decision.seed()) {
+            (Some(tree), Some(after), Some(seed)) if restored && result.as_ref() == Ok(&recorded) => Some(Carried {
+                tree: tree.advance(after).unwrap_or_else(|| SearchTree::new(after)),
+                number: current,
+                seed,
+            }),
+            _ => None,
+        };
+        if number.is_none_or(|number| number == current) {
+            replayed.push(Replayed {
+                number: current,
+                decision: decision.clone(),
+                recorded,
+                replayed: result,
+                restored,
+            });
+        }
+    }
+    if let Some(number) = number.filter(|_| replayed.is_empty()) {
+        return Err(format!("move {} is not an AI move", number));
+    }
+    Ok(replayed)
+}
+
+fn load_room(path: &str) -> Result<RoomInfo, String> {
+    let mut text = String::new();
+    if path == "-" {
+        std::io::stdin().read_to_string(&mut text).map_err(|e| e.to_string())?;
+    } else {
+        text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    serde_json::from_str(&text).map_err(|e| format!("invalid room JSON: {}", e))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        eprintln!("{}", USAGE);
+        return;
+    }
+    let config = match Config::from_args(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let replayed = match load_room(&config.room).and_then(|room| replay_room(&room, config.number)) {
+        Ok(replayed) => replayed,
+        Err(e) => {
+            eprintln!("replay failed: {}", e);
+            process::exit(1);
+        }
+    };
+    for move_replay in &replayed {
+        println!("{}", move_replay.line());
+    }
+    if replayed.iter().any(|r| r.exact() && !r.matches()) {
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quantum_go_api::ai::room_info_to_quantum_board_state;
+    use quantum_go_api::board::Stone;
+    use quantum_go_api::engine::move_seed;
+    use quantum_go_api::rules::Variant;
+
+    /// 与服务端相同的流程：玩家落子后由 AI 按房间种子选点，落子记录复现信息。
+    /// ponder 不为空时 AI 落子后与玩家落子后各按给定的模拟次数后台思考，AI 选点时复用搜索树
+    fn ai_room(engine: AIEngine, player_moves: &[&str], ponder: Option<[u32; 2]>) -> RoomInfo {
+        let room_seed = "5".repeat(64);
+        let komi = 0.5;
+        let mut position = QuantumPosition { variant: Variant::Quantum, quantum_pairs: 1, ..QuantumPosition::new(5, 5) };
+        let mut room = RoomInfo {
+            model: 5,
+            variant: "quantum".to_string(),
+            ko_rule: "simple".to_string(),
+            quantum_pairs: 1,
+            komi,
+            ..RoomInfo::default()
+        };
+        let think = |tree: &mut SearchTree, playouts: u32, rng: &mut StdRng| {
+            Mcts::new(SearchBudget { playouts, time_limit: REPLAY_TIME_LIMIT }, komi).ponder(tree, rng);
+        };
+        let mut records = Vec::new();
+        let mut pondering: Option<(SearchTree, StdRng)> = None;
+        for mv in player_moves {
+            let outcome = position.play(mv, "black").unwrap();
+            records.push(serde_json::to_value(&outcome.record).unwrap());
+            position = outcome.position;
+            pondering = pondering.map(|(tree, mut rng)| {
+                let mut tree = tree.advance(&position).unwrap_or_else(|| SearchTree::new(&position));
+                think(&mut tree, ponder.unwrap()[1], &mut rng);
+                (tree, rng)
+            });
+
+            room.board = position.board_value();
+            room.moves = position.moves;
+            room.position_history = position.history_value();
+            room.chessman_records = serde_json::Value::Array(records.clone());
+            let state = room_info_to_quantum_board_state(&room);
+            let seed = move_seed(&room_seed, room.moves);
+            let reused_tree = pondering.take().and_then(|(tree, _)| tree.advance(&position));
+            let reused = reused_tree.as_ref().map_or(0, SearchTree::visits);
+            let slot = TreeSlot::new(reused_tree);
+            let player = SimpleQuantumAI::new(AIDifficulty::Beginner).with_engine(engine).with_color(Stone::White);
+            // 按时间截止，复现时只能依靠记录的模拟次数
+            let limits = MoveLimits {
+                time_limit: Some(Duration::from_millis(30)),
+                seed: Some(seed),
+                tree: slot.clone(),
+                ..MoveLimits::default()
+            };
+            let ai_move = player.generate(&state, &limits).unwrap();
+            let mut outcome = if ai_move.quantum {
+                position.play_quantum(&ai_move.position, "white")
+            } else {
+                position.play(&ai_move.position, "white")
+            }
+            .unwrap();
+            outcome.record.ai = Some(AiDecision {
+                pondered: ponder.filter(|_| reused > 0).map(Vec::from).unwrap_or_default(),
+                ..AiDecision::new(player.name(), player.version(), seed, ai_move.playouts, reused)
+            });
+            records.push(serde_json::to_value(&outcome.record).unwrap());
+            position = outcome.position;
+
+            if let (Some(tree), Some([after_ai, _])) = (slot.take(), ponder) {
+                let mut tree = tree.advance(&position).unwrap_or_else(|| SearchTree::new(&position));
+                let mut rng = StdRng::seed_from_u64(ponder_seed(seed));
+                think(&mut tree, after_ai, &mut rng);
+                pondering = Some((tree, rng));
+            }
+        }
+        RoomInfo {
+            board: position.board_value(),
+            moves: position.moves,
+            position_history: position.history_value(),
+            chessman_records: serde_json::Value::Array(records),
+            ..room
+        }
+    }
+
+    #[test]
+    fn test_replay_reproduces_seeded_ai_moves() {
+        for engine in [AIEngine::Mcts, AIEngine::Heuristic] {
+            let room = ai_room(engine, &["3,3", "1,1"], None);
+            let replayed = replay_room(&room, None).unwrap();
+            assert_eq!(replayed.iter().map(|r| r.number).collect::<Vec<_>>(), vec![2, 4]);
+            for r in &replayed {
+                assert!(r.exact() && r.matches(), "{}", r.line());
+            }
+            assert_eq!(replay_room(&room, Some(4)).unwrap().len(), 1);
+            assert!(replay_room(&room, Some(3)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_replay_rebuilds_pondered_trees() {
+        let mut room = ai_room(AIEngine::Mcts, &["3,3", "1,1", "5,5"], Some([40, 25]));
+        let replayed = replay_room(&room, None).unwrap();
+        assert_eq!(replayed.iter().map(|r| r.decision.reused > 0).collect::<Vec<_>>(), vec![false, true, true]);
+        for r in &replayed {
+            assert!(r.exact() && r.matches(), "{}", r.line());
+        }
+        // 只报告最后一手时，之前的搜索与思考照样重做
+        let last = &replay_room(&room, Some(6)).unwrap()[0];
+        assert!(last.exact() && last.matches(), "{}", last.line());
+
+        // 思考记录与搜索树对不上时无法精确复现
+        let mut records: Vec<ChessmanRecord> = serde_json::from_value(room.chessman_records.clone()).unwrap();
+        records[3].ai.as_mut().unwrap().pondered[1] += 1;
+        room.chessman_records = serde_json::to_value(&records).unwrap();
+        let replayed = replay_room(&room, Some(4)).unwrap();
+        assert!(!replayed[0].exact());
+        assert!(replayed[0].line().contains("not exactly reproducible"));
+    }
+
+    #[test]
+    fn test_external_and_pondered_moves_are_not_exact() {
+        let mut room = ai_room(AIEngine::Heuristic, &["3,3"], None);
+        let mut records: Vec<ChessmanRecord> = serde_json::from_value(room.chessman_records.clone()).unwrap();
+        let decision = records[1].ai.as_mut().unwrap();
+        decision.reused = 120;
+        decision.engine = "external:research".to_string();
+        room.chessman_records = serde_json::to_value(&records).unwrap();
+
+        let replayed = &replay_room(&room, None).unwrap()[0];
+        assert!(replayed.replayed.is_err());
+        assert!(!replayed.exact());
+        assert!(replayed.line().contains("not exactly reproducible"));
+    }
+
+    #[test]
+    fn test_config_from_args() {
+        let args = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let config = Config::from_args(&args(&["--room", "-", "--move", "7"])).unwrap();
+        assert_eq!((config.room.as_str(), config.number), ("-", Some(7)));
+        assert!(Config::from_args(&args(&["--move", "7"])).is_err());
+        assert!(Config::from_args(&args(&["--room", "a.json", "--move", "0"])).is_err());
+    }
+}
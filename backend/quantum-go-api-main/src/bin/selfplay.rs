@@ -0,0 +1,468 @@
+//! AI 对抗赛：两种 AI 配置在同一棋盘上对弈 N 局（每局交换黑白），按服务端规则判定胜负，
+//! 输出每局记录与 A 方的胜率、95% 置信区间，可选 SPRT 判定。完全离线，不连接数据库。
+//!
+//! 用法：
+//!
+//! ```text
+//! cargo run --release --bin selfplay -- --games 200 --size 9 \
+//!     --player-a mcts:advanced:2000 --player-b heuristic:intermediate \
+//!     --sprt 0,50 --records games.jsonl
+//! ```
+//!
+//! 选手格式为 `engine[:difficulty[:playouts]]`。每局结果与汇总写在标准输出。
+
+use quantum_go_api::ai::{AIDifficulty, AIEngine, PlayerSpec};
+use quantum_go_api::board::{Stone, valid_dimensions};
+use quantum_go_api::rules::{DEFAULT_BOARDS, KoRule, MAX_BOARDS, MAX_QUANTUM_PAIRS, QuantumPosition, Variant};
+use quantum_go_api::scoring::{RuleSet, score_position};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process;
+use std::time::Instant;
+
+/// SPRT 的两类错误率
+const SPRT_ALPHA: f64 = 0.05;
+const SPRT_BETA: f64 = 0.05;
+/// 95% 置信区间的 z 值
+const Z_95: f64 = 1.96;
+
+const USAGE: &str = "usage: selfplay [options]
+  --games N              number of games, colours alternate (default 100)
+  --size N | WxH         board size (default 9)
+  --variant V            quantum | classical (default quantum)
+  --boards N             boards in superposition for quantum games (default 2)
+  --pairs N              quantum pairs per game including the opening pair (default 1)
+  --komi K               komi (default: rule set default)
+  --rule-set R           area | territory (default area)
+  --ko-rule R            simple | positional_superko (default simple)
+  --max-moves N          adjudicate by score after N moves (default 3 x board area)
+  --player-a SPEC        engine[:difficulty[:playouts]] (default mcts:intermediate)
+  --player-b SPEC        engine[:difficulty[:playouts]] (default heuristic:intermediate)
+  --sprt E0,E1           stop once SPRT accepts elo(A - B) <= E0 or >= E1
+  --records PATH         write one JSON line per game to PATH";
+
+/// 对抗赛设置
+#[derive(Debug, Clone)]
+struct Config {
+    games: u32,
+    width: i32,
+    height: i32,
+    variant: Variant,
+    boards: usize,
+    quantum_pairs: i32,
+    komi: f64,
+    rule_set: RuleSet,
+    ko_rule: KoRule,
+    max_moves: i32,
+    player_a: PlayerSpec,
+    player_b: PlayerSpec,
+    sprt: Option<(f64, f64)>,
+    records: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            width: 9,
+            height: 9,
+            variant: Variant::Quantum,
+            boards: DEFAULT_BOARDS,
+            quantum_pairs: 1,
+            komi: RuleSet::Area.default_komi(),
+            rule_set: RuleSet::Area,
+            ko_rule: KoRule::default(),
+            max_moves: 9 * 9 * 3,
+            player_a: PlayerSpec {
+                engine: AIEngine::Mcts,
+                difficulty: AIDifficulty::Intermediate,
+                playouts: None,
+            },
+            player_b: PlayerSpec {
+                engine: AIEngine::Heuristic,
+                difficulty: AIDifficulty::Intermediate,
+                playouts: None,
+            },
+            sprt: None,
+            records: None,
+        }
+    }
+}
+
+impl Config {
+    fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = Config::default();
+        let mut komi = None;
+        let mut max_moves = None;
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+            let invalid = || format!("invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--games" => config.games = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+                "--size" => {
+                    let (width, height) = match value.split_once('x') {
+                        Some((width, height)) => (width.parse(), height.parse()),
+                        None => (value.parse(), value.parse()),
+                    };
+                    let (width, height) = width.ok().zip(height.ok()).ok_or_else(invalid)?;
+                    if !valid_dimensions(width, height) {
+                        return Err(invalid());
+                    }
+                    (config.width, config.height) = (width, height);
+                }
+                "--variant" => config.variant = Variant::parse(value).ok_or_else(invalid)?,
+                "--boards" => {
+                    config.boards = value
+                        .parse()
+                        .ok()
+                        .filter(|n| (DEFAULT_BOARDS..=MAX_BOARDS).contains(n))
+                        .ok_or_else(invalid)?
+                }
+                "--pairs" => {
+                    config.quantum_pairs = value
+                        .parse()
+                        .ok()
+                        .filter(|n| (1..=MAX_QUANTUM_PAIRS).contains(n))
+                        .ok_or_else(invalid)?
+                }
+                "--komi" => komi = Some(value.parse().map_err(|_| invalid())?),
+                "--rule-set" => config.rule_set = RuleSet::parse(value).ok_or_else(invalid)?,
+                "--ko-rule" => config.ko_rule = KoRule::parse(value).ok_or_else(invalid)?,
+                "--max-moves" => max_moves = Some(value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?),
+                "--player-a" => config.player_a = PlayerSpec::parse(value)?,
+                "--player-b" => config.player_b = PlayerSpec::parse(value)?,
+                "--sprt" => {
+                    let bounds = value
+                        .split_once(',')
+                        .and_then(|(elo0, elo1)| elo0.parse().ok().zip(elo1.parse().ok()))
+                        .filter(|(elo0, elo1)| elo0 < elo1)
+                        .ok_or_else(invalid)?;
+                    config.sprt = Some(bounds);
+                }
+                "--records" => config.records = Some(value.clone()),
+                _ => return Err(format!("unknown option: {}", flag)),
+            }
+        }
+        config.komi = komi.unwrap_or_else(|| config.rule_set.default_komi());
+        config.max_moves = max_moves.unwrap_or(config.width * config.height * 3);
+        Ok(config)
+    }
+
+    /// 每局的起始局面，经典围棋只有一个棋盘
+    fn start_position(&self) -> QuantumPosition {
+        let (boards, quantum_pairs) = match self.variant {
+            Variant::Quantum => (self.boards, self.quantum_pairs),
+            Variant::Classical => (1, 0),
+        };
+        QuantumPosition {
+            variant: self.variant,
+            ko_rule: self.ko_rule,
+            quantum_pairs,
+            ..QuantumPosition::with_boards(self.width, self.height, boards)
+        }
+    }
+}
+
+/// 对局中的一手
+#[derive(Debug, Clone, Serialize)]
+struct PlayedMove {
+    color: &'static str,
+    position: String, // 坐标或 "pass"
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    quantum: bool,
+}
+
+/// 一局的记录（JSON 行）
+#[derive(Debug, Clone, Serialize)]
+struct GameRecord {
+    game: u32,
+    black: String,
+    white: String,
+    winner: String, // "black" / "white" / "draw"
+    black_score: f64,
+    white_score: f64,
+    adjudicated: bool, // 达到手数上限后直接数子
+    seconds: f64,
+    moves: Vec<PlayedMove>,
+}
+
+/// 下完一局：双方连续停一手或达到手数上限时按规则数子
+fn play_game(config: &Config, game: u32, black: &PlayerSpec, white: &PlayerSpec) -> Result<GameRecord, String> {
+    let started = Instant::now();
+    let mut position = config.start_position();
+    let mut moves = Vec::new();
+    while !position.both_passed() && position.moves < config.max_moves {
+        let stone = position.to_move();
+        let spec = if stone == Stone::Black { black } else { white };
+        let chosen = spec.genmove(&position, config.komi).map_err(|e| e.to_string())?;
+        let played = chosen.and_then(|(p, quantum)| Some((p, quantum, position.play_at(p, stone, quantum).ok()?)));
+        let (outcome, mv) = match played {
+            Some((p, quantum, outcome)) => (
+                outcome,
+                PlayedMove { color: stone.as_str(), position: p.to_string(), quantum },
+            ),
+            None => (
+                position.pass(stone.as_str()).map_err(|e| e.to_string())?,
+                PlayedMove { color: stone.as_str(), position: "pass".to_string(), quantum: false },
+            ),
+        };
+        position = outcome.position;
+        moves.push(mv);
+    }
+
+    let score = score_position(&position, config.rule_set, config.komi);
+    Ok(GameRecord {
+        game,
+        black: black.label(),
+        white: white.label(),
+        winner: score.winner,
+        black_score: score.black_score,
+        white_score: score.white_score,
+        adjudicated: !position.both_passed(),
+        seconds: started.elapsed().as_secs_f64(),
+        moves,
+    })
+}
+
+/// A 方的战绩
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Tally {
+    wins: u32,
+    losses: u32,
+    draws: u32,
+}
+
+impl Tally {
+    fn add(&mut self, winner: &str, a_color: Stone) {
+        match Stone::parse(winner) {
+            Some(stone) if stone == a_color => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /// 平均得分（胜 1、和 0.5、负 0）
+    fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games().max(1) as f64
+    }
+
+    /// 单局得分的方差
+    fn variance(&self) -> f64 {
+        let n = self.games().max(1) as f64;
+        let s = self.score();
+        (self.wins as f64 * (1.0 - s).powi(2) + self.losses as f64 * s.powi(2) + self.draws as f64 * (0.5 - s).powi(2))
+            / n
+    }
+
+    /// 平均得分的 95% 置信区间（正态近似）
+    fn confidence_interval(&self) -> (f64, f64) {
+        let margin = Z_95 * (self.variance() / self.games().max(1) as f64).sqrt();
+        let s = self.score();
+        ((s - margin).max(0.0), (s + margin).min(1.0))
+    }
+
+    /// SPRT 的对数似然比（GSPRT 正态近似），H0: elo = elo0，H1: elo = elo1。
+    /// 全胜或全负时样本方差为 0，按 1/(4n) 取下限，这时应当最快得出结论
+    fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let n = self.games().max(1) as f64;
+        let variance = self.variance().max(1.0 / (4.0 * n));
+        let (s0, s1) = (expected_score(elo0), expected_score(elo1));
+        self.games() as f64 * (s1 - s0) * (2.0 * self.score() - s0 - s1) / (2.0 * variance)
+    }
+}
+
+/// SPRT 结论
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    H0, // A 不强于 elo0
+    H1, // A 至少强 elo1
+    Continue,
+}
+
+fn sprt(tally: &Tally, elo0: f64, elo1: f64) -> (f64, Verdict) {
+    let llr = tally.llr(elo0, elo1);
+    let lower = (SPRT_BETA / (1.0 - SPRT_ALPHA)).ln();
+    let upper = ((1.0 - SPRT_BETA) / SPRT_ALPHA).ln();
+    let verdict = if llr >= upper {
+        Verdict::H1
+    } else if llr <= lower {
+        Verdict::H0
+    } else {
+        Verdict::Continue
+    };
+    (llr, verdict)
+}
+
+/// Elo 差对应的期望得分
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// 期望得分对应的 Elo 差，全胜/全负时为无穷
+fn elo_difference(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn summary(config: &Config, tally: &Tally) -> String {
+    let (low, high) = tally.confidence_interval();
+    let mut summary = format!(
+        "{} vs {}: {} games, +{} -{} ={}, score {:.3} (95% CI {:.3}..{:.3}), elo {:+.1} ({:+.1}..{:+.1})",
+        config.player_a.label(),
+        config.player_b.label(),
+        tally.games(),
+        tally.wins,
+        tally.losses,
+        tally.draws,
+        tally.score(),
+        low,
+        high,
+        elo_difference(tally.score()),
+        elo_difference(low),
+        elo_difference(high),
+    );
+    if let Some((elo0, elo1)) = config.sprt {
+        let (llr, verdict) = sprt(tally, elo0, elo1);
+        let verdict = match verdict {
+            Verdict::H0 => format!("H0 accepted (elo <= {})", elo0),
+            Verdict::H1 => format!("H1 accepted (elo >= {})", elo1),
+            Verdict::Continue => "inconclusive".to_string(),
+        };
+        summary.push_str(&format!(", SPRT [{}, {}] llr {:.2}: {}", elo0, elo1, llr, verdict));
+    }
+    summary
+}
+
+fn run(config: &Config) -> Result<Tally, String> {
+    let mut records = match &config.records {
+        Some(path) => Some(BufWriter::new(File::create(path).map_err(|e| format!("{}: {}", path, e))?)),
+        None => None,
+    };
+
+    let mut tally = Tally::default();
+    for game in 1..=config.games {
+        // 每局交换黑白，抵消先手优势
+        let a_color = if game % 2 == 1 { Stone::Black } else { Stone::White };
+        let (black, white) = match a_color {
+            Stone::Black => (&config.player_a, &config.player_b),
+            Stone::White => (&config.player_b, &config.player_a),
+        };
+        let record = play_game(config, game, black, white)?;
+        tally.add(&record.winner, a_color);
+
+        println!(
+            "game {}: {} (B) vs {} (W): {} {:.1}-{:.1} in {} moves, {:.1}s{}",
+            game,
+            record.black,
+            record.white,
+            record.winner,
+            record.black_score,
+            record.white_score,
+            record.moves.len(),
+            record.seconds,
+            if record.adjudicated { " [move limit]" } else { "" },
+        );
+        if let Some(records) = records.as_mut() {
+            let line = serde_json::to_string(&record).map_err(|e| e.to_string())?;
+            writeln!(records, "{}", line).map_err(|e| e.to_string())?;
+        }
+
+        if let Some((elo0, elo1)) = config.sprt {
+            if sprt(&tally, elo0, elo1).1 != Verdict::Continue {
+                break;
+            }
+        }
+    }
+    if let Some(mut records) = records {
+        records.flush().map_err(|e| e.to_string())?;
+    }
+    Ok(tally)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        eprintln!("{}", USAGE);
+        return;
+    }
+    let config = match Config::from_args(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    match run(&config) {
+        Ok(tally) => println!("{}", summary(&config, &tally)),
+        Err(e) => {
+            eprintln!("selfplay failed: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_config_from_args() {
+        let config = Config::from_args(&args(&["--size", "7x5", "--rule-set", "territory", "--sprt", "0,50"])).unwrap();
+        assert_eq!((config.width, config.height), (7, 5));
+        assert_eq!(config.komi, 6.5);
+        assert_eq!(config.max_moves, 105);
+        assert_eq!(config.sprt, Some((0.0, 50.0)));
+
+        assert!(Config::from_args(&args(&["--size", "30"])).is_err());
+        assert!(Config::from_args(&args(&["--sprt", "50,0"])).is_err());
+        assert!(Config::from_args(&args(&["--games"])).is_err());
+    }
+
+    #[test]
+    fn test_tally_statistics_and_sprt() {
+        let even = Tally { wins: 50, losses: 50, draws: 0 };
+        assert_eq!(even.score(), 0.5);
+        assert!(elo_difference(even.score()).abs() < 1e-9);
+        let (low, high) = even.confidence_interval();
+        assert!((0.40..0.41).contains(&low) && (0.59..0.60).contains(&high));
+        assert_eq!(sprt(&even, 0.0, 50.0).1, Verdict::Continue);
+
+        let strong = Tally { wins: 80, losses: 20, draws: 0 };
+        assert_eq!(sprt(&strong, 0.0, 50.0).1, Verdict::H1);
+        let weak = Tally { wins: 20, losses: 80, draws: 0 };
+        assert_eq!(sprt(&weak, 0.0, 50.0).1, Verdict::H0);
+
+        // 全胜、全负的方差为 0，也要提前停止
+        assert_eq!(sprt(&Tally { wins: 10, losses: 0, draws: 0 }, 0.0, 50.0).1, Verdict::H1);
+        assert_eq!(sprt(&Tally { wins: 0, losses: 10, draws: 0 }, 0.0, 50.0).1, Verdict::H0);
+        assert_eq!(sprt(&Tally { wins: 1, losses: 0, draws: 0 }, 0.0, 50.0).1, Verdict::Continue);
+        assert_eq!(sprt(&Tally::default(), 0.0, 50.0), (0.0, Verdict::Continue));
+
+        let mut tally = Tally::default();
+        tally.add("black", Stone::Black);
+        tally.add("black", Stone::White);
+        tally.add("draw", Stone::White);
+        assert_eq!(tally, Tally { wins: 1, losses: 1, draws: 1 });
+    }
+
+    #[test]
+    fn test_play_game_finishes_and_scores() {
+        let config = Config::from_args(&args(&["--size", "5", "--player-a", "heuristic:advanced"])).unwrap();
+        let record = play_game(&config, 1, &config.player_a, &config.player_b).unwrap();
+        assert!(["black", "white", "draw"].contains(&record.winner.as_str()));
+        assert!(record.moves.len() <= config.max_moves as usize);
+        // 量子围棋开局第一手为量子落子
+        assert!(record.moves[0].quantum);
+        assert_eq!(record.moves[0].color, "black");
+    }
+}
@@ -0,0 +1,338 @@
+use crate::entity::Chessman;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+
+/// 前端与数据库使用的棋盘格式：坐标 "x,y" -> 棋子
+pub type WireBoard = HashMap<String, Chessman>;
+
+/// 支持的边长范围（长宽分别检查，可以是矩形棋盘）
+pub const MIN_SIZE: i32 = 3;
+pub const MAX_SIZE: i32 = 25;
+
+pub fn valid_dimensions(width: i32, height: i32) -> bool {
+    (MIN_SIZE..=MAX_SIZE).contains(&width) && (MIN_SIZE..=MAX_SIZE).contains(&height)
+}
+
+/// 棋子颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stone {
+    Black,
+    White,
+}
+
+impl Stone {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "black" => Some(Stone::Black),
+            "white" => Some(Stone::White),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stone::Black => "black",
+            Stone::White => "white",
+        }
+    }
+
+    pub fn opponent(&self) -> Self {
+        match self {
+            Stone::Black => Stone::White,
+            Stone::White => Stone::Black,
+        }
+    }
+}
+
+/// 棋盘坐标，从 1 开始，与前端的 "x,y" 一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl Point {
+    pub const fn new(x: u8, y: u8) -> Self {
+        Self { x, y }
+    }
+
+    /// 解析 "x,y"，不检查是否在棋盘范围内
+    pub fn parse(value: &str) -> Option<Self> {
+        let (x, y) = value.split_once(',')?;
+        Some(Self::new(x.trim().parse().ok()?, y.trim().parse().ok()?))
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+/// 序列化为 "x,y"
+impl Serialize for Point {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// 交叉点上的棋子：颜色，以及另一个棋盘上对应棋子的坐标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub stone: Stone,
+    pub brother: Point,
+}
+
+/// 单个棋盘：按 x 优先展开的定长数组，下标 (x - 1) * height + (y - 1)
+/// x 的范围为 1..=width，y 的范围为 1..=height
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Board {
+    width: u8,
+    height: u8,
+    cells: Vec<Option<Cell>>,
+}
+
+impl Board {
+    pub fn new(width: i32, height: i32) -> Self {
+        let width = width.clamp(1, MAX_SIZE) as u8;
+        let height = height.clamp(1, MAX_SIZE) as u8;
+        Self {
+            width,
+            height,
+            cells: vec![None; width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height as i32
+    }
+
+    /// 交叉点总数，用于按下标标记访问状态
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        (1..=self.width).contains(&p.x) && (1..=self.height).contains(&p.y)
+    }
+
+    /// 调用方需保证 p 在棋盘内
+    pub fn index(&self, p: Point) -> usize {
+        (p.x as usize - 1) * self.height as usize + (p.y as usize - 1)
+    }
+
+    pub fn get(&self, p: Point) -> Option<Cell> {
+        if self.contains(p) { self.cells[self.index(p)] } else { None }
+    }
+
+    pub fn stone(&self, p: Point) -> Option<Stone> {
+        self.get(p).map(|cell| cell.stone)
+    }
+
+    pub fn is_occupied(&self, p: Point) -> bool {
+        self.get(p).is_some()
+    }
+
+    /// 棋盘外的坐标直接忽略
+    pub fn set(&mut self, p: Point, cell: Cell) {
+        if self.contains(p) {
+            let index = self.index(p);
+            self.cells[index] = Some(cell);
+        }
+    }
+
+    pub fn remove(&mut self, p: Point) -> Option<Cell> {
+        if !self.contains(p) {
+            return None;
+        }
+        let index = self.index(p);
+        self.cells[index].take()
+    }
+
+    /// 棋盘上的棋子数
+    pub fn len(&self) -> usize {
+        self.cells.iter().filter(|cell| cell.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(|cell| cell.is_none())
+    }
+
+    /// 所有交叉点，顺序与下标一致
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let (width, height) = (self.width, self.height);
+        (1..=width).flat_map(move |x| (1..=height).map(move |y| Point::new(x, y)))
+    }
+
+    pub fn stones(&self) -> impl Iterator<Item = (Point, Cell)> + '_ {
+        self.points().zip(self.cells.iter()).filter_map(|(p, cell)| cell.map(|cell| (p, cell)))
+    }
+
+    /// 上下左右相邻点（顺序：左、右、下、上）
+    pub fn neighbors(&self, p: Point) -> impl Iterator<Item = Point> {
+        let (width, height) = (self.width, self.height);
+        [
+            (p.x > 1).then(|| Point::new(p.x - 1, p.y)),
+            (p.x < width).then(|| Point::new(p.x + 1, p.y)),
+            (p.y > 1).then(|| Point::new(p.x, p.y - 1)),
+            (p.y < height).then(|| Point::new(p.x, p.y + 1)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// 与 start 相连的同色棋子
+    pub fn group(&self, start: Point) -> Vec<Point> {
+        let Some(stone) = self.stone(start) else {
+            return Vec::new();
+        };
+        let mut visited = vec![false; self.area()];
+        let mut group = Vec::new();
+        let mut stack = vec![start];
+        visited[self.index(start)] = true;
+        while let Some(p) = stack.pop() {
+            group.push(p);
+            for neighbor in self.neighbors(p) {
+                let index = self.index(neighbor);
+                if !visited[index] && self.stone(neighbor) == Some(stone) {
+                    visited[index] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+        group
+    }
+
+    /// 棋块的气数
+    pub fn liberties(&self, group: &[Point]) -> usize {
+        let mut seen = vec![false; self.area()];
+        let mut count = 0;
+        for &p in group {
+            for neighbor in self.neighbors(p) {
+                let index = self.index(neighbor);
+                if !seen[index] && !self.is_occupied(neighbor) {
+                    seen[index] = true;
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// 转为接口使用的棋子结构
+    pub fn chessman(&self, p: Point) -> Option<Chessman> {
+        self.get(p).map(|cell| to_chessman(p, cell))
+    }
+
+    /// 从 "x,y" 格式转换，坐标或颜色非法的棋子被忽略
+    pub fn from_wire(board: &WireBoard, width: i32, height: i32) -> Self {
+        let mut result = Self::new(width, height);
+        for (key, ch) in board {
+            let Some(p) = Point::parse(&ch.position).or_else(|| Point::parse(key)) else {
+                continue;
+            };
+            let Some(stone) = Stone::parse(&ch.color) else {
+                continue;
+            };
+            let brother = Point::parse(&ch.brother).unwrap_or(p);
+            result.set(p, Cell { stone, brother });
+        }
+        result
+    }
+
+    pub fn to_wire(&self) -> WireBoard {
+        self.stones()
+            .map(|(p, cell)| (p.to_string(), to_chessman(p, cell)))
+            .collect()
+    }
+}
+
+fn to_chessman(p: Point, cell: Cell) -> Chessman {
+    Chessman {
+        position: p.to_string(),
+        color: cell.stone.as_str().to_string(),
+        brother: cell.brother.to_string(),
+    }
+}
+
+impl fmt::Debug for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.stones().map(|(p, cell)| (p.to_string(), cell)))
+            .finish()
+    }
+}
+
+/// 序列化为 "x,y" 格式，与前端保持一致
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_wire().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(stone: Stone, brother: Point) -> Cell {
+        Cell { stone, brother }
+    }
+
+    #[test]
+    fn test_wire_round_trip() {
+        let mut board = Board::new(9, 9);
+        board.set(Point::new(3, 3), cell(Stone::Black, Point::new(7, 7)));
+        board.set(Point::new(1, 9), cell(Stone::White, Point::new(1, 9)));
+
+        let wire = board.to_wire();
+        assert_eq!(wire["3,3"].color, "black");
+        assert_eq!(wire["3,3"].brother, "7,7");
+        assert_eq!(Board::from_wire(&wire, 9, 9), board);
+
+        // 超出棋盘的棋子被忽略
+        assert!(Board::from_wire(&wire, 9, 5).get(Point::new(1, 9)).is_none());
+    }
+
+    #[test]
+    fn test_neighbors_at_edges() {
+        let board = Board::new(9, 9);
+        assert_eq!(board.neighbors(Point::new(1, 1)).count(), 2);
+        assert_eq!(board.neighbors(Point::new(9, 5)).count(), 3);
+        assert_eq!(board.neighbors(Point::new(5, 5)).count(), 4);
+        assert!(!board.contains(Point::new(0, 3)));
+        assert!(!board.contains(Point::new(10, 3)));
+    }
+
+    #[test]
+    fn test_rectangular_board() {
+        let board = Board::new(7, 5);
+        assert_eq!(board.points().count(), 35);
+        assert!(board.contains(Point::new(7, 5)));
+        assert!(!board.contains(Point::new(5, 7)));
+        assert_eq!(board.neighbors(Point::new(7, 5)).count(), 2);
+        assert!(valid_dimensions(7, 5));
+        assert!(!valid_dimensions(2, 9));
+        assert!(!valid_dimensions(19, 26));
+    }
+
+    #[test]
+    fn test_group_and_liberties() {
+        let mut board = Board::new(9, 9);
+        for p in [Point::new(1, 1), Point::new(1, 2), Point::new(2, 2)] {
+            board.set(p, cell(Stone::Black, p));
+        }
+        board.set(Point::new(2, 1), cell(Stone::White, Point::new(2, 1)));
+
+        let group = board.group(Point::new(1, 1));
+        assert_eq!(group.len(), 3);
+        // 1,3 / 2,3 / 3,2
+        assert_eq!(board.liberties(&group), 3);
+        assert_eq!(board.liberties(&board.group(Point::new(2, 1))), 1);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::ai::{AIDifficulty, AIEngine};
+use crate::db::Database;
+use crate::engine::EngineChoice;
+use crate::rating::{ANCHOR_RD, anchor_rating};
+use crate::rules::Variant;
+use std::collections::HashMap;
+use tracing::info;
+use uuid::Uuid;
+
+/// 机器人账号的用户名前缀，注册时保留
+const BOT_PREFIX: &str = "bot-";
+const BOT_ENGINES: [AIEngine; 2] = [AIEngine::Mcts, AIEngine::Heuristic];
+const BOT_DIFFICULTIES: [AIDifficulty; 3] = [AIDifficulty::Beginner, AIDifficulty::Intermediate, AIDifficulty::Advanced];
+/// 所有评分池，见 rating::rating_pool
+const RATING_POOLS: [i32; 4] = [7, 9, 13, 19];
+
+/// 内置引擎在某个难度下的机器人用户名，如 "bot-mcts-advanced"
+pub fn bot_username(engine: AIEngine, difficulty: AIDifficulty) -> String {
+    format!("{}{}-{}", BOT_PREFIX, engine.as_str(), difficulty.as_str())
+}
+
+/// 以 bot- 开头的用户名留给机器人（不区分大小写）
+pub fn is_reserved_username(username: &str) -> bool {
+    username.to_ascii_lowercase().starts_with(BOT_PREFIX)
+}
+
+/// AI 机器人账号：每个内置引擎与难度一个。AI 对战以机器人为访客，
+/// 对局计入双方评分；机器人的评分固定为锚定值，人类评分以其为参照
+#[derive(Default)]
+pub struct BotAccounts {
+    bots: HashMap<(AIEngine, AIDifficulty), Uuid>,
+}
+
+impl BotAccounts {
+    /// 启动时创建机器人账号，并把各评分池的评分重置为锚定值
+    pub async fn ensure(db: &Database) -> Result<Self, sqlx::Error> {
+        let mut bots = HashMap::new();
+        for engine in BOT_ENGINES {
+            for difficulty in BOT_DIFFICULTIES {
+                let user = db.ensure_bot_user(&bot_username(engine, difficulty)).await?;
+                let rating = anchor_rating(engine, difficulty);
+                for model in RATING_POOLS {
+                    for variant in [Variant::Quantum, Variant::Classical] {
+                        db.set_anchored_ranking(&user.user_id, model, variant.as_str(), rating, ANCHOR_RD).await?;
+                    }
+                }
+                info!("Bot account {} anchored at {}", user.username, rating);
+                bots.insert((engine, difficulty), user.user_id);
+            }
+        }
+        Ok(Self { bots })
+    }
+
+    /// AI 对战房间的机器人；外部引擎没有机器人账号
+    pub fn bot_for(&self, engine: &EngineChoice, difficulty: AIDifficulty) -> Option<Uuid> {
+        match engine {
+            EngineChoice::Builtin(engine) => self.bots.get(&(*engine, difficulty)).copied(),
+            EngineChoice::External(_) => None,
+        }
+    }
+
+    pub fn is_bot(&self, user_id: Uuid) -> bool {
+        self.bots.values().any(|&id| id == user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bot_usernames_are_reserved() {
+        let name = bot_username(AIEngine::Mcts, AIDifficulty::Advanced);
+        assert_eq!(name, "bot-mcts-advanced");
+        assert!(is_reserved_username(&name));
+        assert!(is_reserved_username("BOT-anything"));
+        assert!(!is_reserved_username("robot-fan"));
+    }
+
+    #[test]
+    fn test_bot_for_builtin_engines_only() {
+        let (mcts, heuristic) = (Uuid::new_v4(), Uuid::new_v4());
+        let accounts = BotAccounts {
+            bots: HashMap::from([
+                ((AIEngine::Mcts, AIDifficulty::Beginner), mcts),
+                ((AIEngine::Heuristic, AIDifficulty::Beginner), heuristic),
+            ]),
+        };
+        assert_eq!(accounts.bot_for(&EngineChoice::Builtin(AIEngine::Mcts), AIDifficulty::Beginner), Some(mcts));
+        assert_eq!(accounts.bot_for(&EngineChoice::Builtin(AIEngine::Mcts), AIDifficulty::Advanced), None);
+        assert_eq!(accounts.bot_for(&EngineChoice::External("research".to_string()), AIDifficulty::Beginner), None);
+        assert!(accounts.is_bot(heuristic));
+        assert!(!accounts.is_bot(Uuid::new_v4()));
+    }
+}
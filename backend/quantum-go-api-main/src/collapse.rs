@@ -0,0 +1,119 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// 坍缩规则（随房间保存）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollapseRule {
+    /// 不坍缩：各棋盘得分取平均
+    #[default]
+    None,
+    /// 终局坍缩：双方连续停一手后抽取一个棋盘，只计该棋盘的得分
+    End,
+    /// 测量：对局中轮到的一方可随时发起测量；无人测量时终局坍缩
+    Measure,
+}
+
+impl CollapseRule {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(CollapseRule::None),
+            "end" => Some(CollapseRule::End),
+            "measure" => Some(CollapseRule::Measure),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CollapseRule::None => "none",
+            CollapseRule::End => "end",
+            CollapseRule::Measure => "measure",
+        }
+    }
+}
+
+/// 抽取用的随机种子：建房时只公布承诺，抽取后公开种子
+pub struct Seed {
+    pub seed: String,       // 32 字节随机数的十六进制串
+    pub commitment: String, // SHA-256(seed) 的十六进制串
+}
+
+impl Seed {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let seed = hex::encode(bytes);
+        Self {
+            commitment: commitment(&seed),
+            seed,
+        }
+    }
+}
+
+/// 种子的承诺：对种子的十六进制串（ASCII）做 SHA-256，
+/// 等价于 `printf %s <seed> | sha256sum`
+pub fn commitment(seed: &str) -> String {
+    hex::encode(Sha256::digest(seed.as_bytes()))
+}
+
+/// 公开的种子是否与建房时公布的承诺一致
+#[allow(dead_code)]
+pub fn verify(seed: &str, commitment_hex: &str) -> bool {
+    commitment(seed).eq_ignore_ascii_case(commitment_hex)
+}
+
+/// 由种子抽取棋盘下标：SHA-256("collapse:" + seed) 前 8 字节（大端）对棋盘数取模
+/// 棋盘数远小于 2^64，取模带来的偏差可以忽略
+pub fn draw_board(seed: &str, count: usize) -> usize {
+    if count <= 1 {
+        return 0;
+    }
+    let digest = Sha256::new()
+        .chain_update(b"collapse:")
+        .chain_update(seed.as_bytes())
+        .finalize();
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(head) % count as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commitment_matches_seed() {
+        let seed = Seed::generate();
+        assert_eq!(seed.seed.len(), 64);
+        assert_eq!(seed.commitment.len(), 64);
+        assert!(verify(&seed.seed, &seed.commitment));
+        assert!(!verify(&Seed::generate().seed, &seed.commitment));
+        // 已知值：sha256("abc")
+        assert_eq!(
+            commitment("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_draw_is_deterministic_and_in_range() {
+        for _ in 0..20 {
+            let seed = Seed::generate().seed;
+            let index = draw_board(&seed, 2);
+            assert!(index < 2);
+            assert_eq!(index, draw_board(&seed, 2));
+            assert!(draw_board(&seed, 3) < 3);
+        }
+        assert_eq!(draw_board("anything", 1), 0);
+    }
+
+    #[test]
+    fn test_collapse_rule_round_trip() {
+        for rule in [CollapseRule::None, CollapseRule::End, CollapseRule::Measure] {
+            assert_eq!(CollapseRule::parse(rule.as_str()), Some(rule));
+        }
+        assert_eq!(CollapseRule::parse("later"), None);
+    }
+}
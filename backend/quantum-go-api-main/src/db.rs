@@ -1,4 +1,4 @@
-use crate::entity::{RoomInfo, User, UserRanking, LeaderboardEntry};
+use crate::entity::{GameReviewRecord, RoomInfo, User, UserRanking, LeaderboardEntry};
 use bcrypt::{DEFAULT_COST, hash, verify};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::{Error, PgPool};
@@ -33,12 +33,14 @@ impl Database {
                 id SERIAL PRIMARY KEY,
                 user_id UUID NOT NULL UNIQUE,
                 username VARCHAR(255) NOT NULL UNIQUE,
-                password VARCHAR(255) NOT NULL
+                password VARCHAR(255) NOT NULL,
+                is_bot BOOLEAN NOT NULL DEFAULT FALSE
             )
             "#,
         )
         .execute(pool)
         .await?;
+        Self::add_column_if_missing(pool, "users", "is_bot", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
 
         // Create room_infos table
         sqlx::query(
@@ -58,27 +60,97 @@ impl Database {
                 white_lost INTEGER NOT NULL DEFAULT 0,
                 model INTEGER NOT NULL DEFAULT 9,
                 chessman_records JSONB NOT NULL DEFAULT '[]'::jsonb,
-                phase VARCHAR(50) DEFAULT 'BlackQuantum'
+                phase VARCHAR(50) DEFAULT 'BlackQuantum',
+                ko_rule VARCHAR(50) NOT NULL DEFAULT 'simple',
+                position_history JSONB NOT NULL DEFAULT '[]'::jsonb,
+                komi DOUBLE PRECISION NOT NULL DEFAULT 7.5,
+                rule_set VARCHAR(50) NOT NULL DEFAULT 'area',
+                scoring_state JSONB NOT NULL DEFAULT '{}'::jsonb,
+                handicap INTEGER NOT NULL DEFAULT 0,
+                height INTEGER,
+                variant VARCHAR(50) NOT NULL DEFAULT 'quantum',
+                quantum_pairs INTEGER NOT NULL DEFAULT 1,
+                collapse_rule VARCHAR(50) NOT NULL DEFAULT 'none',
+                seed_commitment VARCHAR(64),
+                collapse_seed VARCHAR(64),
+                revealed_seed VARCHAR(64),
+                collapsed_board INTEGER,
+                ai_difficulty VARCHAR(50),
+                ai_color VARCHAR(50),
+                ai_engine VARCHAR(50),
+                game_type VARCHAR(50) NOT NULL DEFAULT 'pvp',
+                hints BOOLEAN NOT NULL DEFAULT FALSE,
+                ai_seed VARCHAR(64)
             );
             "#,
         )
         .execute(pool)
         .await?;
 
-        // 检查并添加phase字段（如果不存在）
-        let result = sqlx::query(
-            "SELECT column_name FROM information_schema.columns WHERE table_name = 'room_infos' AND column_name = 'phase'"
-        )
-        .fetch_optional(pool)
-        .await?;
-
-        if result.is_none() {
-            println!("Adding phase column to room_infos table...");
-            sqlx::query("ALTER TABLE room_infos ADD COLUMN phase VARCHAR(50) DEFAULT 'BlackQuantum'")
+        // 检查并添加后续新增的字段（如果不存在）
+        Self::add_column_if_missing(pool, "room_infos", "phase", "VARCHAR(50) DEFAULT 'BlackQuantum'").await?;
+        Self::add_column_if_missing(pool, "room_infos", "ko_rule", "VARCHAR(50) NOT NULL DEFAULT 'simple'").await?;
+        Self::add_column_if_missing(pool, "room_infos", "position_history", "JSONB NOT NULL DEFAULT '[]'::jsonb").await?;
+        Self::add_column_if_missing(pool, "room_infos", "komi", "DOUBLE PRECISION NOT NULL DEFAULT 7.5").await?;
+        Self::add_column_if_missing(pool, "room_infos", "rule_set", "VARCHAR(50) NOT NULL DEFAULT 'area'").await?;
+        Self::add_column_if_missing(pool, "room_infos", "scoring_state", "JSONB NOT NULL DEFAULT '{}'::jsonb").await?;
+        Self::add_column_if_missing(pool, "room_infos", "handicap", "INTEGER NOT NULL DEFAULT 0").await?;
+        Self::add_column_if_missing(pool, "room_infos", "height", "INTEGER").await?;
+        Self::add_column_if_missing(pool, "room_infos", "variant", "VARCHAR(50) NOT NULL DEFAULT 'quantum'").await?;
+        Self::add_column_if_missing(pool, "room_infos", "quantum_pairs", "INTEGER NOT NULL DEFAULT 1").await?;
+        Self::add_column_if_missing(pool, "room_infos", "collapse_rule", "VARCHAR(50) NOT NULL DEFAULT 'none'").await?;
+        Self::add_column_if_missing(pool, "room_infos", "seed_commitment", "VARCHAR(64)").await?;
+        Self::add_column_if_missing(pool, "room_infos", "collapse_seed", "VARCHAR(64)").await?;
+        Self::add_column_if_missing(pool, "room_infos", "revealed_seed", "VARCHAR(64)").await?;
+        Self::add_column_if_missing(pool, "room_infos", "collapsed_board", "INTEGER").await?;
+        Self::add_column_if_missing(pool, "room_infos", "ai_difficulty", "VARCHAR(50)").await?;
+        Self::add_column_if_missing(pool, "room_infos", "ai_color", "VARCHAR(50)").await?;
+        Self::add_column_if_missing(pool, "room_infos", "ai_engine", "VARCHAR(50)").await?;
+        if Self::add_column_if_missing(pool, "room_infos", "game_type", "VARCHAR(50) NOT NULL DEFAULT 'pvp'").await? {
+            // 早期房间没有对局类型：AI 房间建房时以虚拟 AI 玩家（不在 users 表中）作为访客
+            sqlx::query(
+                r#"
+                UPDATE room_infos SET
+                    game_type = 'ai',
+                    ai_engine = COALESCE(ai_engine, 'mcts'),
+                    ai_difficulty = COALESCE(ai_difficulty, 'intermediate'),
+                    ai_color = COALESCE(ai_color, 'white')
+                WHERE ai_color IS NOT NULL
+                    OR (visitor_id IS NOT NULL AND visitor_id NOT IN (SELECT user_id FROM users))
+                "#,
+            )
+            .execute(pool)
+            .await?;
+        }
+        if Self::add_column_if_missing(pool, "room_infos", "hints", "BOOLEAN NOT NULL DEFAULT FALSE").await? {
+            // 已有的 AI 对战房间允许提示
+            sqlx::query("UPDATE room_infos SET hints = TRUE WHERE game_type = 'ai'")
                 .execute(pool)
                 .await?;
-            println!("Phase column added successfully");
         }
+        if Self::add_column_if_missing(pool, "room_infos", "ai_seed", "VARCHAR(64)").await? {
+            // 已有的 AI 对战房间补上种子，之后的 AI 落子可以复现
+            sqlx::query("UPDATE room_infos SET ai_seed = md5(random()::text) || md5(random()::text) WHERE game_type = 'ai'")
+                .execute(pool)
+                .await?;
+        }
+
+        // 对局复盘
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS game_reviews (
+                id SERIAL PRIMARY KEY,
+                room_id UUID NOT NULL UNIQUE,
+                status VARCHAR(50) NOT NULL DEFAULT 'pending',
+                review JSONB NOT NULL DEFAULT '{}'::jsonb,
+                error TEXT,
+                created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
 
         // Create user_rankings table
         sqlx::query(
@@ -87,6 +159,7 @@ impl Database {
                 id SERIAL PRIMARY KEY,
                 user_id UUID NOT NULL,
                 model INTEGER NOT NULL,
+                variant VARCHAR(50) NOT NULL DEFAULT 'quantum',
                 rating DOUBLE PRECISION NOT NULL DEFAULT 1500.0,
                 rd DOUBLE PRECISION NOT NULL DEFAULT 350.0,
                 vol DOUBLE PRECISION NOT NULL DEFAULT 0.06,
@@ -95,17 +168,52 @@ impl Database {
                 losses INTEGER NOT NULL DEFAULT 0,
                 draws INTEGER NOT NULL DEFAULT 0,
                 created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                UNIQUE(user_id, model)
+                updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
             )
             "#,
         )
         .execute(pool)
         .await?;
 
+        // 经典围棋使用独立的评分池：唯一约束由 (user_id, model) 扩展为 (user_id, model, variant)
+        Self::add_column_if_missing(pool, "user_rankings", "variant", "VARCHAR(50) NOT NULL DEFAULT 'quantum'").await?;
+        sqlx::query("ALTER TABLE user_rankings DROP CONSTRAINT IF EXISTS user_rankings_user_id_model_key")
+            .execute(pool)
+            .await?;
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS user_rankings_user_model_variant ON user_rankings (user_id, model, variant)",
+        )
+        .execute(pool)
+        .await?;
+
         Ok(())
     }
 
+    /// 列不存在时添加，返回是否新增（新增时可顺带迁移已有数据）
+    async fn add_column_if_missing(
+        pool: &PgPool,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<bool, Error> {
+        let result = sqlx::query(
+            "SELECT column_name FROM information_schema.columns WHERE table_name = $1 AND column_name = $2"
+        )
+        .bind(table)
+        .bind(column)
+        .fetch_optional(pool)
+        .await?;
+
+        if result.is_none() {
+            println!("Adding {} column to {} table...", column, table);
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(pool)
+                .await?;
+            println!("{} column added successfully", column);
+        }
+        Ok(result.is_none())
+    }
+
     // Actively used user operations
     pub async fn create_user(&self, username: &str, password: &str) -> Result<User, Error> {
         let user_id = Uuid::new_v4();
@@ -120,14 +228,32 @@ impl Database {
         .fetch_one(&self.pool)
         .await?;
 
-        // 为新用户创建默认评分记录
+        // 为新用户创建默认评分记录（量子围棋）
         for model in [9, 13, 19] {
-            self.create_user_ranking(&user_id, model).await?;
+            self.create_user_ranking(&user_id, model, "quantum").await?;
         }
 
         Ok(user)
     }
 
+    /// 机器人账号：不存在时创建。密码字段不是有效的哈希，无法登录；
+    /// 同名的账号不是机器人时返回错误
+    pub async fn ensure_bot_user(&self, username: &str) -> Result<User, Error> {
+        sqlx::query(
+            "INSERT INTO users (user_id, username, password, is_bot) VALUES ($1, $2, '!', TRUE) ON CONFLICT (username) DO NOTHING",
+        )
+        .bind(Uuid::new_v4())
+        .bind(username)
+        .execute(&self.pool)
+        .await?;
+
+        let user = self.get_user_by_username(username).await?;
+        if !user.is_bot {
+            return Err(Error::Protocol(format!("Username {} belongs to a human account", username)));
+        }
+        Ok(user)
+    }
+
     pub async fn verify_user(&self, username: &str, password: &str) -> Result<User, Error> {
         let user = self.get_user_by_username(username).await?;
 
@@ -191,8 +317,10 @@ impl Database {
         sqlx::query_as::<_, RoomInfo>(
             r#"
             INSERT INTO room_infos (
-                room_id, owner_id, visitor_id, status, round, winner, board, countdown, moves, black_lost, white_lost, model, chessman_records
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING *
+                room_id, owner_id, visitor_id, status, round, winner, board, countdown, moves, black_lost, white_lost, model, chessman_records,
+                ko_rule, position_history, komi, rule_set, handicap, height, variant, quantum_pairs,
+                collapse_rule, seed_commitment, collapse_seed, ai_difficulty, ai_color, ai_engine, game_type, hints, ai_seed
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30) RETURNING *
             "#,
         )
         .bind(room_info.room_id)
@@ -208,6 +336,23 @@ impl Database {
         .bind(room_info.white_lost)
         .bind(room_info.model)
         .bind(&room_info.chessman_records)
+        .bind(&room_info.ko_rule)
+        .bind(&room_info.position_history)
+        .bind(room_info.komi)
+        .bind(&room_info.rule_set)
+        .bind(room_info.handicap)
+        .bind(room_info.height)
+        .bind(&room_info.variant)
+        .bind(room_info.quantum_pairs)
+        .bind(&room_info.collapse_rule)
+        .bind(&room_info.seed_commitment)
+        .bind(&room_info.collapse_seed)
+        .bind(&room_info.ai_difficulty)
+        .bind(&room_info.ai_color)
+        .bind(&room_info.ai_engine)
+        .bind(&room_info.game_type)
+        .bind(room_info.hints)
+        .bind(&room_info.ai_seed)
         .fetch_one(&self.pool)
         .await
     }
@@ -234,8 +379,12 @@ impl Database {
                 white_lost = $9,
                 model = $10,
                 chessman_records = $11,
-                phase = $12
-            WHERE id = $13 RETURNING *
+                phase = $12,
+                position_history = $13,
+                scoring_state = $14,
+                revealed_seed = $15,
+                collapsed_board = $16
+            WHERE id = $17 RETURNING *
             "#,
         )
         .bind(room_info.visitor_id)       // $1
@@ -250,7 +399,11 @@ impl Database {
         .bind(room_info.model)            // $10
         .bind(&room_info.chessman_records)// $11
         .bind(&room_info.phase)           // $12 <- 新增 phase 字段
-        .bind(room_info.id)               // $13
+        .bind(&room_info.position_history)// $13
+        .bind(&room_info.scoring_state)   // $14
+        .bind(&room_info.revealed_seed)   // $15
+        .bind(room_info.collapsed_board)  // $16
+        .bind(room_info.id)               // $17
         .fetch_one(&self.pool)
         .await
     }
@@ -275,26 +428,86 @@ impl Database {
     }
 
     // 新增：用户评分相关操作
-    pub async fn create_user_ranking(&self, user_id: &Uuid, model: i32) -> Result<UserRanking, Error> {
+    /// 登记房间的复盘任务；已经登记过时返回 false
+    pub async fn create_review(&self, room_id: Uuid) -> Result<bool, Error> {
+        let result = sqlx::query("INSERT INTO game_reviews (room_id) VALUES ($1) ON CONFLICT (room_id) DO NOTHING")
+            .bind(room_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_review(&self, room_id: Uuid) -> Result<Option<GameReviewRecord>, Error> {
+        sqlx::query_as::<_, GameReviewRecord>("SELECT * FROM game_reviews WHERE room_id = $1")
+            .bind(room_id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn finish_review(
+        &self,
+        room_id: Uuid,
+        status: &str,
+        review: &serde_json::Value,
+        error: Option<&str>,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE game_reviews SET status = $1, review = $2, error = $3, updated_at = NOW() WHERE room_id = $4")
+            .bind(status)
+            .bind(review)
+            .bind(error)
+            .bind(room_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create_user_ranking(&self, user_id: &Uuid, model: i32, variant: &str) -> Result<UserRanking, Error> {
         sqlx::query_as::<_, UserRanking>(
             r#"
-            INSERT INTO user_rankings (user_id, model, rating, rd, vol, games_played, wins, losses, draws)
-            VALUES ($1, $2, 1500.0, 350.0, 0.06, 0, 0, 0, 0)
+            INSERT INTO user_rankings (user_id, model, variant, rating, rd, vol, games_played, wins, losses, draws)
+            VALUES ($1, $2, $3, 1500.0, 350.0, 0.06, 0, 0, 0, 0)
             RETURNING *
             "#,
         )
         .bind(user_id)
         .bind(model)
+        .bind(variant)
         .fetch_one(&self.pool)
         .await
     }
 
-    pub async fn get_user_ranking(&self, user_id: &Uuid, model: i32) -> Result<UserRanking, Error> {
+    /// 机器人的评分固定为锚定值，只保留对局统计
+    pub async fn set_anchored_ranking(
+        &self,
+        user_id: &Uuid,
+        model: i32,
+        variant: &str,
+        rating: f64,
+        rd: f64,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_rankings (user_id, model, variant, rating, rd) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, model, variant) DO UPDATE SET rating = $4, rd = $5, vol = 0.06, updated_at = NOW()
+            "#,
+        )
+        .bind(user_id)
+        .bind(model)
+        .bind(variant)
+        .bind(rating)
+        .bind(rd)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_user_ranking(&self, user_id: &Uuid, model: i32, variant: &str) -> Result<UserRanking, Error> {
         sqlx::query_as::<_, UserRanking>(
-            "SELECT * FROM user_rankings WHERE user_id = $1 AND model = $2"
+            "SELECT * FROM user_rankings WHERE user_id = $1 AND model = $2 AND variant = $3"
         )
         .bind(user_id)
         .bind(model)
+        .bind(variant)
         .fetch_one(&self.pool)
         .await
     }
@@ -304,7 +517,7 @@ impl Database {
             r#"
             UPDATE user_rankings SET
                 rating = $1, rd = $2, vol = $3, games_played = $4, wins = $5, losses = $6, draws = $7, updated_at = NOW()
-            WHERE user_id = $8 AND model = $9 RETURNING *
+            WHERE user_id = $8 AND model = $9 AND variant = $10 RETURNING *
             "#,
         )
         .bind(ranking.rating)
@@ -316,15 +529,18 @@ impl Database {
         .bind(ranking.draws)
         .bind(ranking.user_id)
         .bind(ranking.model)
+        .bind(&ranking.variant)
         .fetch_one(&self.pool)
         .await
     }
 
-    pub async fn get_leaderboard(&self, model: i32, limit: i32) -> Result<Vec<LeaderboardEntry>, Error> {
+    /// 排行榜：下过棋的玩家，以及评分作为参照的机器人
+    pub async fn get_leaderboard(&self, model: i32, variant: &str, limit: i32) -> Result<Vec<LeaderboardEntry>, Error> {
         let rows = sqlx::query(
             r#"
             SELECT
                 u.username,
+                u.is_bot,
                 ur.rating,
                 ur.rd,
                 ur.games_played,
@@ -333,12 +549,13 @@ impl Database {
                 ur.draws
             FROM user_rankings ur
             JOIN users u ON ur.user_id = u.user_id
-            WHERE ur.model = $1 AND ur.games_played > 0
+            WHERE ur.model = $1 AND ur.variant = $2 AND (ur.games_played > 0 OR u.is_bot)
             ORDER BY ur.rating DESC
-            LIMIT $2
+            LIMIT $3
             "#
         )
         .bind(model)
+        .bind(variant)
         .bind(limit)
         .fetch_all(&self.pool)
         .await?;
@@ -347,6 +564,7 @@ impl Database {
         for row in rows {
             let entry = LeaderboardEntry {
                 username: row.get::<String, _>("username"),
+                is_bot: row.get::<bool, _>("is_bot"),
                 rating: row.get::<f64, _>("rating"),
                 rd: row.get::<f64, _>("rd"),
                 games_played: row.get::<i32, _>("games_played"),
@@ -364,18 +582,12 @@ impl Database {
 // Helper functions for password hashing
 fn hash_password(password: &str) -> Result<String, Error> {
     hash(password, DEFAULT_COST).map_err(|e| {
-        Error::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            e.to_string(),
-        ))
+        Error::Io(std::io::Error::other(e.to_string()))
     })
 }
 
 fn verify_password(password: &str, hash: &str) -> Result<bool, Error> {
     verify(password, hash).map_err(|e| {
-        Error::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            e.to_string(),
-        ))
+        Error::Io(std::io::Error::other(e.to_string()))
     })
 }
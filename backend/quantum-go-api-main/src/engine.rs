@@ -0,0 +1,514 @@
+//! 可插拔的选点引擎：内置 AI 与外部引擎实现同一个 MoveGenerator 接口，
+//! 房间按 ai_engine 字段选用，处理函数不关心具体实现。
+//!
+//! 外部引擎以子进程运行，经标准输入输出逐行交换 JSON（任何语言都可以实现）：
+//! - 请求：`{"state": {...}, "time_limit_ms": 1500, "seed": 42}`，state 为 QuantumBoardState 的序列化，
+//!   轮到 state.current_player 落子；time_limit_ms 为本手的思考时间，没有限制时省略；
+//!   seed 为本手的随机种子，引擎按它选点时落子可以复现，没有种子时省略
+//! - 应答：`{"position": "3,4", "quantum": false, "confidence": 0.8}`，
+//!   position 为 "x,y"、"pass"（停一手）或 "none"（无需落子），quantum 与 confidence 可省略
+//!
+//! 进程在第一次请求时启动并常驻。读写出错、应答无法解析、超过思考时间（另有宽限）未应答
+//! 或请求被取消时结束进程，下一次请求重新启动。
+
+use crate::ai::{AIDifficulty, AIEngine, AIMove, QuantumBoardState, QuantumPhase, SimpleQuantumAI};
+use crate::board::{Point, Stone};
+use crate::mcts::SearchTree;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 外部引擎登记的环境变量，格式为 "name=command args;name2=command2 args"
+pub const ENGINES_ENV: &str = "AI_ENGINES";
+/// 房间 ai_engine 字段中外部引擎的前缀，如 "external:katago-q"
+const EXTERNAL_PREFIX: &str = "external:";
+/// 外部引擎名称的最大长度（ai_engine 列为 VARCHAR(50)）
+const MAX_NAME_LEN: usize = 32;
+/// 外部引擎超过思考时间后的宽限（进程通信与序列化的开销）
+const EXTERNAL_GRACE: Duration = Duration::from_secs(2);
+/// 等待外部引擎应答时检查取消信号的间隔
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+/// 内置引擎的版本，随每一手 AI 落子记录；复现旧的落子需要同一版本的代码
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 房间第 move_number 手（落子前的手数）的随机种子：
+/// SHA-256("ai:" + 房间种子 + ":" + 手数) 前 8 字节（大端）。
+/// 房间种子保密，公开已下各手的种子不会泄露之后的种子
+pub fn move_seed(room_seed: &str, move_number: i32) -> u64 {
+    digest_seed(
+        Sha256::new()
+            .chain_update(b"ai:")
+            .chain_update(room_seed.as_bytes())
+            .chain_update(format!(":{}", move_number).as_bytes()),
+    )
+}
+
+/// AI 落子后在玩家回合思考（pondering）的随机种子，由这一手的种子导出：
+/// SHA-256("ponder:" + 种子的 16 位十六进制) 前 8 字节（大端），复现时可由落子记录算出
+pub fn ponder_seed(move_seed: u64) -> u64 {
+    digest_seed(Sha256::new().chain_update(b"ponder:").chain_update(format!("{:016x}", move_seed).as_bytes()))
+}
+
+fn digest_seed(hasher: Sha256) -> u64 {
+    let digest = hasher.finalize();
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(head)
+}
+
+/// 取消信号：置位后搜索尽快结束，结果不再使用
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// 是否为同一个信号（克隆得到的信号相同）
+    pub fn same(&self, other: &CancelToken) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// 跨回合复用的搜索树：选点前放入对方思考时积累的树，选点后取回继续思考
+#[derive(Debug, Clone, Default)]
+pub struct TreeSlot(Arc<Mutex<Option<SearchTree>>>);
+
+impl TreeSlot {
+    pub fn new(tree: Option<SearchTree>) -> Self {
+        Self(Arc::new(Mutex::new(tree)))
+    }
+
+    pub fn take(&self) -> Option<SearchTree> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+
+    pub fn put(&self, tree: SearchTree) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(tree);
+    }
+}
+
+/// 一次选点的限制
+#[derive(Debug, Clone, Default)]
+pub struct MoveLimits {
+    pub time_limit: Option<Duration>, // 思考时间上限，为空时按引擎自身的预算
+    pub cancel: CancelToken,
+    pub tree: TreeSlot, // 可复用的搜索树，只有内置 MCTS 使用
+    pub seed: Option<u64>, // 随机种子，为空时每次不同
+}
+
+impl MoveLimits {
+    /// 选点使用的随机数生成器，有种子时可复现
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+/// 选点引擎
+pub trait MoveGenerator: Send + Sync {
+    /// 引擎名称，用于日志与落子记录
+    fn name(&self) -> String;
+
+    /// 引擎版本，随 AI 落子记录；服务端不知道外部引擎的版本
+    fn version(&self) -> String {
+        "unknown".to_string()
+    }
+
+    /// 为当前局面选一手；不轮到该引擎执子时返回 "waiting"。
+    /// 到达时间上限时返回当前最好的一手，被取消时尽快返回
+    fn generate(&self, state: &QuantumBoardState, limits: &MoveLimits) -> Result<AIMove, Box<dyn Error + Send + Sync>>;
+}
+
+impl MoveGenerator for SimpleQuantumAI {
+    fn name(&self) -> String {
+        format!("{}:{}", self.engine.as_str(), self.difficulty.as_str())
+    }
+
+    fn version(&self) -> String {
+        ENGINE_VERSION.to_string()
+    }
+
+    fn generate(&self, state: &QuantumBoardState, limits: &MoveLimits) -> Result<AIMove, Box<dyn Error + Send + Sync>> {
+        self.get_next_move_within(state, limits)
+    }
+}
+
+/// 房间选用的引擎：内置引擎，或在 AI_ENGINES 中登记的外部引擎
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineChoice {
+    Builtin(AIEngine),
+    External(String),
+}
+
+impl Default for EngineChoice {
+    fn default() -> Self {
+        EngineChoice::Builtin(AIEngine::default())
+    }
+}
+
+impl EngineChoice {
+    /// "mcts" / "heuristic" / "external:<name>"
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.strip_prefix(EXTERNAL_PREFIX) {
+            Some(name) if valid_name(name) => Some(EngineChoice::External(name.to_string())),
+            Some(_) => None,
+            None => AIEngine::parse(value).map(EngineChoice::Builtin),
+        }
+    }
+
+    pub fn as_string(&self) -> String {
+        match self {
+            EngineChoice::Builtin(engine) => engine.as_str().to_string(),
+            EngineChoice::External(name) => format!("{}{}", EXTERNAL_PREFIX, name),
+        }
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// 服务端可用的外部引擎
+#[derive(Default)]
+pub struct EngineRegistry {
+    external: HashMap<String, Arc<ExternalEngine>>,
+}
+
+impl EngineRegistry {
+    /// 从环境变量 AI_ENGINES 读取，未设置时没有外部引擎
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(&std::env::var(ENGINES_ENV).unwrap_or_default())
+    }
+
+    /// 解析 "name=command args;name2=command2 args"，参数按空白分隔
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut external = HashMap::new();
+        for entry in spec.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (name, command) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid engine entry: {}", entry))?;
+            let name = name.trim();
+            if !valid_name(name) {
+                return Err(format!("Invalid engine name: {}", name));
+            }
+            let engine = ExternalEngine::new(name, command)?;
+            if external.insert(name.to_string(), Arc::new(engine)).is_some() {
+                return Err(format!("Duplicate engine name: {}", name));
+            }
+        }
+        Ok(Self { external })
+    }
+
+    /// 已登记的外部引擎名称
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.external.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// 引擎在本服务端是否可用（内置引擎总是可用）
+    pub fn contains(&self, engine: &EngineChoice) -> bool {
+        match engine {
+            EngineChoice::Builtin(_) => true,
+            EngineChoice::External(name) => self.external.contains_key(name),
+        }
+    }
+
+    /// 按房间设置创建执 color 的 AI 玩家
+    pub fn player(
+        &self,
+        engine: &EngineChoice,
+        difficulty: AIDifficulty,
+        color: Stone,
+    ) -> Result<Box<dyn MoveGenerator>, String> {
+        match engine {
+            EngineChoice::Builtin(engine) => Ok(Box::new(
+                SimpleQuantumAI::new(difficulty).with_engine(*engine).with_color(color),
+            )),
+            EngineChoice::External(name) => {
+                let engine = self
+                    .external
+                    .get(name)
+                    .ok_or_else(|| format!("AI engine {} is not available on this server", name))?;
+                Ok(Box::new(ExternalPlayer { engine: Arc::clone(engine), color }))
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExternalRequest<'a> {
+    state: &'a QuantumBoardState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_limit_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ExternalReply {
+    position: String,
+    #[serde(default)]
+    quantum: bool,
+    #[serde(default)]
+    confidence: f64,
+}
+
+struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<io::Result<String>>, // 读取线程转发的输出行，进程退出时断开
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// 以子进程运行的外部引擎，同一时间只处理一个请求
+pub struct ExternalEngine {
+    name: String,
+    program: String,
+    args: Vec<String>,
+    process: Mutex<Option<EngineProcess>>,
+}
+
+impl ExternalEngine {
+    pub fn new(name: &str, command: &str) -> Result<Self, String> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        let program = words.next().ok_or_else(|| format!("Missing command for engine {}", name))?;
+        Ok(Self {
+            name: name.to_string(),
+            program,
+            args: words.collect(),
+            process: Mutex::new(None),
+        })
+    }
+
+    fn spawn(&self) -> Result<EngineProcess, Box<dyn Error + Send + Sync>> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("Failed to start engine {}: {}", self.name, e))?;
+        let stdin = child.stdin.take().ok_or("engine stdin unavailable")?;
+        let stdout = child.stdout.take().ok_or("engine stdout unavailable")?;
+        // 在单独的线程中阻塞读取，请求方可以按时间限制与取消信号放弃等待
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(EngineProcess { child, stdin, lines })
+    }
+
+    /// 发送一次请求；出错时结束进程，下一次请求重新启动
+    fn request(&self, state: &QuantumBoardState, limits: &MoveLimits) -> Result<ExternalReply, Box<dyn Error + Send + Sync>> {
+        let mut process = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+        let running = match process.take() {
+            Some(running) => running,
+            None => self.spawn()?,
+        };
+        let running = process.insert(running);
+        let reply = exchange(running, state, limits);
+        if reply.is_err() {
+            *process = None;
+        }
+        reply.map_err(|e| format!("Engine {} failed: {}", self.name, e).into())
+    }
+}
+
+fn exchange(
+    process: &mut EngineProcess,
+    state: &QuantumBoardState,
+    limits: &MoveLimits,
+) -> Result<ExternalReply, Box<dyn Error + Send + Sync>> {
+    let time_limit_ms = limits.time_limit.map(|limit| limit.as_millis());
+    let request = serde_json::to_string(&ExternalRequest { state, time_limit_ms, seed: limits.seed })?;
+    writeln!(process.stdin, "{}", request)?;
+    process.stdin.flush()?;
+
+    let deadline = limits.time_limit.map(|limit| Instant::now() + limit + EXTERNAL_GRACE);
+    loop {
+        if limits.cancel.is_cancelled() {
+            return Err("cancelled".into());
+        }
+        let wait = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(left) => left.min(CANCEL_POLL),
+                None => return Err("timed out".into()),
+            },
+            None => CANCEL_POLL,
+        };
+        match process.lines.recv_timeout(wait) {
+            Ok(line) => return Ok(serde_json::from_str(line?.trim())?),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Err("engine exited".into()),
+        }
+    }
+}
+
+/// 房间中执某一方的外部引擎
+struct ExternalPlayer {
+    engine: Arc<ExternalEngine>,
+    color: Stone,
+}
+
+impl MoveGenerator for ExternalPlayer {
+    fn name(&self) -> String {
+        format!("{}{}", EXTERNAL_PREFIX, self.engine.name)
+    }
+
+    fn generate(&self, state: &QuantumBoardState, limits: &MoveLimits) -> Result<AIMove, Box<dyn Error + Send + Sync>> {
+        if state.quantum_phase == QuantumPhase::Entanglement {
+            return Ok(AIMove::none("none"));
+        }
+        if state.current_player != self.color {
+            return Ok(AIMove::waiting());
+        }
+
+        let reply = self.engine.request(state, limits)?;
+        match reply.position.as_str() {
+            "none" => return Ok(AIMove::none(self.color.as_str())),
+            "pass" => return Ok(AIMove { confidence: reply.confidence, ..AIMove::pass(self.color.as_str()) }),
+            _ => {}
+        }
+        let position = Point::parse(&reply.position)
+            .filter(|&p| state.boards[0].contains(p))
+            .ok_or_else(|| format!("Engine {} returned an invalid position: {}", self.engine.name, reply.position))?;
+        Ok(AIMove {
+            position: position.to_string(),
+            color: self.color.as_str().to_string(),
+            confidence: reply.confidence,
+            quantum: reply.quantum,
+            playouts: None,
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::rules::QuantumPosition;
+
+    fn state(to_move: Stone) -> QuantumBoardState {
+        let position = QuantumPosition::new(5, 5);
+        let mut state = QuantumBoardState::from_position(&position, 7.5);
+        state.current_player = to_move;
+        state
+    }
+
+    fn shell_engine(script: &str) -> EngineRegistry {
+        let mut registry = EngineRegistry::default();
+        let engine = ExternalEngine {
+            name: "test".to_string(),
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            process: Mutex::new(None),
+        };
+        registry.external.insert("test".to_string(), Arc::new(engine));
+        registry
+    }
+
+    #[test]
+    fn test_engine_choice_round_trip() {
+        for value in ["mcts", "heuristic", "external:my-engine_2"] {
+            assert_eq!(EngineChoice::parse(value).unwrap().as_string(), value);
+        }
+        assert_eq!(EngineChoice::parse("heuristic"), Some(EngineChoice::Builtin(AIEngine::Heuristic)));
+        assert!(EngineChoice::parse("external:").is_none());
+        assert!(EngineChoice::parse("external:bad name").is_none());
+        assert!(EngineChoice::parse("random").is_none());
+    }
+
+    #[test]
+    fn test_registry_parse() {
+        let registry = EngineRegistry::parse("py = python3 engine.py --fast; ; rust=./engine").unwrap();
+        assert_eq!(registry.names(), vec!["py", "rust"]);
+        assert_eq!(registry.external["py"].args, vec!["engine.py", "--fast"]);
+        assert!(registry.contains(&EngineChoice::External("py".to_string())));
+        assert!(!registry.contains(&EngineChoice::External("other".to_string())));
+        assert!(registry.player(&EngineChoice::External("other".to_string()), AIDifficulty::Beginner, Stone::White).is_err());
+
+        assert!(EngineRegistry::parse("py").is_err());
+        assert!(EngineRegistry::parse("py=").is_err());
+        assert!(EngineRegistry::parse("a=x;a=y").is_err());
+        assert!(EngineRegistry::parse("").unwrap().names().is_empty());
+    }
+
+    #[test]
+    fn test_move_seed_is_stable_per_room_and_move() {
+        let room = "a".repeat(64);
+        assert_eq!(move_seed(&room, 3), move_seed(&room, 3));
+        assert_ne!(move_seed(&room, 3), move_seed(&room, 4));
+        assert_ne!(move_seed(&room, 3), move_seed(&"b".repeat(64), 3));
+        assert_ne!(ponder_seed(move_seed(&room, 3)), move_seed(&room, 3));
+
+        use rand::Rng;
+        let limits = MoveLimits { seed: Some(move_seed(&room, 3)), ..MoveLimits::default() };
+        assert_eq!(limits.rng().gen::<u64>(), limits.rng().gen::<u64>());
+    }
+
+    #[test]
+    fn test_external_engine_answers_and_waits() {
+        let registry = shell_engine(r#"while read line; do echo '{"position": "2,3", "quantum": true}'; done"#);
+        let engine = EngineChoice::External("test".to_string());
+        let player = registry.player(&engine, AIDifficulty::Beginner, Stone::White).unwrap();
+        assert_eq!(player.name(), "external:test");
+
+        assert_eq!(player.generate(&state(Stone::Black), &MoveLimits::default()).unwrap().position, "waiting");
+        for _ in 0..2 {
+            let mv = player.generate(&state(Stone::White), &MoveLimits::default()).unwrap();
+            assert_eq!((mv.position.as_str(), mv.color.as_str(), mv.quantum), ("2,3", "white", true));
+        }
+    }
+
+    #[test]
+    fn test_external_engine_errors_and_restarts() {
+        // 每个进程只应答一次：第二次请求时进程已退出，出错后第三次请求重新启动
+        let registry = shell_engine(r#"read line; echo '{"position": "9,9"}'"#);
+        let engine = EngineChoice::External("test".to_string());
+        let player = registry.player(&engine, AIDifficulty::Beginner, Stone::White).unwrap();
+
+        assert!(player.generate(&state(Stone::White), &MoveLimits::default()).unwrap_err().to_string().contains("invalid position"));
+        assert!(player.generate(&state(Stone::White), &MoveLimits::default()).is_err());
+        assert!(player.generate(&state(Stone::White), &MoveLimits::default()).unwrap_err().to_string().contains("invalid position"));
+    }
+
+    #[test]
+    fn test_builtin_player() {
+        let registry = EngineRegistry::default();
+        let player = registry
+            .player(&EngineChoice::Builtin(AIEngine::Heuristic), AIDifficulty::Beginner, Stone::Black)
+            .unwrap();
+        assert_eq!(player.name(), "heuristic:beginner");
+        assert_eq!(player.generate(&state(Stone::White), &MoveLimits::default()).unwrap().position, "waiting");
+    }
+}
@@ -14,7 +14,7 @@ pub struct Room {
     pub user2: Option<WsSender>,
 }
 
-#[derive(Clone, Deserialize, Serialize, FromRow, Debug)]
+#[derive(Clone, Default, Deserialize, Serialize, FromRow, Debug)]
 pub struct RoomInfo {
     #[serde(skip_serializing)]
     pub id: i32,
@@ -29,9 +29,43 @@ pub struct RoomInfo {
     pub moves: i32,
     pub black_lost: i32,
     pub white_lost: i32,
-    pub model: i32,
+    pub model: i32,            // 棋盘宽度（方形棋盘即边长）
     pub chessman_records: serde_json::Value,
     pub phase: Option<String>, // 量子阶段字段，存储为字符串
+    pub ko_rule: String,       // 打劫规则："simple" / "positional_superko"
+    pub position_history: serde_json::Value, // 历次局面哈希（十六进制字符串数组）
+    pub komi: f64,             // 贴目
+    pub rule_set: String,      // 计分规则："area" / "territory"
+    pub scoring_state: serde_json::Value, // 数子阶段的死子标记与双方确认
+    pub handicap: i32,         // 让子数（0 为分先）
+    pub height: Option<i32>,   // 矩形棋盘的高度，为空时与 model 相同
+    pub variant: String,       // 对局变体："quantum" / "classical"
+    pub quantum_pairs: i32,    // 量子对数（含开局一对），经典围棋为 0
+    pub collapse_rule: String, // 坍缩规则："none" / "end" / "measure"
+    pub seed_commitment: Option<String>, // 建房时公布的随机种子承诺 SHA-256(seed)
+    #[serde(skip_serializing)]
+    pub collapse_seed: Option<String>,   // 随机种子，抽取前保密
+    pub revealed_seed: Option<String>,   // 抽取后公开的种子
+    pub collapsed_board: Option<i32>,    // 抽中的棋盘下标，未坍缩时为空
+    pub game_type: String,               // 对局类型："pvp" / "ai"
+    pub ai_engine: Option<String>,       // AI 选点方式："mcts" / "heuristic"，人人对战为空
+    pub ai_difficulty: Option<String>,   // AI 对战的难度："beginner" / "intermediate" / "advanced"
+    pub ai_color: Option<String>,        // AI 执子颜色，人人对战为空
+    pub hints: bool,                     // 对局中能否请求提示（计分的人人对战默认关闭），终局后总可以
+    #[serde(skip_serializing)]
+    pub ai_seed: Option<String>,         // AI 对战的随机种子，各手的种子由它与手数导出，保密
+}
+
+impl RoomInfo {
+    /// 棋盘的 (宽, 高)
+    pub fn board_size(&self) -> (i32, i32) {
+        (self.model, self.height.unwrap_or(self.model))
+    }
+
+    /// 房主执子颜色：默认执黑，AI 执黑时房主执白
+    pub fn owner_color(&self) -> &'static str {
+        if self.ai_color.as_deref() == Some("black") { "white" } else { "black" }
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize, FromRow)]
@@ -44,6 +78,8 @@ pub struct User {
     pub username: String,
     #[serde(rename(serialize = "user_password", deserialize = "user_password"))]
     pub password: String,
+    #[serde(default)]
+    pub is_bot: bool, // AI 机器人账号，不能登录
 }
 
 // 新增：用户评分结构
@@ -51,7 +87,8 @@ pub struct User {
 pub struct UserRanking {
     pub id: i32,
     pub user_id: Uuid,
-    pub model: i32, // 9, 13, 19
+    pub model: i32, // 评分池：7（教学小棋盘）、9、13、19，见 rating::rating_pool
+    pub variant: String, // 量子围棋与经典围棋分别评分
     pub rating: f64,
     pub rd: f64,    // Rating Deviation
     pub vol: f64,   // Volatility
@@ -67,6 +104,7 @@ pub struct UserRanking {
 #[derive(Clone, Deserialize, Serialize)]
 pub struct LeaderboardEntry {
     pub username: String,
+    pub is_bot: bool,
     pub rating: f64,
     pub rd: f64,
     pub games_played: i32,
@@ -75,13 +113,29 @@ pub struct LeaderboardEntry {
     pub draws: i32,
 }
 
+// 对局复盘：终局后由后台任务生成
+#[derive(Clone, Deserialize, Serialize, FromRow)]
+pub struct GameReviewRecord {
+    #[serde(skip_serializing)]
+    #[allow(dead_code)]
+    pub id: i32,
+    pub room_id: Uuid,
+    pub status: String, // "pending" / "done" / "failed"
+    pub review: serde_json::Value, // review::GameReview，完成前为空对象
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
 // 新增：游戏结果
 #[derive(Clone, Deserialize, Serialize)]
 pub struct GameResult {
     pub winner: Option<String>, // "black", "white", or None for draw
-    pub black_score: i32,
-    pub white_score: i32,
-    pub model: i32,
+    pub black_score: f64,
+    pub white_score: f64,
+    pub model: i32, // 评分池
+    pub variant: String, // 对局变体，与 model 一起确定评分池
+    pub handicap: i32, // 让子数，评分时修正双方的等效实力
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
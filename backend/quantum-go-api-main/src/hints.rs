@@ -0,0 +1,128 @@
+use crate::ai::{AIDifficulty, MoveHint, SimpleQuantumAI, create_quantum_state_from_board_state, room_info_to_quantum_board_state};
+use crate::engine::MoveLimits;
+use crate::entity::RoomInfo;
+use crate::worker::JobError;
+use crate::ws::AppState;
+use axum::http::StatusCode;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// 未指定数量时返回的候选着法数
+pub const DEFAULT_HINT_COUNT: usize = 3;
+/// 一次最多返回的候选着法数
+pub const MAX_HINT_COUNT: usize = 10;
+/// 同一对局两次提示的最小间隔
+pub const HINT_INTERVAL: Duration = Duration::from_secs(10);
+/// 提示使用的搜索预算（思考时间另受 HINT_TIME_LIMIT 限制）
+const HINT_DIFFICULTY: AIDifficulty = AIDifficulty::Advanced;
+const HINT_TIME_LIMIT: Duration = Duration::from_secs(2);
+
+/// 按对局限制提示的频率
+#[derive(Default)]
+pub struct HintLimiter {
+    last: Mutex<HashMap<Uuid, Instant>>,
+}
+
+impl HintLimiter {
+    /// 记录对局的一次提示；距上次不足 HINT_INTERVAL 时返回还需等待的时间
+    pub fn check(&self, room_id: Uuid, now: Instant) -> Result<(), Duration> {
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        // 顺带清理已过间隔的记录，避免随房间数增长
+        last.retain(|_, at| now.duration_since(*at) < HINT_INTERVAL);
+        if let Some(at) = last.get(&room_id) {
+            return Err(HINT_INTERVAL - now.duration_since(*at));
+        }
+        last.insert(room_id, now);
+        Ok(())
+    }
+}
+
+/// 只有对局双方可以请求提示；对局中还需房间允许提示，终局后复盘总可以
+fn check_hint_access(room_info: &RoomInfo, user_id: Uuid) -> Result<(), (StatusCode, String)> {
+    if user_id != room_info.owner_id && room_info.visitor_id != Some(user_id) {
+        return Err((StatusCode::FORBIDDEN, "Only players in this room can request hints".to_string()));
+    }
+    if !room_info.hints && room_info.status != "finished" {
+        return Err((StatusCode::FORBIDDEN, "Hints are disabled in this game".to_string()));
+    }
+    Ok(())
+}
+
+/// 为房间的当前行棋方分析候选着法，HTTP 接口与 WebSocket 共用。
+/// board_state 与 AI 落子接口相同，不传时使用房间保存的局面
+pub async fn room_hints(
+    state: &AppState,
+    room_info: &RoomInfo,
+    user_id: Uuid,
+    count: Option<usize>,
+    board_state: Option<&Value>,
+) -> Result<Vec<MoveHint>, (StatusCode, String)> {
+    check_hint_access(room_info, user_id)?;
+    if let Err(wait) = state.hint_limiter.check(room_info.room_id, Instant::now()) {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Too many hint requests. Try again in {} seconds", wait.as_secs().max(1)),
+        ));
+    }
+
+    let count = count.unwrap_or(DEFAULT_HINT_COUNT).clamp(1, MAX_HINT_COUNT);
+    let game_state = match board_state {
+        Some(board_state) => create_quantum_state_from_board_state(board_state, room_info),
+        None => room_info_to_quantum_board_state(room_info),
+    };
+    let analyst = SimpleQuantumAI::new(HINT_DIFFICULTY).with_color(game_state.current_player);
+    let limits = MoveLimits { time_limit: Some(HINT_TIME_LIMIT), ..MoveLimits::default() };
+
+    // 与 AI 落子共用线程池；按玩家登记，不会取消房间里的 AI 落子
+    state
+        .ai_workers
+        .run(user_id, move |cancel| analyst.analyze(&game_state, count, &MoveLimits { cancel, ..limits }))
+        .await
+        .map_err(|err| match err {
+            JobError::Cancelled => (StatusCode::CONFLICT, "Hint request was cancelled".to_string()),
+            JobError::Failed => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to analyze the position".to_string()),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limiter_allows_one_hint_per_interval_per_game() {
+        let limiter = HintLimiter::default();
+        let (room, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Instant::now();
+
+        assert_eq!(limiter.check(room, now), Ok(()));
+        assert_eq!(limiter.check(other, now), Ok(()));
+        let wait = limiter.check(room, now + Duration::from_secs(4)).unwrap_err();
+        assert_eq!(wait, HINT_INTERVAL - Duration::from_secs(4));
+        assert_eq!(limiter.check(room, now + HINT_INTERVAL), Ok(()));
+    }
+
+    #[test]
+    fn test_hint_access() {
+        let (owner, visitor) = (Uuid::new_v4(), Uuid::new_v4());
+        let ranked = RoomInfo {
+            owner_id: owner,
+            visitor_id: Some(visitor),
+            status: "playing".to_string(),
+            hints: false,
+            ..RoomInfo::default()
+        };
+        assert_eq!(check_hint_access(&ranked, owner).unwrap_err().0, StatusCode::FORBIDDEN);
+        assert_eq!(check_hint_access(&ranked, Uuid::new_v4()).unwrap_err().0, StatusCode::FORBIDDEN);
+
+        // 终局后可以复盘
+        let finished = RoomInfo { status: "finished".to_string(), ..ranked.clone() };
+        assert!(check_hint_access(&finished, visitor).is_ok());
+
+        let casual = RoomInfo { hints: true, ..ranked };
+        assert!(check_hint_access(&casual, owner).is_ok());
+        assert!(check_hint_access(&casual, visitor).is_ok());
+    }
+}
@@ -0,0 +1,11 @@
+//! 量子围棋的规则与 AI：服务端与离线工具（如 selfplay）共用，不依赖数据库
+
+pub mod ai;
+pub mod board;
+pub mod collapse;
+pub mod engine;
+pub mod entity;
+pub mod mcts;
+pub mod review;
+pub mod rules;
+pub mod scoring;
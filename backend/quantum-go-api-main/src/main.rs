@@ -3,6 +3,8 @@ use axum::{
     routing::{any, get, post},
 };
 use db::Database;
+use engine::EngineRegistry;
+use quantum_go_api::{ai, board, collapse, engine, entity, mcts, review, rules, scoring};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::{env, net::SocketAddr, path::PathBuf};
@@ -16,11 +18,14 @@ use tower_http::{
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-mod ai;
 mod api;
+mod bots;
 mod db;
-mod entity;
+mod hints;
+mod ponder;
 mod rating;
+mod reviewer;
+mod worker;
 mod ws;
 
 #[tokio::main]
@@ -45,9 +50,24 @@ async fn main() {
         .await
         .expect("Failed to connect to database");
 
+    // 外部 AI 引擎（可选），见 engine::ENGINES_ENV
+    let engines = EngineRegistry::from_env().expect("Invalid AI_ENGINES");
+    info!("External AI engines: {:?}", engines.names());
+
+    // AI 机器人账号，评分固定为各引擎与难度的锚定值
+    let bots = bots::BotAccounts::ensure(&database)
+        .await
+        .expect("Failed to create bot accounts");
+
     let state = ws::AppState {
         rooms: Arc::new(Mutex::new(HashMap::new())),
         db: Arc::new(database),
+        engines: Arc::new(engines),
+        ai_workers: Arc::new(worker::AiWorkers::from_env()),
+        review_workers: Arc::new(worker::AiWorkers::reviews_from_env()),
+        ponderer: Arc::new(ponder::Ponderer::default()),
+        hint_limiter: Arc::new(hints::HintLimiter::default()),
+        bots: Arc::new(bots),
     };
 
     let cors = CorsLayer::new()
@@ -66,6 +86,8 @@ async fn main() {
         .route("/getLeaderboard", post(api::get_leaderboard))
         .route("/aiMove", post(api::ai_move))
         .route("/updatePlayerMove", post(api::update_player_move))
+        .route("/getHints", post(api::get_hints))
+        .route("/getReview", post(api::get_review))
         .route("/ws/{user_id}/{room_id}", any(ws::ws_handler))
         .with_state(state)
         // logging so we can see what's going on
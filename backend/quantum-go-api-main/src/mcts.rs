@@ -0,0 +1,646 @@
+use crate::board::{Point, Stone};
+use crate::engine::CancelToken;
+use crate::rules::QuantumPosition;
+use crate::scoring::{RuleSet, score_position};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::time::{Duration, Instant};
+
+/// UCT 的探索系数（约为 √2）
+const EXPLORATION: f64 = 1.4;
+/// 模拟对局的最大手数为棋盘面积乘以该倍数，避免打劫等循环无法结束
+const PLAYOUT_LENGTH_FACTOR: usize = 3;
+/// 分析结果中主要变化的最大手数
+const MAX_VARIATION: usize = 10;
+
+/// 搜索预算：模拟对局次数与思考时间，任一用完即停止搜索
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchBudget {
+    pub playouts: u32,
+    pub time_limit: Duration,
+}
+
+/// 搜索结果：访问次数最多的一手及其胜率（站在落子方的角度）
+#[derive(Debug, Clone, Copy)]
+pub struct SearchResult {
+    pub position: Point,
+    pub win_rate: f64,
+    pub visits: u32,
+    pub playouts: u32,
+}
+
+/// 候选着法的分析：访问次数、胜率与平均领先目数都站在落子方的角度
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub position: Point,
+    pub visits: u32,
+    pub win_rate: f64,
+    pub score_lead: f64,               // 模拟终局（数子法）的平均领先目数
+    pub variation: Vec<Option<Point>>, // 主要变化，从该手开始，None 为停一手
+}
+
+/// 局面评估（站在行棋方的角度）：访问次数最多的一手及其胜率
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Evaluation {
+    pub win_rate: f64,
+    pub best: Option<Point>, // 只能停一手或已终局时为空
+}
+
+/// 实际一手与访问次数最多的一手的比较（站在落子方的角度）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    pub best: Option<Point>, // 访问次数最多的一手，是停一手或就是实际一手时为空
+    pub best_win_rate: f64,
+    pub played_win_rate: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Move {
+    Play(Point),
+    Pass,
+}
+
+struct Node {
+    position: QuantumPosition, // 下出 mv 之后的局面
+    mv: Move,                  // 进入该节点的一手（根节点无意义）
+    mover: Stone,              // 下出 mv 的一方
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    wins: f64,  // mover 的胜局数，和棋计半局
+    score: f64, // mover 的领先目数之和
+}
+
+/// 可复用的搜索树：对方思考时（pondering）继续模拟，对方落子后以对应的子节点为新的根
+pub struct SearchTree {
+    nodes: Vec<Node>,
+}
+
+impl SearchTree {
+    /// 以 root 为根的空树，根节点可选所有空点与停一手
+    pub fn new(root: &QuantumPosition) -> Self {
+        Self::with_moves(root, tree_moves(root))
+    }
+
+    fn with_moves(root: &QuantumPosition, untried: Vec<Move>) -> Self {
+        Self {
+            nodes: vec![Node {
+                position: root.clone(),
+                mv: Move::Pass,
+                mover: root.to_move().opponent(),
+                parent: None,
+                children: Vec::new(),
+                untried,
+                visits: 0,
+                wins: 0.0,
+                score: 0.0,
+            }],
+        }
+    }
+
+    pub fn root(&self) -> &QuantumPosition {
+        &self.nodes[0].position
+    }
+
+    /// 根节点累计的模拟次数
+    pub fn visits(&self) -> u32 {
+        self.nodes[0].visits
+    }
+
+    /// 以 position 为新的根：position 是当前的根或根的某个子节点时保留对应的子树，否则返回 None
+    pub fn advance(self, position: &QuantumPosition) -> Option<SearchTree> {
+        let mut tree = if same_position(self.root(), position) {
+            self
+        } else {
+            let child = self.nodes[0]
+                .children
+                .iter()
+                .copied()
+                .find(|&child| same_position(&self.nodes[child].position, position))?;
+            self.subtree(child)
+        };
+        // 以实际局面为准（如打劫所需的历史局面）
+        tree.nodes[0].position = position.clone();
+        Some(tree)
+    }
+
+    /// 取出以 root 为根的子树，其余节点丢弃
+    fn subtree(self, root: usize) -> SearchTree {
+        let mut order = vec![root];
+        let mut next = 0;
+        while next < order.len() {
+            order.extend(self.nodes[order[next]].children.iter().copied());
+            next += 1;
+        }
+        let mut remap = vec![usize::MAX; self.nodes.len()];
+        for (index, &old) in order.iter().enumerate() {
+            remap[old] = index;
+        }
+
+        let mut nodes: Vec<Option<Node>> = self.nodes.into_iter().map(Some).collect();
+        let nodes = order
+            .iter()
+            .map(|&old| {
+                let mut node = nodes[old].take().expect("node visited twice");
+                node.parent = if old == root { None } else { node.parent.map(|parent| remap[parent]) };
+                for child in &mut node.children {
+                    *child = remap[*child];
+                }
+                node
+            })
+            .collect();
+        SearchTree { nodes }
+    }
+
+    /// 根节点只保留 candidates 中的落子
+    fn restrict_root(&mut self, candidates: &[Point]) {
+        let allowed = |mv: Move| matches!(mv, Move::Play(p) if candidates.contains(&p));
+        self.nodes[0].untried.retain(|&mv| allowed(mv));
+        let children = std::mem::take(&mut self.nodes[0].children);
+        self.nodes[0].children = children.into_iter().filter(|&child| allowed(self.nodes[child].mv)).collect();
+    }
+}
+
+impl std::fmt::Debug for SearchTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SearchTree")
+            .field("nodes", &self.nodes.len())
+            .field("visits", &self.visits())
+            .finish()
+    }
+}
+
+/// 蒙特卡洛树搜索：UCT 选择 + 随机模拟对局。
+/// 落子、纠缠与提子都交给规则引擎，模拟对局在所有棋盘上同样遵守提子与打劫规则
+pub struct Mcts {
+    budget: SearchBudget,
+    komi: f64,
+    cancel: CancelToken,
+}
+
+impl Mcts {
+    pub fn new(budget: SearchBudget, komi: f64) -> Self {
+        Self { budget, komi, cancel: CancelToken::default() }
+    }
+
+    /// 取消信号置位后停止搜索，返回已有结果
+    pub fn with_cancel(self, cancel: CancelToken) -> Self {
+        Self { cancel, ..self }
+    }
+
+    /// 在 candidates 中搜索最佳一手，candidates 为空时返回 None
+    pub fn search<R: Rng>(&self, root: &QuantumPosition, candidates: &[Point], rng: &mut R) -> Option<SearchResult> {
+        let untried = candidates.iter().map(|&p| Move::Play(p)).collect();
+        self.search_from(&mut SearchTree::with_moves(root, untried), candidates, rng)
+    }
+
+    /// 同 search，在已有的树上继续搜索（根节点只考虑 candidates），树保留供下一手复用
+    pub fn search_from<R: Rng>(&self, tree: &mut SearchTree, candidates: &[Point], rng: &mut R) -> Option<SearchResult> {
+        if candidates.is_empty() {
+            return None;
+        }
+        tree.restrict_root(candidates);
+        let playouts = self.grow(tree, rng);
+
+        let nodes = &tree.nodes;
+        nodes[0]
+            .children
+            .iter()
+            .map(|&child| &nodes[child])
+            .filter_map(|child| match child.mv {
+                Move::Play(position) => Some((position, child)),
+                Move::Pass => None,
+            })
+            .max_by_key(|(_, child)| child.visits)
+            .map(|(position, child)| SearchResult {
+                position,
+                win_rate: child.wins / child.visits.max(1) as f64,
+                visits: child.visits,
+                playouts,
+            })
+    }
+
+    /// 在树上继续模拟而不选点（对方思考时使用），返回本次的模拟次数
+    pub fn ponder<R: Rng>(&self, tree: &mut SearchTree, rng: &mut R) -> u32 {
+        self.grow(tree, rng)
+    }
+
+    /// 模拟直到预算用完或被取消，至少模拟一次；返回本次的模拟次数
+    fn grow<R: Rng>(&self, tree: &mut SearchTree, rng: &mut R) -> u32 {
+        let nodes = &mut tree.nodes;
+        let started = Instant::now();
+        let mut playouts = 0;
+        while playouts < self.budget.playouts
+            && (playouts == 0 || (started.elapsed() < self.budget.time_limit && !self.cancel.is_cancelled()))
+        {
+            // 1) 选择：沿 UCT 值最大的子节点下行，直到有未展开的着法或到达终局
+            let mut node = 0;
+            while nodes[node].untried.is_empty() && !nodes[node].children.is_empty() {
+                node = select_child(nodes, node);
+            }
+
+            // 2) 扩展：随机展开一个未尝试的着法，非法着法（如打劫）直接丢弃
+            if !nodes[node].untried.is_empty() {
+                let index = rng.gen_range(0..nodes[node].untried.len());
+                let mv = nodes[node].untried.swap_remove(index);
+                let Some(position) = apply_move(&nodes[node].position, mv) else {
+                    continue;
+                };
+                let child = nodes.len();
+                nodes.push(Node {
+                    untried: tree_moves(&position),
+                    mover: nodes[node].position.to_move(),
+                    position,
+                    mv,
+                    parent: Some(node),
+                    children: Vec::new(),
+                    visits: 0,
+                    wins: 0.0,
+                    score: 0.0,
+                });
+                nodes[node].children.push(child);
+                node = child;
+            }
+
+            // 3) 模拟
+            let (winner, black_lead) = self.playout(&nodes[node].position, rng);
+
+            // 4) 回传
+            let mut current = Some(node);
+            while let Some(index) = current {
+                let node = &mut nodes[index];
+                node.visits += 1;
+                node.wins += match winner {
+                    Some(stone) if stone == node.mover => 1.0,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                };
+                node.score += if node.mover == Stone::Black { black_lead } else { -black_lead };
+                current = node.parent;
+            }
+            playouts += 1;
+        }
+        playouts
+    }
+
+    /// 在 candidates 中搜索，按访问次数返回最多 count 个候选着法及其主要变化
+    pub fn analyze<R: Rng>(&self, root: &QuantumPosition, candidates: &[Point], count: usize, rng: &mut R) -> Vec<Candidate> {
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+        let untried = candidates.iter().map(|&p| Move::Play(p)).collect();
+        let mut tree = SearchTree::with_moves(root, untried);
+        self.grow(&mut tree, rng);
+
+        let nodes = &tree.nodes;
+        let mut children: Vec<usize> = nodes[0]
+            .children
+            .iter()
+            .copied()
+            .filter(|&child| nodes[child].visits > 0)
+            .collect();
+        children.sort_by_key(|&child| std::cmp::Reverse(nodes[child].visits));
+        children
+            .into_iter()
+            .take(count)
+            .filter_map(|child| {
+                let Move::Play(position) = nodes[child].mv else {
+                    return None;
+                };
+                let visits = nodes[child].visits;
+                Some(Candidate {
+                    position,
+                    visits,
+                    win_rate: nodes[child].wins / visits as f64,
+                    score_lead: nodes[child].score / visits as f64,
+                    variation: principal_variation(nodes, child),
+                })
+            })
+            .collect()
+    }
+
+    /// 评估局面：考虑所有空点与停一手；没有可走的一手（如已终局）时以模拟结果为准
+    pub fn evaluate<R: Rng>(&self, root: &QuantumPosition, rng: &mut R) -> Evaluation {
+        let mut tree = SearchTree::new(root);
+        self.grow(&mut tree, rng);
+
+        let nodes = &tree.nodes;
+        let best = nodes[0]
+            .children
+            .iter()
+            .map(|&child| &nodes[child])
+            .filter(|child| child.visits > 0)
+            .max_by_key(|child| child.visits);
+        match best {
+            Some(child) => Evaluation {
+                win_rate: child.wins / child.visits as f64,
+                best: match child.mv {
+                    Move::Play(p) => Some(p),
+                    Move::Pass => None,
+                },
+            },
+            // 根节点的胜局数站在上一手的一方
+            None => Evaluation {
+                win_rate: 1.0 - nodes[0].wins / nodes[0].visits.max(1) as f64,
+                best: None,
+            },
+        }
+    }
+
+    /// 比较 root 上实际下出的一手（下完后为 played）与访问次数最多的一手。
+    /// 选出最佳一手的模拟偏向它（选择偏差），两手的胜率都用同样预算的新搜索重新估计，
+    /// 两次搜索使用相同的随机数，差值的噪声更小
+    pub fn compare<R: Rng + Clone>(&self, root: &QuantumPosition, played: &QuantumPosition, rng: &mut R) -> Comparison {
+        let mut tree = SearchTree::new(root);
+        self.grow(&mut tree, rng);
+        let nodes = &tree.nodes;
+        let best = nodes[0]
+            .children
+            .iter()
+            .map(|&child| &nodes[child])
+            .filter(|child| child.visits > 0)
+            .max_by_key(|child| child.visits)
+            .filter(|child| !same_position(&child.position, played));
+
+        let played_win_rate = self.mover_win_rate(played, &mut rng.clone());
+        match best {
+            Some(best) => Comparison {
+                best: match best.mv {
+                    Move::Play(p) => Some(p),
+                    Move::Pass => None,
+                },
+                best_win_rate: self.mover_win_rate(&best.position, rng),
+                played_win_rate,
+            },
+            // 下出的就是最佳一手（或无从比较）
+            None => Comparison { best: None, best_win_rate: played_win_rate, played_win_rate },
+        }
+    }
+
+    /// 刚下完一手的一方在 position 上的胜率：以 position 为根搜索，取根节点的平均
+    fn mover_win_rate<R: Rng>(&self, position: &QuantumPosition, rng: &mut R) -> f64 {
+        let mut tree = SearchTree::new(position);
+        self.grow(&mut tree, rng);
+        tree.nodes[0].wins / tree.nodes[0].visits.max(1) as f64
+    }
+
+    /// 随机下完一局：不填自己的眼，无处可下时停一手，按数子法判定胜负（None 为和棋），
+    /// 同时返回黑方的领先目数
+    fn playout<R: Rng>(&self, position: &QuantumPosition, rng: &mut R) -> (Option<Stone>, f64) {
+        let mut position = position.clone();
+        let limit = position.boards[0].area() * PLAYOUT_LENGTH_FACTOR;
+        for _ in 0..limit {
+            if position.both_passed() {
+                break;
+            }
+            let stone = position.to_move();
+            let mut points: Vec<Point> = position.boards[0]
+                .points()
+                .filter(|&p| is_empty(&position, p) && !is_eye(&position, p, stone))
+                .collect();
+            points.shuffle(rng);
+
+            let next = points
+                .iter()
+                .find_map(|&p| position.play_at(p, stone, false).ok())
+                .or_else(|| position.pass(stone.as_str()).ok());
+            match next {
+                Some(outcome) => position = outcome.position,
+                None => break,
+            }
+        }
+
+        let result = score_position(&position, RuleSet::Area, self.komi);
+        let winner = match result.winner.as_str() {
+            "black" => Some(Stone::Black),
+            "white" => Some(Stone::White),
+            _ => None,
+        };
+        (winner, result.black_score - result.white_score)
+    }
+}
+
+/// 子节点中 UCT 值最大的一个：胜率 + 探索项
+fn select_child(nodes: &[Node], parent: usize) -> usize {
+    let log_visits = (nodes[parent].visits.max(1) as f64).ln();
+    let uct = |index: usize| {
+        let node = &nodes[index];
+        let visits = node.visits.max(1) as f64;
+        node.wins / visits + EXPLORATION * (log_visits / visits).sqrt()
+    };
+    nodes[parent]
+        .children
+        .iter()
+        .copied()
+        .max_by(|&a, &b| uct(a).total_cmp(&uct(b)))
+        .unwrap_or(parent)
+}
+
+/// 从 start 起沿访问次数最多的子节点下行得到的变化
+fn principal_variation(nodes: &[Node], start: usize) -> Vec<Option<Point>> {
+    let mut variation = Vec::new();
+    let mut node = Some(start);
+    while let Some(index) = node.filter(|_| variation.len() < MAX_VARIATION) {
+        variation.push(match nodes[index].mv {
+            Move::Play(p) => Some(p),
+            Move::Pass => None,
+        });
+        node = nodes[index]
+            .children
+            .iter()
+            .copied()
+            .filter(|&child| nodes[child].visits > 0)
+            .max_by_key(|&child| nodes[child].visits);
+    }
+    variation
+}
+
+/// 树内可选的着法：所有棋盘都为空的点，以及停一手；双方都已停一手则为终局
+fn tree_moves(position: &QuantumPosition) -> Vec<Move> {
+    if position.both_passed() {
+        return Vec::new();
+    }
+    position.boards[0]
+        .points()
+        .filter(|&p| is_empty(position, p))
+        .map(Move::Play)
+        .chain(std::iter::once(Move::Pass))
+        .collect()
+}
+
+fn apply_move(position: &QuantumPosition, mv: Move) -> Option<QuantumPosition> {
+    let stone = position.to_move();
+    let outcome = match mv {
+        Move::Play(p) => position.play_at(p, stone, false),
+        Move::Pass => position.pass(stone.as_str()),
+    };
+    outcome.ok().map(|outcome| outcome.position)
+}
+
+/// 复用搜索树时判断两个局面是否相同：各盘棋子、行棋方与等待纠缠的量子子
+fn same_position(a: &QuantumPosition, b: &QuantumPosition) -> bool {
+    a.to_move() == b.to_move() && a.pending_quantum == b.pending_quantum && a.boards == b.boards
+}
+
+fn is_empty(position: &QuantumPosition, p: Point) -> bool {
+    position.boards.iter().all(|board| !board.is_occupied(p))
+}
+
+/// 在每个棋盘上 p 的邻点都是己方棋子，视为眼，模拟时不填
+pub fn is_eye(position: &QuantumPosition, p: Point, stone: Stone) -> bool {
+    position
+        .boards
+        .iter()
+        .all(|board| board.neighbors(p).all(|neighbor| board.stone(neighbor) == Some(stone)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Cell;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn budget(playouts: u32) -> SearchBudget {
+        SearchBudget {
+            playouts,
+            time_limit: Duration::from_secs(30),
+        }
+    }
+
+    fn play_all(position: QuantumPosition, moves: &[&str]) -> QuantumPosition {
+        moves.iter().fold(position, |position, mv| {
+            let color = position.next_color();
+            position.play(mv, color).unwrap().position
+        })
+    }
+
+    #[test]
+    fn test_search_respects_budget_and_candidates() {
+        let position = play_all(QuantumPosition::new(5, 5), &["3,3", "2,2"]);
+        let candidates = [Point::new(1, 1), Point::new(4, 4)];
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let result = Mcts::new(budget(40), 0.5).search(&position, &candidates, &mut rng).unwrap();
+        assert!(candidates.contains(&result.position));
+        assert_eq!(result.playouts, 40);
+        assert!((0.0..=1.0).contains(&result.win_rate));
+        assert!(Mcts::new(budget(40), 0.5).search(&position, &[], &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_search_stops_when_cancelled_or_out_of_time() {
+        let position = play_all(QuantumPosition::new(5, 5), &["3,3", "2,2"]);
+        let candidates: Vec<Point> = position.boards[0].points().filter(|&p| is_empty(&position, p)).collect();
+        let mut rng = StdRng::seed_from_u64(5);
+
+        // 已取消：只做一次模拟，仍然给出一手
+        let cancel = CancelToken::default();
+        cancel.cancel();
+        let result = Mcts::new(budget(10_000), 0.5).with_cancel(cancel).search(&position, &candidates, &mut rng).unwrap();
+        assert_eq!(result.playouts, 1);
+
+        let budget = SearchBudget { playouts: u32::MAX, time_limit: Duration::from_millis(50) };
+        let started = Instant::now();
+        assert!(Mcts::new(budget, 0.5).search(&position, &candidates, &mut rng).is_some());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_tree_is_reused_after_advance() {
+        let position = play_all(QuantumPosition::new(5, 5), &["3,3", "2,2"]);
+        let candidates: Vec<Point> = position.boards[0].points().filter(|&p| is_empty(&position, p)).collect();
+        let mut rng = StdRng::seed_from_u64(11);
+        let mcts = Mcts::new(budget(200), 0.5);
+
+        let mut tree = SearchTree::new(&position);
+        let result = mcts.search_from(&mut tree, &candidates, &mut rng).unwrap();
+        assert_eq!(tree.visits(), 200);
+
+        // 沿选中的一手下行，子树的模拟次数保留
+        let next = position.play_at(result.position, position.to_move(), false).unwrap().position;
+        let mut tree = tree.advance(&next).unwrap();
+        assert_eq!(tree.visits(), result.visits);
+        assert_eq!(mcts.ponder(&mut tree, &mut rng), 200);
+        assert_eq!(tree.visits(), result.visits + 200);
+
+        // 不在树中的局面无法复用
+        let elsewhere = play_all(QuantumPosition::new(5, 5), &["1,1", "5,5"]);
+        assert!(tree.advance(&elsewhere).is_none());
+    }
+
+    #[test]
+    fn test_analyze_ranks_candidates_with_variations() {
+        let position = play_all(QuantumPosition::new(5, 5), &["3,3", "2,2"]);
+        let candidates: Vec<Point> = position.boards[0].points().filter(|&p| is_empty(&position, p)).collect();
+        let mut rng = StdRng::seed_from_u64(13);
+
+        let analysis = Mcts::new(budget(300), 0.5).analyze(&position, &candidates, 3, &mut rng);
+        assert_eq!(analysis.len(), 3);
+        assert!(analysis.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+        for candidate in &analysis {
+            assert!(candidates.contains(&candidate.position));
+            assert_eq!(candidate.variation[0], Some(candidate.position));
+            assert!(candidate.variation.len() <= MAX_VARIATION);
+            assert!((0.0..=1.0).contains(&candidate.win_rate));
+        }
+        assert!(Mcts::new(budget(10), 0.5).analyze(&position, &[], 3, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_finished_position() {
+        // 经典 5 路，黑方占满左侧三列，双方都已停一手：黑方必胜
+        let mut position = QuantumPosition {
+            variant: crate::rules::Variant::Classical,
+            moves: 2,
+            passes: 2,
+            ..QuantumPosition::with_boards(5, 5, 1)
+        };
+        for y in 1..=5 {
+            for (x, stone) in [(3, Stone::Black), (4, Stone::White)] {
+                let p = Point::new(x, y);
+                position.boards[0].set(p, Cell { stone, brother: p });
+            }
+        }
+        let mut rng = StdRng::seed_from_u64(17);
+
+        let evaluation = Mcts::new(budget(20), 0.5).evaluate(&position, &mut rng);
+        assert_eq!(position.to_move(), Stone::Black);
+        assert_eq!(evaluation, Evaluation { win_rate: 1.0, best: None });
+    }
+
+    #[test]
+    fn test_search_captures_in_atari() {
+        // 经典 5 路，轮到白方：黑方四子只剩 (5,3) 一口气，提掉即可锁定胜局
+        let mut position = QuantumPosition {
+            variant: crate::rules::Variant::Classical,
+            moves: 1,
+            ..QuantumPosition::with_boards(5, 5, 1)
+        };
+        for x in 1..=4 {
+            for (y, stone) in [(2, Stone::White), (3, Stone::Black), (4, Stone::White)] {
+                let p = Point::new(x, y);
+                position.boards[0].set(p, Cell { stone, brother: p });
+            }
+        }
+        let candidates: Vec<Point> = position.boards[0].points().filter(|&p| is_empty(&position, p)).collect();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let result = Mcts::new(budget(400), 0.5).search(&position, &candidates, &mut rng).unwrap();
+        assert_eq!(result.position, Point::new(5, 3));
+    }
+
+    #[test]
+    fn test_search_answers_pending_quantum_stone() {
+        // 黑方开局的量子子等待纠缠，白方的回应与之成对
+        let position = play_all(QuantumPosition::new(5, 5), &["3,3"]);
+        let candidates: Vec<Point> = position.boards[0].points().filter(|&p| is_empty(&position, p)).collect();
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let result = Mcts::new(budget(50), 0.5).search(&position, &candidates, &mut rng).unwrap();
+        let next = position.play_at(result.position, Stone::White, false).unwrap().position;
+        assert_eq!(next.pending_quantum, None);
+        assert_eq!(next.boards[1].stone(Point::new(3, 3)), Some(Stone::White));
+    }
+}
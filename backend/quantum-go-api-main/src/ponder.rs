@@ -0,0 +1,177 @@
+use crate::ai::AIDifficulty;
+use crate::mcts::{Mcts, SearchBudget, SearchTree};
+use crate::rules::QuantumPosition;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tracing::info;
+use uuid::Uuid;
+
+/// 每个房间思考的时间上限，用完后不再占用 CPU
+pub const PONDER_TIME_LIMIT: Duration = Duration::from_secs(30);
+/// 两次检查落子流之间的模拟次数
+const PONDER_SLICE: u32 = 32;
+/// 思考结束后等待玩家落子的时间，超时后线程退出、搜索树丢弃
+const PONDER_IDLE: Duration = Duration::from_secs(600);
+
+/// 房间落子流中的事件
+enum PonderEvent {
+    /// 玩家落子后的局面：搜索树移到对应的子节点，继续为 AI 的下一手思考
+    Played(QuantumPosition),
+    /// AI 开始选点：交出以该局面为根的搜索树，思考结束
+    Take(QuantumPosition, oneshot::Sender<Option<Pondered>>),
+}
+
+/// 取回的思考结果
+#[derive(Debug)]
+pub struct Pondered {
+    pub tree: SearchTree,
+    /// 各段的模拟次数：开始思考的局面一段，之后玩家每下一手一段（见 AiDecision::pondered）
+    pub playouts: Vec<u32>,
+}
+
+/// AI 对战房间的后台思考（pondering）：AI 落子后，每个房间一个线程在玩家思考时继续搜索，
+/// 玩家的落子经房间的落子流通知该线程，AI 选点时复用积累的搜索树。
+/// 每轮思考的模拟次数与时间都有上限，空闲的房间不会一直占用 CPU
+#[derive(Default)]
+pub struct Ponderer {
+    rooms: Mutex<HashMap<Uuid, Sender<PonderEvent>>>,
+}
+
+impl Ponderer {
+    /// AI 落子后开始思考，seed 为思考的随机种子（见 engine::ponder_seed），为空时每次不同；
+    /// 同一房间之前的思考结束
+    pub fn start(&self, room_id: Uuid, tree: SearchTree, difficulty: AIDifficulty, komi: f64, seed: Option<u64>) {
+        let (sender, events) = mpsc::channel();
+        // 每轮最多追加与难度预算相同的模拟次数
+        let limit = difficulty.search_budget().playouts;
+        let spawned = thread::Builder::new()
+            .name(format!("ponder-{}", room_id))
+            .spawn(move || ponder(tree, limit, komi, seed, events));
+        if let Err(err) = spawned {
+            info!("Failed to start pondering for room `{room_id}`: {err}");
+            return;
+        }
+        self.lock_rooms().insert(room_id, sender);
+    }
+
+    /// 玩家落子：通知房间的思考线程
+    pub fn played(&self, room_id: Uuid, position: &QuantumPosition) {
+        let mut rooms = self.lock_rooms();
+        if let Some(events) = rooms.get(&room_id) {
+            if events.send(PonderEvent::Played(position.clone())).is_err() {
+                rooms.remove(&room_id);
+            }
+        }
+    }
+
+    /// 结束房间的思考，取回以 position 为根的搜索树；没有在思考或局面对不上时返回 None
+    pub async fn take(&self, room_id: Uuid, position: &QuantumPosition) -> Option<Pondered> {
+        let events = self.lock_rooms().remove(&room_id)?;
+        let (reply, tree) = oneshot::channel();
+        events.send(PonderEvent::Take(position.clone(), reply)).ok()?;
+        tree.await.ok().flatten()
+    }
+
+    /// 结束房间的思考（对局结束或玩家断开）
+    pub fn stop(&self, room_id: Uuid) {
+        if self.lock_rooms().remove(&room_id).is_some() {
+            info!("Stopped pondering for room `{room_id}`");
+        }
+    }
+
+    fn lock_rooms(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, Sender<PonderEvent>>> {
+        self.rooms.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// 思考线程：分片模拟，片间处理落子流；用完上限后只等待事件。
+/// 发送端被丢弃（思考被结束或被新的思考替换）时退出
+fn ponder(mut tree: SearchTree, limit: u32, komi: f64, seed: Option<u64>, events: Receiver<PonderEvent>) {
+    let mcts = Mcts::new(SearchBudget { playouts: PONDER_SLICE, time_limit: PONDER_TIME_LIMIT }, komi);
+    let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+    let started = Instant::now();
+    let mut playouts = 0;
+    let mut segments = vec![0];
+    loop {
+        let thinking = playouts < limit && started.elapsed() < PONDER_TIME_LIMIT;
+        let event = if thinking {
+            match events.try_recv() {
+                Ok(event) => Some(event),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        } else {
+            match events.recv_timeout(PONDER_IDLE) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return,
+            }
+        };
+
+        match event {
+            Some(PonderEvent::Played(position)) => {
+                tree = tree.advance(&position).unwrap_or_else(|| SearchTree::new(&position));
+                segments.push(0);
+            }
+            Some(PonderEvent::Take(position, reply)) => {
+                let pondered = tree.advance(&position).map(|tree| Pondered { tree, playouts: segments });
+                let _ = reply.send(pondered);
+                return;
+            }
+            None => {
+                let slice = mcts.ponder(&mut tree, &mut rng);
+                playouts += slice;
+                *segments.last_mut().unwrap() += slice;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(position: &QuantumPosition, mv: &str) -> QuantumPosition {
+        position.play(mv, position.next_color()).unwrap().position
+    }
+
+    #[tokio::test]
+    async fn test_take_returns_tree_following_played_moves() {
+        let ponderer = Ponderer::default();
+        let room_id = Uuid::new_v4();
+        let after_ai = play(&play(&QuantumPosition::new(5, 5), "3,3"), "2,2");
+        ponderer.start(room_id, SearchTree::new(&after_ai), AIDifficulty::Beginner, 0.5, None);
+
+        // 玩家的落子未必在树中，对不上时从新局面重新开始
+        let after_player = play(&after_ai, "4,4");
+        ponderer.played(room_id, &after_player);
+        thread::sleep(Duration::from_millis(50));
+
+        let pondered = ponderer.take(room_id, &after_player).await.unwrap();
+        assert_eq!(pondered.tree.root().boards, after_player.boards);
+        assert!(pondered.tree.visits() > 0);
+        assert_eq!(pondered.playouts.len(), 2);
+        assert!(pondered.playouts.iter().sum::<u32>() > 0);
+        // 取回后思考结束
+        assert!(ponderer.take(room_id, &after_player).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_take_rejects_unrelated_position_and_stop_ends_pondering() {
+        let ponderer = Ponderer::default();
+        let room_id = Uuid::new_v4();
+        let after_ai = play(&QuantumPosition::new(5, 5), "3,3");
+        ponderer.start(room_id, SearchTree::new(&after_ai), AIDifficulty::Beginner, 0.5, None);
+        let elsewhere = play(&play(&after_ai, "1,1"), "5,5");
+        assert!(ponderer.take(room_id, &elsewhere).await.is_none());
+
+        ponderer.start(room_id, SearchTree::new(&after_ai), AIDifficulty::Beginner, 0.5, None);
+        ponderer.stop(room_id);
+        assert!(ponderer.take(room_id, &after_ai).await.is_none());
+    }
+}
@@ -1,48 +1,86 @@
+use crate::ai::{AIDifficulty, AIEngine};
 use crate::db::Database;
 use crate::entity::{UserRanking, GameResult as MatchResult};
 use uuid::Uuid;
 
 // glicko2 0.3.1 文档：GameResult::win/loss/draw(opponent_rating)
 // new_rating(prior, results, sys_constant) -> Glicko2Rating
-use glicko2::{Glicko2Rating, GameResult as GlickoGameResult, new_rating};
+// 数据库里的 rating/rd 是 Glicko 量表（1500 / 350），计算前后用 GlickoRating 换算
+use glicko2::{Glicko2Rating, GlickoRating, GameResult as GlickoGameResult, new_rating};
 
 const TAU: f64 = 0.5; // 系统常数 τ，0.3 ~ 1.2 之间自行选择
+const HANDICAP_STONE_RATING: f64 = 100.0; // 每让一子约等于的等级分差
+/// 机器人评分的 RD：评分固定，人类的评分以机器人已知的强度为参照
+pub const ANCHOR_RD: f64 = 60.0;
+
+/// 机器人的锚定等级分（各评分池相同），可用 selfplay 测得的 Elo 差校准
+pub fn anchor_rating(engine: AIEngine, difficulty: AIDifficulty) -> f64 {
+    match (engine, difficulty) {
+        (AIEngine::Heuristic, AIDifficulty::Beginner) => 700.0,
+        (AIEngine::Heuristic, AIDifficulty::Intermediate) => 900.0,
+        (AIEngine::Heuristic, AIDifficulty::Advanced) => 1100.0,
+        (AIEngine::Mcts, AIDifficulty::Beginner) => 1100.0,
+        (AIEngine::Mcts, AIDifficulty::Intermediate) => 1500.0,
+        (AIEngine::Mcts, AIDifficulty::Advanced) => 1800.0,
+    }
+}
+
+/// 按棋盘大小划分评分池：标准的 9/13/19 路各自独立，
+/// 其它尺寸按面积折算的等效边长 round(sqrt(宽 x 高)) 归入最接近的池：
+/// 不超过 7 为教学小棋盘池 7，8~10 归 9 路，11~15 归 13 路，16 及以上归 19 路
+pub fn rating_pool(width: i32, height: i32) -> i32 {
+    let side = ((width * height) as f64).sqrt().round() as i32;
+    match side {
+        ..=7 => 7,
+        8..=10 => 9,
+        11..=15 => 13,
+        _ => 19,
+    }
+}
 
 pub struct RatingSystem;
 
 impl RatingSystem {
     pub fn new() -> Self { Self }
 
+    /// 按对局结果更新双方评分；anchored 为评分固定的一方（机器人），只累计对局统计
     pub async fn update_ratings(
         &self,
         db: &Database,
         game_result: &MatchResult,
         black_player_id: Uuid,
         white_player_id: Uuid,
+        anchored: Option<Uuid>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let model = game_result.model;
+        let variant = game_result.variant.as_str();
 
         // 1) 读取双方当前评级，如果不存在则创建默认记录
-        let black_ranking = self.get_or_create_user_ranking(db, &black_player_id, model).await?;
-        let white_ranking = self.get_or_create_user_ranking(db, &white_player_id, model).await?;
+        let black_ranking = self.get_or_create_user_ranking(db, &black_player_id, model, variant).await?;
+        let white_ranking = self.get_or_create_user_ranking(db, &white_player_id, model, variant).await?;
 
-        // 2) 转成 glicko2 的 Rating 结构（字段名 value/deviation/volatility）
-        let black_rating = to_glicko2(&black_ranking);
-        let white_rating = to_glicko2(&white_ranking);
+        // 2) 换算到 Glicko-2 量表（μ≈0，φ = RD / 173.7178）
+        let black_rating = to_glicko2(&black_ranking, 0.0);
+        let white_rating = to_glicko2(&white_ranking, 0.0);
+
+        // 让子局：黑方的让子优势折算为等级分（Glicko 量表），双方都以修正后的对手评级计算
+        let adjustment = handicap_adjustment(game_result.handicap);
+        let white_as_opponent = to_glicko2(&white_ranking, -adjustment);
+        let black_as_opponent = to_glicko2(&black_ranking, adjustment);
 
         // 3) 构造对局结果（从各自视角）
         let (black_res, white_res) = match game_result.winner.as_deref() {
             Some("black") => (
-                [GlickoGameResult::win(white_rating)],
-                [GlickoGameResult::loss(black_rating)],
+                [GlickoGameResult::win(white_as_opponent)],
+                [GlickoGameResult::loss(black_as_opponent)],
             ),
             Some("white") => (
-                [GlickoGameResult::loss(white_rating)],
-                [GlickoGameResult::win(black_rating)],
+                [GlickoGameResult::loss(white_as_opponent)],
+                [GlickoGameResult::win(black_as_opponent)],
             ),
             _ => (
-                [GlickoGameResult::draw(white_rating)],
-                [GlickoGameResult::draw(black_rating)],
+                [GlickoGameResult::draw(white_as_opponent)],
+                [GlickoGameResult::draw(black_as_opponent)],
             ),
         };
 
@@ -52,9 +90,9 @@ impl RatingSystem {
 
         // 5) 写回数据库字段（你的表用 rating/rd/vol 命名）
         let mut nb = black_ranking;
-        nb.rating = new_black.value;
-        nb.rd     = new_black.deviation;
-        nb.vol    = new_black.volatility;
+        if anchored != Some(black_player_id) {
+            set_glicko2(&mut nb, new_black);
+        }
         nb.games_played += 1;
         match game_result.winner.as_deref() {
             Some("black") => nb.wins += 1,
@@ -63,9 +101,9 @@ impl RatingSystem {
         }
 
         let mut nw = white_ranking;
-        nw.rating = new_white.value;
-        nw.rd     = new_white.deviation;
-        nw.vol    = new_white.volatility;
+        if anchored != Some(white_player_id) {
+            set_glicko2(&mut nw, new_white);
+        }
         nw.games_played += 1;
         match game_result.winner.as_deref() {
             Some("white") => nw.wins += 1,
@@ -84,8 +122,9 @@ impl RatingSystem {
         db: &Database,
         user_id: &Uuid,
         model: i32,
+        variant: &str,
     ) -> Result<UserRanking, Box<dyn std::error::Error>> {
-        match db.get_user_ranking(user_id, model).await {
+        match db.get_user_ranking(user_id, model, variant).await {
             Ok(ranking) => Ok(ranking),
             Err(_) => {
                 // 如果不存在，创建默认评级记录
@@ -93,6 +132,7 @@ impl RatingSystem {
                     id: 0,
                     user_id: *user_id,
                     model,
+                    variant: variant.to_string(),
                     rating: 1500.0,
                     rd: 350.0,
                     vol: 0.06,
@@ -104,7 +144,7 @@ impl RatingSystem {
                     updated_at: chrono::Utc::now(),
                 };
                 
-                db.create_user_ranking(&default_ranking.user_id, default_ranking.model).await?;
+                db.create_user_ranking(&default_ranking.user_id, default_ranking.model, &default_ranking.variant).await?;
                 Ok(default_ranking)
             }
         }
@@ -115,10 +155,114 @@ impl Default for RatingSystem {
     fn default() -> Self { Self::new() }
 }
 
-fn to_glicko2(r: &UserRanking) -> Glicko2Rating {
-    Glicko2Rating {
-        value:      r.rating, // μ
-        deviation:  r.rd,     // φ
-        volatility: r.vol,    // σ
+/// 让子对应的等级分差：让先（1 子、不贴目）约半子
+fn handicap_adjustment(handicap: i32) -> f64 {
+    if handicap <= 0 {
+        0.0
+    } else {
+        (handicap as f64 - 0.5) * HANDICAP_STONE_RATING
+    }
+}
+
+/// 换算到 Glicko-2 量表；offset 为换算前在 Glicko 量表上的等级分修正（如让子）
+fn to_glicko2(r: &UserRanking, offset: f64) -> Glicko2Rating {
+    let rating: Glicko2Rating = GlickoRating { value: r.rating + offset, deviation: r.rd }.into();
+    Glicko2Rating { volatility: r.vol, ..rating }
+}
+
+/// 把新的 Glicko-2 评级换算回 Glicko 量表写入 rating/rd
+fn set_glicko2(r: &mut UserRanking, rating: Glicko2Rating) {
+    let glicko = GlickoRating::from(rating);
+    r.rating = glicko.value;
+    r.rd = glicko.deviation;
+    r.vol = rating.volatility;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rating_pools() {
+        assert_eq!(rating_pool(9, 9), 9);
+        assert_eq!(rating_pool(13, 13), 13);
+        assert_eq!(rating_pool(19, 19), 19);
+        assert_eq!(rating_pool(5, 5), 7);
+        assert_eq!(rating_pool(7, 7), 7);
+        assert_eq!(rating_pool(7, 9), 9); // 等效边长 8
+        assert_eq!(rating_pool(9, 13), 13); // 等效边长 11
+        assert_eq!(rating_pool(25, 25), 19);
+    }
+
+    fn ranking(rating: f64, rd: f64) -> UserRanking {
+        UserRanking {
+            id: 0,
+            user_id: Uuid::new_v4(),
+            model: 9,
+            variant: "quantum".to_string(),
+            rating,
+            rd,
+            vol: 0.06,
+            games_played: 0,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    /// 新玩家与 opponent 对局后的评分
+    fn after_game(opponent: &UserRanking, result: fn(Glicko2Rating) -> GlickoGameResult) -> UserRanking {
+        let mut player = ranking(1500.0, 350.0);
+        let rated = new_rating(to_glicko2(&player, 0.0), &[result(to_glicko2(opponent, 0.0))], TAU);
+        set_glicko2(&mut player, rated);
+        player
+    }
+
+    #[test]
+    fn test_glicko_scale_round_trip() {
+        let fresh = to_glicko2(&ranking(1500.0, 350.0), 0.0);
+        assert!(fresh.value.abs() < 1e-9);
+        assert!((fresh.deviation - 350.0 / 173.7178).abs() < 1e-9);
+        let mut r = ranking(0.0, 0.0);
+        set_glicko2(&mut r, to_glicko2(&ranking(1800.0, ANCHOR_RD), 0.0));
+        assert!((r.rating - 1800.0).abs() < 1e-9 && (r.rd - ANCHOR_RD).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_handicap_adjustment_on_glicko_scale() {
+        // 让两子约 150 分：对手按 1350 计算，而不是在 μ 上平移 150
+        let opponent = ranking(1500.0, ANCHOR_RD);
+        let adjusted = to_glicko2(&opponent, -handicap_adjustment(2));
+        let expected = to_glicko2(&ranking(1350.0, ANCHOR_RD), 0.0);
+        assert!((adjusted.value - expected.value).abs() < 1e-9);
+        assert!((adjusted.deviation - expected.deviation).abs() < 1e-9);
+        assert_eq!(handicap_adjustment(0), 0.0);
+    }
+
+    #[test]
+    fn test_games_against_anchors_are_informative() {
+        // 赢了 1800 的机器人，新玩家的评分明显上升但仍在合理范围
+        let bot = ranking(anchor_rating(AIEngine::Mcts, AIDifficulty::Advanced), ANCHOR_RD);
+        let winner = after_game(&bot, GlickoGameResult::win);
+        assert!(winner.rating > 1700.0 && winner.rating < 2300.0, "{}", winner.rating);
+        // 同样输一盘，对手的 RD 越小（越确定），结论越可靠，玩家的 RD 下降越多
+        let anchored = after_game(&ranking(1500.0, ANCHOR_RD), GlickoGameResult::loss);
+        let unknown = after_game(&ranking(1500.0, 350.0), GlickoGameResult::loss);
+        assert!(anchored.rd < unknown.rd);
+        assert!(anchored.rating < unknown.rating);
+    }
+
+    #[test]
+    fn test_anchor_ratings_follow_strength() {
+        let difficulties = [AIDifficulty::Beginner, AIDifficulty::Intermediate, AIDifficulty::Advanced];
+        for engine in [AIEngine::Mcts, AIEngine::Heuristic] {
+            let anchors: Vec<f64> = difficulties.iter().map(|&d| anchor_rating(engine, d)).collect();
+            assert!(anchors.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        for difficulty in difficulties {
+            assert!(anchor_rating(AIEngine::Heuristic, difficulty) <= anchor_rating(AIEngine::Mcts, difficulty));
+        }
     }
 }
@@ -0,0 +1,340 @@
+//! 复盘：按 chessman_records 重放对局，用 MCTS 评估每一手之后的局面，
+//! 落子方胜率大幅下降的一手标为问题手或恶手。
+
+use crate::board::{Point, Stone};
+use crate::engine::CancelToken;
+use crate::entity::RoomInfo;
+use crate::mcts::{Evaluation, Mcts, SearchBudget};
+use crate::rules::{
+    ChessmanRecord, KoRule, MAX_BOARDS, MoveAction, QuantumPosition, RuleError, Variant, fixed_handicap_points,
+    parse_history, parse_room_boards, position_hash,
+};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 落子方胜率下降达到该值为恶手
+pub const BLUNDER_DROP: f64 = 0.2;
+/// 落子方胜率下降达到该值为问题手
+pub const MISTAKE_DROP: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Judgement {
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    pub fn from_drop(drop: f64) -> Option<Self> {
+        if drop >= BLUNDER_DROP {
+            Some(Judgement::Blunder)
+        } else if drop >= MISTAKE_DROP {
+            Some(Judgement::Mistake)
+        } else {
+            None
+        }
+    }
+}
+
+/// 一手的复盘结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveReview {
+    pub number: usize,         // 对应 chessman_records 的下标 + 1
+    pub color: Option<String>, // 落子方，终局坍缩为空
+    pub position: String,      // "x,y"，或 "pass" / "measure"
+    pub black_win_rate: f64,   // 这一手之后黑方的胜率，即评估曲线
+    pub win_rate_drop: f64,    // 落子方胜率的下降，负数为上升
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best: Option<String>,  // 引擎在这一手之前推荐的一手（与实战不同时）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judgement: Option<Judgement>,
+}
+
+/// 整局的复盘结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameReview {
+    pub initial_black_win_rate: f64,
+    pub moves: Vec<MoveReview>,
+}
+
+impl GameReview {
+    pub fn count(&self, judgement: Judgement) -> usize {
+        self.moves.iter().filter(|review| review.judgement == Some(judgement)).count()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReviewError {
+    /// 无法确定开局局面（如自由摆放的让子）
+    UnknownStart,
+    /// 第 number 手无法重放
+    Replay { number: usize, error: RuleError },
+    Cancelled,
+}
+
+impl fmt::Display for ReviewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReviewError::UnknownStart => write!(f, "Cannot reconstruct the starting position of this game"),
+            ReviewError::Replay { number, error } => write!(f, "Cannot replay move {}: {}", number, error),
+            ReviewError::Cancelled => write!(f, "Review was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for ReviewError {}
+
+/// 重放中的一步：chessman_records 中的第 number 条及其之后的局面
+#[derive(Debug, Clone)]
+pub struct ReplayStep {
+    pub number: usize,
+    pub color: Option<Stone>,
+    pub played: Option<Point>, // 落子位置，停一手与坍缩为空
+    pub action: Option<MoveAction>,
+    pub position: QuantumPosition,
+}
+
+impl ReplayStep {
+    fn label(&self) -> String {
+        match (self.played, self.action) {
+            (Some(p), _) => p.to_string(),
+            (None, Some(MoveAction::Measure)) => "measure".to_string(),
+            _ => "pass".to_string(),
+        }
+    }
+}
+
+/// 开局局面：房间不保存开局时的棋盘数与让子，以 position_history 的第一项（开局局面哈希）
+/// 在可能的棋盘数与星位让子中找出；早期房间没有历史记录时取当前棋盘数、不摆让子
+pub fn initial_position(room_info: &RoomInfo) -> Result<QuantumPosition, ReviewError> {
+    let (width, height) = room_info.board_size();
+    let variant = Variant::parse(&room_info.variant).unwrap_or_default();
+    let setup = |count: usize| QuantumPosition {
+        ko_rule: KoRule::parse(&room_info.ko_rule).unwrap_or_default(),
+        variant,
+        quantum_pairs: room_info.quantum_pairs,
+        handicap: room_info.handicap,
+        ..QuantumPosition::with_boards(width, height, count)
+    };
+    let counts = match variant {
+        Variant::Classical => 1..=1,
+        Variant::Quantum => 2..=MAX_BOARDS,
+    };
+
+    let Some(&start) = parse_history(&room_info.position_history).first() else {
+        if room_info.handicap >= 2 {
+            return Err(ReviewError::UnknownStart);
+        }
+        let count = parse_room_boards(&room_info.board, width, height, variant).len();
+        return Ok(setup(count));
+    };
+    let stones = match room_info.handicap {
+        handicap if handicap >= 2 => fixed_handicap_points(width, height, handicap).ok_or(ReviewError::UnknownStart)?,
+        _ => Vec::new(),
+    };
+    counts
+        .filter_map(|count| setup(count).with_handicap(&stones).ok())
+        .find(|position| position_hash(&position.boards) == start)
+        .ok_or(ReviewError::UnknownStart)
+}
+
+/// 按 chessman_records 重放对局，认输之后的记录忽略
+pub fn replay(room_info: &RoomInfo) -> Result<(QuantumPosition, Vec<ReplayStep>), ReviewError> {
+    let initial = initial_position(room_info)?;
+    let records: Vec<ChessmanRecord> = room_info
+        .chessman_records
+        .as_array()
+        .map(|items| items.iter().map(|item| serde_json::from_value(item.clone()).unwrap_or_default()).collect())
+        .unwrap_or_default();
+
+    let mut position = initial.clone();
+    let mut steps = Vec::new();
+    for (index, record) in records.iter().enumerate() {
+        let number = index + 1;
+        let failed = |error: RuleError| ReviewError::Replay { number, error };
+        let color = position.to_move();
+        let (color, played, next) = match record.action {
+            Some(MoveAction::Resign) => break,
+            Some(MoveAction::Resume) => {
+                // 数子有争议，恢复对局：连续停一手重新计数
+                position.passes = 0;
+                continue;
+            }
+            Some(MoveAction::Measure) => {
+                let board = room_info.collapsed_board.ok_or(failed(RuleError::MeasureNotAllowed))?;
+                let measured_by = record.color.as_deref().and_then(Stone::parse);
+                (measured_by, None, position.collapse(board as usize))
+            }
+            Some(MoveAction::Pass) => (Some(color), None, position.pass(color.as_str()).map_err(failed)?.position),
+            None => {
+                let added = record.add.first().ok_or_else(|| failed(RuleError::InvalidPosition(String::new())))?;
+                let p = Point::parse(&added.position).ok_or_else(|| failed(RuleError::InvalidPosition(added.position.clone())))?;
+                let next = position.play_at(p, color, record.quantum).map_err(failed)?.position;
+                (Some(color), Some(p), next)
+            }
+        };
+        position = next;
+        steps.push(ReplayStep {
+            number,
+            color,
+            played,
+            action: record.action,
+            position: position.clone(),
+        });
+    }
+    Ok((initial, steps))
+}
+
+/// 黑方胜率
+fn black_win_rate(evaluation: &Evaluation, position: &QuantumPosition) -> f64 {
+    match position.to_move() {
+        Stone::Black => evaluation.win_rate,
+        Stone::White => 1.0 - evaluation.win_rate,
+    }
+}
+
+/// 复盘整局：每一手与搜索中访问次数最多的一手比较，两者的胜率各自重新估计，差值为落子方的损失；
+/// 坍缩由随机抽取决定，不评判。随机数由房间 ID 导出，同一对局的复盘结果相同
+pub fn review_game(
+    room_info: &RoomInfo,
+    budget: SearchBudget,
+    cancel: &CancelToken,
+) -> Result<GameReview, ReviewError> {
+    let (initial, steps) = replay(room_info)?;
+    let mcts = Mcts::new(budget, room_info.komi).with_cancel(cancel.clone());
+    let (high, low) = room_info.room_id.as_u64_pair();
+    let mut rng = StdRng::seed_from_u64(high ^ low);
+
+    let initial_black_win_rate = black_win_rate(&mcts.evaluate(&initial, &mut rng), &initial);
+    let mut before = &initial;
+    let mut moves = Vec::with_capacity(steps.len());
+    for step in &steps {
+        if cancel.is_cancelled() {
+            return Err(ReviewError::Cancelled);
+        }
+        let judged = step.action != Some(MoveAction::Measure);
+        let (black, win_rate_drop, best) = match step.color.filter(|_| judged) {
+            Some(color) => {
+                let comparison = mcts.compare(before, &step.position, &mut rng);
+                let black = match color {
+                    Stone::Black => comparison.played_win_rate,
+                    Stone::White => 1.0 - comparison.played_win_rate,
+                };
+                let best = comparison.best.filter(|&best| step.played != Some(best));
+                (black, comparison.best_win_rate - comparison.played_win_rate, best)
+            }
+            None => (black_win_rate(&mcts.evaluate(&step.position, &mut rng), &step.position), 0.0, None),
+        };
+        moves.push(MoveReview {
+            number: step.number,
+            color: step.color.map(|color| color.as_str().to_string()),
+            position: step.label(),
+            black_win_rate: black,
+            win_rate_drop,
+            best: best.map(|p| p.to_string()),
+            judgement: Judgement::from_drop(win_rate_drop).filter(|_| judged),
+        });
+        before = &step.position;
+    }
+    Ok(GameReview { initial_black_win_rate, moves })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 按 moves 依次落子（"pass" 为停一手）得到的房间
+    fn played_room(width: i32, variant: Variant, handicap: &[Point], moves: &[&str]) -> RoomInfo {
+        let empty = QuantumPosition {
+            variant,
+            quantum_pairs: if variant == Variant::Quantum { 1 } else { 0 },
+            ..QuantumPosition::with_boards(width, width, if variant == Variant::Quantum { 2 } else { 1 })
+        };
+        let mut position = empty.with_handicap(handicap).unwrap();
+        let mut records = Vec::new();
+        for mv in moves {
+            let color = position.next_color();
+            let outcome = if *mv == "pass" { position.pass(color) } else { position.play(mv, color) }.unwrap();
+            records.push(serde_json::to_value(&outcome.record).unwrap());
+            position = outcome.position;
+        }
+        RoomInfo {
+            model: width,
+            variant: variant.as_str().to_string(),
+            ko_rule: "simple".to_string(),
+            quantum_pairs: position.quantum_pairs,
+            handicap: handicap.len() as i32,
+            komi: 0.5,
+            board: position.board_value(),
+            moves: position.moves,
+            position_history: position.history_value(),
+            chessman_records: serde_json::Value::Array(records),
+            status: "finished".to_string(),
+            ..RoomInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_replay_reconstructs_final_position() {
+        let room = played_room(5, Variant::Quantum, &[], &["3,3", "2,2", "4,4", "2,3", "pass"]);
+        let (initial, steps) = replay(&room).unwrap();
+        assert_eq!(initial.boards.len(), 2);
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[4].label(), "pass");
+        let last = &steps[4].position;
+        assert_eq!(last.boards, QuantumPosition::from_room_info(&room).boards);
+        assert_eq!(last.history, parse_history(&room.position_history));
+    }
+
+    #[test]
+    fn test_replay_finds_fixed_handicap_and_stops_at_resignation() {
+        let stones = fixed_handicap_points(9, 9, 2).unwrap();
+        let mut room = played_room(9, Variant::Classical, &stones, &["5,5", "3,3"]);
+        let mut records = room.chessman_records.as_array().cloned().unwrap();
+        records.push(serde_json::to_value(ChessmanRecord::action(MoveAction::Resign, "white")).unwrap());
+        room.chessman_records = serde_json::Value::Array(records);
+
+        let (initial, steps) = replay(&room).unwrap();
+        assert_eq!(initial.boards[0].stone(stones[0]), Some(Stone::Black));
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].color, Some(Stone::White));
+
+        // 自由摆放的让子无法从哈希还原
+        let free = played_room(9, Variant::Classical, &[Point::new(1, 1), Point::new(2, 2)], &["5,5"]);
+        assert_eq!(replay(&free).unwrap_err(), ReviewError::UnknownStart);
+    }
+
+    #[test]
+    fn test_review_game_marks_judgements() {
+        // 前 12 手是引擎自战的着法，第 13 手黑棋停一手（应下 3,4），局势随之逆转
+        let moves = ["3,3", "3,4", "2,2", "4,2", "4,4", "4,3", "2,4", "5,4", "3,2", "4,1", "3,5", "1,2", "pass"];
+        let room = played_room(5, Variant::Classical, &[], &moves);
+        let budget = SearchBudget { playouts: 200, time_limit: Duration::from_secs(30) };
+        let review = review_game(&room, budget, &CancelToken::default()).unwrap();
+        assert_eq!(review.moves.len(), moves.len());
+        for (index, review) in review.moves.iter().enumerate() {
+            assert_eq!(review.number, index + 1);
+            assert!((0.0..=1.0).contains(&review.black_win_rate));
+            assert_eq!(review.judgement, Judgement::from_drop(review.win_rate_drop));
+        }
+        let blunder = review.moves.last().unwrap();
+        assert_eq!(blunder.judgement, Some(Judgement::Blunder));
+        assert!(review.moves[..moves.len() - 1].iter().all(|review| review.judgement != Some(Judgement::Blunder)));
+
+        // 随机数由房间号决定，同一局的复盘结果不变
+        let short = played_room(5, Variant::Classical, &[], &["3,3", "1,1", "3,2", "pass", "pass"]);
+        let quick = SearchBudget { playouts: 60, ..budget };
+        let reviews = [(); 2].map(|_| serde_json::to_value(review_game(&short, quick, &CancelToken::default()).unwrap()).unwrap());
+        assert_eq!(reviews[0], reviews[1]);
+
+        let cancel = CancelToken::default();
+        cancel.cancel();
+        assert_eq!(review_game(&room, budget, &cancel).unwrap_err(), ReviewError::Cancelled);
+        assert_eq!(Judgement::from_drop(0.25), Some(Judgement::Blunder));
+        assert_eq!(Judgement::from_drop(0.1), Some(Judgement::Mistake));
+        assert_eq!(Judgement::from_drop(0.05), None);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::entity::RoomInfo;
+use crate::mcts::SearchBudget;
+use crate::review::{Judgement, review_game};
+use crate::ws::AppState;
+use std::time::Duration;
+use tracing::info;
+use uuid::Uuid;
+
+/// 复盘时每个局面的搜索预算
+const REVIEW_BUDGET: SearchBudget = SearchBudget {
+    playouts: 400,
+    time_limit: Duration::from_millis(500),
+};
+
+/// 在后台为已结束的房间生成复盘，结果写入 game_reviews；已经登记过的房间不重复生成
+pub fn spawn_review(state: &AppState, room_info: RoomInfo) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let room_id = room_info.room_id;
+        match state.db.create_review(room_id).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(err) => {
+                info!("Failed to create review for room `{room_id}`: {err}");
+                return;
+            }
+        }
+
+        // 在复盘线程池中排队，不会挡住对局中的 AI 落子；任务单独登记，不会因玩家断开而被取消
+        let result = state
+            .review_workers
+            .run(Uuid::new_v4(), move |cancel| review_game(&room_info, REVIEW_BUDGET, &cancel))
+            .await;
+        let stored = match result {
+            Ok(Ok(review)) => {
+                info!(
+                    "Review for room `{room_id}` finished: {} moves, {} mistakes, {} blunders",
+                    review.moves.len(),
+                    review.count(Judgement::Mistake),
+                    review.count(Judgement::Blunder)
+                );
+                let value = serde_json::to_value(&review).unwrap_or_else(|_| serde_json::json!({}));
+                state.db.finish_review(room_id, "done", &value, None).await
+            }
+            Ok(Err(err)) => state.db.finish_review(room_id, "failed", &serde_json::json!({}), Some(&err.to_string())).await,
+            Err(err) => {
+                let message = format!("Review job failed: {:?}", err);
+                state.db.finish_review(room_id, "failed", &serde_json::json!({}), Some(&message)).await
+            }
+        };
+        if let Err(err) = stored {
+            info!("Failed to store review for room `{room_id}`: {err}");
+        }
+    });
+}
@@ -0,0 +1,1234 @@
+use crate::board::{Board, Cell, Point, Stone, WireBoard};
+use crate::entity::{Chessman, RoomInfo};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// 落子记录（与前端 ChessmanRecord 结构一致）
+/// 停一手、认输等非落子动作 add/reduce 为空，并记录 action 与 color
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChessmanRecord {
+    pub add: Vec<Chessman>,
+    pub reduce: Vec<Chessman>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<MoveAction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// 量子落子：与对方的下一手纠缠成对
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub quantum: bool,
+    /// AI 落子的复现信息，玩家的落子为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai: Option<AiDecision>,
+}
+
+/// AI 落子的复现信息：同一局面、同一引擎版本、同一种子与模拟次数得到同一手
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AiDecision {
+    pub engine: String,  // 引擎名称，如 "mcts:advanced"
+    pub version: String, // 引擎版本
+    pub seed: String,    // 本手的随机种子，16 位十六进制（前端的数字放不下 u64）
+    /// MCTS 本手的模拟次数，复现时按此预算搜索（思考时间不可复现）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playouts: Option<u32>,
+    /// 复用的搜索树（上一手的搜索加上后台思考）的访问次数
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub reused: u32,
+    /// 后台思考的模拟次数：AI 上一手之后的局面一段，之后玩家每下一手一段；
+    /// 复现时按此重做思考，缺少时复用了搜索树的一手无法精确复现
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pondered: Vec<u32>,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+impl AiDecision {
+    pub fn new(engine: String, version: String, seed: u64, playouts: Option<u32>, reused: u32) -> Self {
+        Self {
+            engine,
+            version,
+            seed: format!("{:016x}", seed),
+            playouts,
+            reused,
+            pondered: Vec::new(),
+        }
+    }
+
+    /// 记录中的种子，格式不对时为 None
+    pub fn seed(&self) -> Option<u64> {
+        u64::from_str_radix(&self.seed, 16).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveAction {
+    Pass,
+    Resign,
+    Resume,  // 数子有争议，恢复对局
+    Measure, // 测量：叠加态坍缩为一个棋盘
+}
+
+impl ChessmanRecord {
+    pub fn action(action: MoveAction, color: &str) -> Self {
+        Self {
+            action: Some(action),
+            color: Some(color.to_string()),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    InvalidColor(String),
+    InvalidPosition(String),
+    NotYourTurn { expected: String },
+    Occupied(String),
+    Suicide(String),
+    Ko(String),
+    PassNotAllowed,
+    QuantumNotAllowed,
+    MeasureNotAllowed,
+    GameFinished,
+    Scoring,
+    BoardMismatch,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::InvalidColor(color) => write!(f, "Invalid color: {}", color),
+            RuleError::InvalidPosition(pos) => write!(f, "Invalid position: {}", pos),
+            RuleError::NotYourTurn { expected } => write!(f, "Not your turn, {} to play", expected),
+            RuleError::Occupied(pos) => write!(f, "Position {} is already occupied", pos),
+            RuleError::Suicide(pos) => write!(f, "Suicide move at {} is not allowed", pos),
+            RuleError::Ko(pos) => write!(f, "Move at {} violates the ko rule", pos),
+            RuleError::PassNotAllowed => write!(f, "Cannot pass while a quantum move is waiting to be entangled"),
+            RuleError::QuantumNotAllowed => write!(f, "No quantum move is available"),
+            RuleError::MeasureNotAllowed => write!(f, "Measurement is not available now"),
+            RuleError::GameFinished => write!(f, "Game is already finished"),
+            RuleError::Scoring => write!(f, "Game is in the scoring phase"),
+            RuleError::BoardMismatch => write!(f, "Submitted board does not match server state"),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// 打劫规则（随房间保存）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KoRule {
+    /// 禁止立即提回：落子后的局面不能与对方上一手之前的局面相同
+    #[default]
+    Simple,
+    /// 局面同形禁止：落子后的局面不能与历史上任何局面相同
+    PositionalSuperko,
+}
+
+impl KoRule {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "simple" => Some(KoRule::Simple),
+            "positional_superko" => Some(KoRule::PositionalSuperko),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KoRule::Simple => "simple",
+            KoRule::PositionalSuperko => "positional_superko",
+        }
+    }
+
+    /// history 的最后一项为当前局面
+    pub fn forbids(&self, history: &[u64], hash: u64) -> bool {
+        match self {
+            KoRule::Simple => history.len() >= 2 && history[history.len() - 2] == hash,
+            KoRule::PositionalSuperko => history.contains(&hash),
+        }
+    }
+}
+
+/// 对局变体（随房间保存，并区分等级分池）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    /// 量子围棋：多个叠加的棋盘（默认两个），前两手量子落子并纠缠
+    #[default]
+    Quantum,
+    /// 经典围棋：单盘，没有纠缠步骤
+    Classical,
+}
+
+impl Variant {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "quantum" => Some(Variant::Quantum),
+            "classical" => Some(Variant::Classical),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Variant::Quantum => "quantum",
+            Variant::Classical => "classical",
+        }
+    }
+}
+
+/// 每方最多的量子落子数（含开局的一对）
+pub const MAX_QUANTUM_PAIRS: i32 = 9;
+
+/// 量子围棋叠加的棋盘数范围，默认 2 个
+pub const DEFAULT_BOARDS: usize = 2;
+pub const MAX_BOARDS: usize = 4;
+
+/// 房间 board 字段的格式版本：
+/// - 1：{ "board1": {...}, "board2": {...} }（早期格式，没有 version 字段）
+/// - 2：{ "version": 2, "boards": [{...}, ...] }
+pub const BOARD_FORMAT_VERSION: u64 = 2;
+
+/// 多盘量子围棋局面（服务端权威状态）
+///
+/// 每个棋子在各棋盘上各有一个位置，颜色相同：第 k 个棋盘上棋子的 brother
+/// 为它在第 k + 1 个棋盘上的位置（最后一个棋盘指回第一个），两盘时即 board1/board2 互指。
+/// 普通棋子 brother 等于自身坐标，各盘位置一致；纠缠的棋子在各盘间按置换交换位置。
+/// 经典围棋没有纠缠，只有一个棋盘。
+#[derive(Debug, Clone)]
+pub struct QuantumPosition {
+    pub boards: Vec<Board>,
+    pub width: i32,
+    pub height: i32,
+    pub moves: i32, // 已进行的手数（含停一手），前两手为量子落子
+    pub black_lost: i32,
+    pub white_lost: i32,
+    pub ko_rule: KoRule,
+    pub history: Vec<u64>, // 历次局面哈希，最后一项为当前局面
+    pub passes: i32,       // 连续停一手的次数，达到 2 时终局数子
+    pub handicap: i32,     // 让子数，不少于 2 子时白方先行
+    pub variant: Variant,
+    pub quantum_pairs: i32,              // 本局允许的量子对数（开局一对计入），即每方的量子落子数上限
+    pub pairs_used: i32,                 // 已发起的量子落子数
+    pub pending_quantum: Option<Point>,  // 等待对方下一手纠缠的量子子
+}
+
+/// 一次合法落子的结果
+#[derive(Debug, Clone)]
+pub struct MoveOutcome {
+    pub position: QuantumPosition,
+    pub record: ChessmanRecord,
+}
+
+impl QuantumPosition {
+    /// 默认双盘的空局面
+    #[allow(dead_code)]
+    pub fn new(width: i32, height: i32) -> Self {
+        Self::with_boards(width, height, DEFAULT_BOARDS)
+    }
+
+    /// 指定棋盘数的空局面（至少一个棋盘）
+    pub fn with_boards(width: i32, height: i32, count: usize) -> Self {
+        let boards = vec![Board::new(width, height); count.max(1)];
+        Self {
+            history: vec![position_hash(&boards)],
+            boards,
+            width,
+            height,
+            moves: 0,
+            black_lost: 0,
+            white_lost: 0,
+            ko_rule: KoRule::default(),
+            passes: 0,
+            handicap: 0,
+            variant: Variant::default(),
+            quantum_pairs: 1,
+            pairs_used: 0,
+            pending_quantum: None,
+        }
+    }
+
+    /// 让子局：让子同时摆在所有棋盘上，之后由白方先行
+    pub fn with_handicap(mut self, stones: &[Point]) -> Result<Self, RuleError> {
+        for &p in stones {
+            if !self.boards[0].contains(p) {
+                return Err(RuleError::InvalidPosition(p.to_string()));
+            }
+            if self.boards[0].is_occupied(p) {
+                return Err(RuleError::Occupied(p.to_string()));
+            }
+            place_stone(&mut self.boards, p, Stone::Black);
+        }
+        self.handicap = stones.len() as i32;
+        self.history = vec![position_hash(&self.boards)];
+        Ok(self)
+    }
+
+    /// 从数据库中的房间信息恢复局面
+    pub fn from_room_info(room_info: &RoomInfo) -> Self {
+        let (width, height) = room_info.board_size();
+        let variant = Variant::parse(&room_info.variant).unwrap_or_default();
+        let boards = parse_room_boards(&room_info.board, width, height, variant);
+        let mut history = parse_history(&room_info.position_history);
+        // 旧房间没有历史记录时，以当前局面作为起点
+        if history.is_empty() {
+            history.push(position_hash(&boards));
+        }
+        let (pairs_used, pending_quantum) = quantum_progress(&room_info.chessman_records, variant);
+        Self {
+            boards,
+            width,
+            height,
+            moves: room_info.moves,
+            black_lost: room_info.black_lost,
+            white_lost: room_info.white_lost,
+            ko_rule: KoRule::parse(&room_info.ko_rule).unwrap_or_default(),
+            history,
+            passes: trailing_passes(&room_info.chessman_records),
+            handicap: room_info.handicap,
+            variant,
+            quantum_pairs: room_info.quantum_pairs,
+            pairs_used,
+            pending_quantum,
+        }
+    }
+
+    /// 序列化为房间 board 字段（版本 2）：{ "version": 2, "boards": [{...}, ...] }
+    pub fn board_value(&self) -> Value {
+        serde_json::json!({
+            "version": BOARD_FORMAT_VERSION,
+            "boards": self.boards,
+        })
+    }
+
+    /// 从所有棋盘上移除 boards[0] 上位于 p 的棋子，返回它在 boards[0] 上的状态
+    pub fn remove_stone(&mut self, p: Point) -> Option<Cell> {
+        let cell = self.boards[0].get(p)?;
+        let positions = stone_positions(&self.boards, 0, p);
+        for (board, pos) in self.boards.iter_mut().zip(positions) {
+            board.remove(pos);
+        }
+        Some(cell)
+    }
+
+    /// 轮到哪一方（按手数交替，先行方见 first_to_move）
+    pub fn to_move(&self) -> Stone {
+        let first = first_to_move(self.handicap);
+        if self.moves % 2 == 0 { first } else { first.opponent() }
+    }
+
+    pub fn next_color(&self) -> &'static str {
+        self.to_move().as_str()
+    }
+
+    fn check_turn(&self, stone: Stone) -> Result<(), RuleError> {
+        if stone != self.to_move() {
+            return Err(RuleError::NotYourTurn {
+                expected: self.next_color().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// 检查 stone 在 p 落子是否合法（每个棋盘都需合法）
+    fn check_point(&self, p: Point, stone: Stone) -> Result<(), RuleError> {
+        if self.boards.iter().any(|board| board.is_occupied(p)) {
+            return Err(RuleError::Occupied(p.to_string()));
+        }
+        if !self.boards.iter().all(|board| can_put_chess(board, p, stone)) {
+            return Err(RuleError::Suicide(p.to_string()));
+        }
+        Ok(())
+    }
+
+    /// 落子：校验、纠缠（回应量子子）、各盘提子，返回新局面与落子记录
+    pub fn play(&self, position: &str, color: &str) -> Result<MoveOutcome, RuleError> {
+        let stone = parse_color(color)?;
+        self.check_turn(stone)?;
+        self.play_at(parse_position(position, &self.boards[0])?, stone, false)
+    }
+
+    /// 量子落子：对方的下一手与之纠缠成对
+    pub fn play_quantum(&self, position: &str, color: &str) -> Result<MoveOutcome, RuleError> {
+        let stone = parse_color(color)?;
+        self.check_turn(stone)?;
+        self.play_at(parse_position(position, &self.boards[0])?, stone, true)
+    }
+
+    /// 还能否发起量子落子（开局第一手总是量子落子，不需要单独发起）
+    pub fn can_play_quantum(&self) -> bool {
+        self.variant == Variant::Quantum
+            && self.boards.len() > 1
+            && self.moves > 0
+            && self.pending_quantum.is_none()
+            && self.pairs_used < self.quantum_pairs
+    }
+
+    /// 以坐标落子，供 AI 搜索等内部调用
+    pub fn play_at(&self, p: Point, stone: Stone, quantum: bool) -> Result<MoveOutcome, RuleError> {
+        self.check_turn(stone)?;
+        if !self.boards[0].contains(p) {
+            return Err(RuleError::InvalidPosition(p.to_string()));
+        }
+        // 开局第一手本身就是量子落子，显式发起也可以
+        let opening = self.variant == Variant::Quantum && self.moves == 0;
+        if quantum && !opening && !self.can_play_quantum() {
+            return Err(RuleError::QuantumNotAllowed);
+        }
+        self.check_point(p, stone)?;
+
+        let mut next = self.clone();
+        place_stone(&mut next.boards, p, stone);
+
+        // 回应量子子的一手与之纠缠：两子在各棋盘间交换位置（两盘时即第二个棋盘颜色互换）
+        if let Some(first_pos) = self.pending_quantum {
+            entangle(&mut next.boards, &[first_pos, p]);
+            next.pending_quantum = None;
+        }
+        if quantum || opening {
+            next.pending_quantum = Some(p);
+            next.pairs_used += 1;
+        }
+
+        let mut record = ChessmanRecord {
+            add: next.boards[0].chessman(p).into_iter().collect(),
+            quantum: quantum || opening,
+            ..ChessmanRecord::default()
+        };
+
+        for (pos, cell) in remove_captured(&mut next.boards, stone) {
+            match cell.stone {
+                Stone::Black => next.black_lost += 1,
+                Stone::White => next.white_lost += 1,
+            }
+            record.reduce.push(Chessman {
+                position: pos.to_string(),
+                color: cell.stone.as_str().to_string(),
+                brother: cell.brother.to_string(),
+            });
+        }
+
+        let hash = position_hash(&next.boards);
+        if self.ko_rule.forbids(&self.history, hash) {
+            return Err(RuleError::Ko(p.to_string()));
+        }
+        next.history.push(hash);
+
+        next.moves += 1;
+        next.passes = 0;
+        Ok(MoveOutcome { position: next, record })
+    }
+
+    /// 停一手：量子开局完成前、量子子等待纠缠时不允许，经典围棋随时可以停一手
+    pub fn pass(&self, color: &str) -> Result<MoveOutcome, RuleError> {
+        let stone = parse_color(color)?;
+        self.check_turn(stone)?;
+        let opening = self.variant == Variant::Quantum && self.moves == 0;
+        if opening || self.pending_quantum.is_some() {
+            return Err(RuleError::PassNotAllowed);
+        }
+
+        let mut next = self.clone();
+        // 局面不变，重复记录当前哈希，使简单劫的判断仍以“对方上一手之前”为准
+        next.history.push(position_hash(&self.boards));
+        next.moves += 1;
+        next.passes += 1;
+        Ok(MoveOutcome {
+            position: next,
+            record: ChessmanRecord::action(MoveAction::Pass, stone.as_str()),
+        })
+    }
+
+    /// 坍缩：只保留第 index 个棋盘，之后按普通围棋继续（不能再发起量子落子）
+    pub fn collapse(&self, index: usize) -> QuantumPosition {
+        let mut board = self.boards[index.min(self.boards.len() - 1)].clone();
+        let stones: Vec<(Point, Cell)> = board.stones().collect();
+        for (p, cell) in stones {
+            board.set(p, Cell { brother: p, ..cell });
+        }
+        let boards = vec![board];
+        let mut history = self.history.clone();
+        history.push(position_hash(&boards));
+        QuantumPosition {
+            boards,
+            history,
+            pending_quantum: None,
+            ..self.clone()
+        }
+    }
+
+    /// 测量：轮到的一方发起，局面坍缩到抽中的棋盘，不占用手数
+    /// 量子开局完成前、量子子等待纠缠时以及已经坍缩后不允许
+    pub fn measure(&self, color: &str, index: usize) -> Result<MoveOutcome, RuleError> {
+        let stone = parse_color(color)?;
+        self.check_turn(stone)?;
+        let opening = self.variant == Variant::Quantum && self.moves == 0;
+        if self.boards.len() < 2 || opening || self.pending_quantum.is_some() {
+            return Err(RuleError::MeasureNotAllowed);
+        }
+        Ok(MoveOutcome {
+            position: self.collapse(index),
+            record: ChessmanRecord::action(MoveAction::Measure, stone.as_str()),
+        })
+    }
+
+    /// 双方连续停一手，进入数子
+    pub fn both_passed(&self) -> bool {
+        self.passes >= 2
+    }
+
+    pub fn history_value(&self) -> Value {
+        Value::Array(
+            self.history
+                .iter()
+                .map(|hash| Value::String(format!("{hash:016x}")))
+                .collect(),
+        )
+    }
+
+    /// 校验客户端提交的棋盘与服务端计算结果一致（只比较位置与颜色）
+    pub fn verify_submitted(&self, submitted: &Value) -> Result<(), RuleError> {
+        if submitted.is_null() {
+            return Ok(());
+        }
+        // 只比较客户端实际提交的棋盘（旧客户端可能只提交 board1）
+        let provided = provided_boards(submitted);
+        let boards = parse_boards(submitted, self.width, self.height);
+        if boards.iter().zip(&self.boards).take(provided).any(|(a, b)| !same_stones(a, b)) {
+            return Err(RuleError::BoardMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// 先行方：让 2 子及以上时白方先行，否则黑方先行
+pub fn first_to_move(handicap: i32) -> Stone {
+    if handicap >= 2 { Stone::White } else { Stone::Black }
+}
+
+/// 让子的摆放方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HandicapPlacement {
+    /// 按星位固定摆放
+    #[default]
+    Fixed,
+    /// 由黑方自行指定位置
+    Free,
+}
+
+impl HandicapPlacement {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fixed" => Some(HandicapPlacement::Fixed),
+            "free" => Some(HandicapPlacement::Free),
+            _ => None,
+        }
+    }
+}
+
+/// 固定让子的星位（2~9 子），长宽分别计算。
+/// 边长小于 13 时以 3 线为星位，否则以 4 线为星位；边长小于 7 的棋盘没有固定让子
+pub fn fixed_handicap_points(width: i32, height: i32, count: i32) -> Option<Vec<Point>> {
+    if !(2..=9).contains(&count) || width < 7 || height < 7 {
+        return None;
+    }
+    // 某一方向上的 (低位星, 高位星, 中间星)
+    let lines = |side: i32| {
+        let edge = if side < 13 { 3 } else { 4 };
+        (edge as u8, (side + 1 - edge) as u8, ((side + 1) / 2) as u8)
+    };
+    let (x_lo, x_hi, x_mid) = lines(width);
+    let (y_lo, y_hi, y_mid) = lines(height);
+
+    let mut points = vec![Point::new(x_hi, y_lo), Point::new(x_lo, y_hi)];
+    if count >= 3 {
+        points.push(Point::new(x_hi, y_hi));
+    }
+    if count >= 4 {
+        points.push(Point::new(x_lo, y_lo));
+    }
+    if count >= 6 {
+        points.push(Point::new(x_lo, y_mid));
+        points.push(Point::new(x_hi, y_mid));
+    }
+    if count >= 8 {
+        points.push(Point::new(x_mid, y_lo));
+        points.push(Point::new(x_mid, y_hi));
+    }
+    // 奇数子数时加上天元
+    if count % 2 == 1 && count >= 5 {
+        points.push(Point::new(x_mid, y_mid));
+    }
+    Some(points)
+}
+
+fn parse_color(color: &str) -> Result<Stone, RuleError> {
+    Stone::parse(color).ok_or_else(|| RuleError::InvalidColor(color.to_string()))
+}
+
+fn parse_position(position: &str, board: &Board) -> Result<Point, RuleError> {
+    Point::parse(position)
+        .filter(|p| board.contains(*p))
+        .ok_or_else(|| RuleError::InvalidPosition(position.to_string()))
+}
+
+/// 普通棋子：在每个棋盘的同一位置
+fn place_stone(boards: &mut [Board], p: Point, stone: Stone) {
+    let cell = Cell { stone, brother: p };
+    for board in boards {
+        board.set(p, cell);
+    }
+}
+
+/// 第 k 个棋盘上位于 p 的棋子在各棋盘上的位置（下标为棋盘序号），沿 brother 依次查找
+fn stone_positions(boards: &[Board], k: usize, p: Point) -> Vec<Point> {
+    let count = boards.len();
+    let mut positions = vec![p; count];
+    let mut current = p;
+    for step in 1..count {
+        let Some(cell) = boards[(k + step - 1) % count].get(current) else {
+            break;
+        };
+        current = cell.brother;
+        positions[(k + step) % count] = current;
+    }
+    positions
+}
+
+/// 分别在每个棋盘上计算提子，经 brother 找到棋子在其他棋盘上的位置，
+/// 从所有棋盘同时移除并返回被提棋子在 boards[0] 上的坐标与状态
+fn remove_captured(boards: &mut [Board], stone: Stone) -> Vec<(Point, Cell)> {
+    let snapshot: &[Board] = boards;
+    let mut captured: Vec<Vec<Point>> = snapshot
+        .iter()
+        .enumerate()
+        .flat_map(|(k, board)| {
+            captured_stones(board, stone)
+                .into_iter()
+                .map(move |p| stone_positions(snapshot, k, p))
+        })
+        .collect();
+    captured.sort();
+    captured.dedup();
+
+    let mut removed = Vec::new();
+    for positions in captured {
+        if let Some(cell) = boards[0].get(positions[0]) {
+            removed.push((positions[0], cell));
+        }
+        for (board, p) in boards.iter_mut().zip(positions) {
+            board.remove(p);
+        }
+    }
+    removed
+}
+
+/// 落子后的各盘局面（有等待纠缠的量子子时与之纠缠），供 AI 预判打劫等规则
+pub fn position_after(boards: &[Board], p: Point, stone: Stone, pending: Option<Point>) -> Vec<Board> {
+    let mut boards = boards.to_vec();
+    place_stone(&mut boards, p, stone);
+    if let Some(first) = pending {
+        entangle(&mut boards, &[first, p]);
+    }
+    remove_captured(&mut boards, stone);
+    boards
+}
+
+/// 多盘局面哈希（FNV-1a，结果稳定，可持久化；两盘时与早期的双盘哈希一致）
+pub fn position_hash(boards: &[Board]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = FNV_OFFSET;
+    let mut feed = |byte: u8| {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    };
+    for (tag, board) in (1u8..).zip(boards) {
+        for (p, cell) in board.stones() {
+            let color = match cell.stone {
+                Stone::Black => 1,
+                Stone::White => 2,
+            };
+            for byte in [tag, p.x, p.y, color] {
+                feed(byte);
+            }
+        }
+        feed(b'|');
+    }
+    hash
+}
+
+/// 落子记录末尾连续停一手的次数
+fn trailing_passes(records: &Value) -> i32 {
+    records
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .rev()
+                .take_while(|item| item.get("action").and_then(|v| v.as_str()) == Some("pass"))
+                .count() as i32
+        })
+        .unwrap_or(0)
+}
+
+/// 解析房间 position_history 字段（十六进制字符串数组）
+pub fn parse_history(value: &Value) -> Vec<u64> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str())
+                .filter_map(|item| u64::from_str_radix(item, 16).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 从落子记录恢复量子落子进度：(已发起的量子落子数, 等待纠缠的量子子)
+/// 量子围棋的第一手总是量子落子（早期记录没有 quantum 标记）；
+/// 量子子之后只能落子（不能停一手），因此只有最后一条记录可能在等待纠缠
+pub fn quantum_progress(records: &Value, variant: Variant) -> (i32, Option<Point>) {
+    if variant != Variant::Quantum {
+        return (0, None);
+    }
+    let records: Vec<ChessmanRecord> = records
+        .as_array()
+        .map(|items| items.iter().filter_map(|item| serde_json::from_value(item.clone()).ok()).collect())
+        .unwrap_or_default();
+    let is_quantum = |(i, record): &(usize, &ChessmanRecord)| record.action.is_none() && (record.quantum || *i == 0);
+
+    let used = records.iter().enumerate().filter(is_quantum).count() as i32;
+    let pending = records
+        .iter()
+        .enumerate()
+        .next_back()
+        .filter(is_quantum)
+        .and_then(|(_, record)| record.add.first())
+        .and_then(|chessman| Point::parse(&chessman.position));
+    (used, pending)
+}
+
+/// 纠缠一组刚落下的普通棋子 group（各盘位置相同）：在第 k 个棋盘上，
+/// 原本位于 group[j] 的棋子移到 group[(j + k) % len]，即按轮换置换各盘的位置。
+/// 两盘一对时，board2 上两子交换位置，相当于颜色互换。
+fn entangle(boards: &mut [Board], group: &[Point]) {
+    let (count, len) = (boards.len(), group.len());
+    let stones: Vec<Option<Stone>> = group.iter().map(|&p| boards[0].stone(p)).collect();
+    for k in 0..count {
+        let next = (k + 1) % count;
+        for (j, stone) in stones.iter().enumerate() {
+            let Some(stone) = *stone else { continue };
+            let brother = group[(j + next) % len];
+            boards[k].set(group[(j + k) % len], Cell { stone, brother });
+        }
+    }
+}
+
+fn same_stones(a: &Board, b: &Board) -> bool {
+    a.width() == b.width() && a.height() == b.height() && a.points().all(|p| a.stone(p) == b.stone(p))
+}
+
+/// 解析房间 board 字段，兼容以下格式：
+/// - { "version": 2, "boards": [{...}, ...] }
+/// - { "board1": {...}, "board2": {...} }
+/// - [["x,y", chessman], ...]（board1 的条目数组）
+/// - { "x,y": chessman, ... }（board1 平铺）
+///
+/// 早期格式缺少 board2 时按 brother 关系由 board1 镜像生成。
+pub fn parse_boards(value: &Value, width: i32, height: i32) -> Vec<Board> {
+    if let Some(boards) = versioned_boards(value) {
+        let boards: Vec<Board> = boards
+            .iter()
+            .map(|board| Board::from_wire(&parse_single_board(board), width, height))
+            .collect();
+        if !boards.is_empty() {
+            return boards;
+        }
+    }
+
+    let board1_value = value.get("board1").unwrap_or(value);
+    let board1 = Board::from_wire(&parse_single_board(board1_value), width, height);
+    let board2 = match value.get("board2") {
+        Some(board2_value) => Board::from_wire(&parse_single_board(board2_value), width, height),
+        None => mirror_board(&board1),
+    };
+    vec![board1, board2]
+}
+
+/// 按对局变体解析棋盘：早期的经典围棋房间按双盘保存（两盘相同），只保留一个
+pub fn parse_room_boards(value: &Value, width: i32, height: i32, variant: Variant) -> Vec<Board> {
+    let mut boards = parse_boards(value, width, height);
+    if variant == Variant::Classical {
+        boards.truncate(1);
+    }
+    boards
+}
+
+fn versioned_boards(value: &Value) -> Option<&Vec<Value>> {
+    let version = value.get("version")?.as_u64()?;
+    if version > BOARD_FORMAT_VERSION {
+        return None;
+    }
+    value.get("boards")?.as_array()
+}
+
+/// 提交的 board 字段中实际包含的棋盘数
+fn provided_boards(value: &Value) -> usize {
+    match versioned_boards(value) {
+        Some(boards) => boards.len(),
+        None => 1 + value.get("board2").is_some() as usize,
+    }
+}
+
+fn parse_single_board(value: &Value) -> WireBoard {
+    let mut board = WireBoard::new();
+    let entries: Vec<(Option<&str>, &Value)> = match value {
+        Value::Object(obj) => obj.iter().map(|(k, v)| (Some(k.as_str()), v)).collect(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::Array(pair) if pair.len() == 2 => Some((pair[0].as_str(), &pair[1])),
+                Value::Object(_) => Some((None, item)),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    for (key, data) in entries {
+        if let Some(chessman) = parse_chessman(key, data) {
+            board.insert(chessman.position.clone(), chessman);
+        }
+    }
+    board
+}
+
+fn parse_chessman(key: Option<&str>, data: &Value) -> Option<Chessman> {
+    let obj = data.as_object()?;
+    // 兼容 color / type 两种字段名
+    let position = obj.get("position").and_then(|v| v.as_str()).or(key)?;
+    let color = obj
+        .get("type")
+        .and_then(|v| v.as_str())
+        .or_else(|| obj.get("color").and_then(|v| v.as_str()))?;
+    let brother = obj.get("brother").and_then(|v| v.as_str()).unwrap_or(position);
+    Some(Chessman {
+        position: position.to_string(),
+        color: color.to_string(),
+        brother: brother.to_string(),
+    })
+}
+
+fn mirror_board(board1: &Board) -> Board {
+    let mut board2 = Board::new(board1.width(), board1.height());
+    for (p, cell) in board1.stones() {
+        board2.set(cell.brother, Cell { stone: cell.stone, brother: p });
+    }
+    board2
+}
+
+/// 无气的 stone 方棋块
+fn dead_groups(board: &Board, stone: Stone) -> Vec<Vec<Point>> {
+    let mut visited = vec![false; board.area()];
+    let mut groups = Vec::new();
+    for (p, cell) in board.stones() {
+        if cell.stone != stone || visited[board.index(p)] {
+            continue;
+        }
+        let group = board.group(p);
+        for &member in &group {
+            visited[board.index(member)] = true;
+        }
+        if board.liberties(&group) == 0 {
+            groups.push(group);
+        }
+    }
+    groups
+}
+
+/// 落子后应被提走的棋子：先提对方无气棋块，再提己方无气棋块
+pub fn captured_stones(board: &Board, last: Stone) -> Vec<Point> {
+    let mut temp = board.clone();
+    let mut captured = Vec::new();
+    for group in dead_groups(&temp, last.opponent()) {
+        for p in group {
+            temp.remove(p);
+            captured.push(p);
+        }
+    }
+    for group in dead_groups(&temp, last) {
+        captured.extend(group);
+    }
+    captured
+}
+
+/// 单盘落子检查：空点且提子后己方棋块仍有气
+pub fn can_put_chess(board: &Board, p: Point, stone: Stone) -> bool {
+    if !board.contains(p) || board.is_occupied(p) {
+        return false;
+    }
+    let mut temp = board.clone();
+    temp.set(p, Cell { stone, brother: p });
+    for group in dead_groups(&temp, stone.opponent()) {
+        for member in group {
+            temp.remove(member);
+        }
+    }
+    let group = temp.group(p);
+    temp.liberties(&group) > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pt(position: &str) -> Point {
+        Point::parse(position).unwrap()
+    }
+
+    fn play_all(moves: &[&str]) -> QuantumPosition {
+        let mut position = QuantumPosition::new(9, 9);
+        for mv in moves {
+            let color = position.next_color();
+            position = position.play(mv, color).unwrap().position;
+        }
+        position
+    }
+
+    #[test]
+    fn test_quantum_opening_entangles_pair() {
+        let position = play_all(&["3,3", "7,7"]);
+
+        assert_eq!(position.boards[0].stone(pt("3,3")), Some(Stone::Black));
+        assert_eq!(position.boards[0].get(pt("3,3")).unwrap().brother, pt("7,7"));
+        assert_eq!(position.boards[0].stone(pt("7,7")), Some(Stone::White));
+        // 第二个棋盘颜色互换
+        assert_eq!(position.boards[1].stone(pt("3,3")), Some(Stone::White));
+        assert_eq!(position.boards[1].stone(pt("7,7")), Some(Stone::Black));
+        assert_eq!(position.boards[1].get(pt("7,7")).unwrap().brother, pt("3,3"));
+    }
+
+    #[test]
+    fn test_turn_order_and_occupied() {
+        let position = play_all(&["3,3"]);
+        assert_eq!(
+            position.play("4,4", "black").unwrap_err(),
+            RuleError::NotYourTurn { expected: "white".to_string() }
+        );
+        assert_eq!(
+            position.play("3,3", "white").unwrap_err(),
+            RuleError::Occupied("3,3".to_string())
+        );
+        assert_eq!(
+            position.play("0,3", "white").unwrap_err(),
+            RuleError::InvalidPosition("0,3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_capture_on_both_boards() {
+        // 白子 1,1 在角上被黑子 1,2 / 2,1 包围
+        let position = play_all(&["5,5", "6,6", "1,2", "1,1", "2,1"]);
+
+        assert!(!position.boards[0].is_occupied(pt("1,1")));
+        assert!(!position.boards[1].is_occupied(pt("1,1")));
+        assert_eq!(position.white_lost, 1);
+        assert_eq!(position.black_lost, 0);
+    }
+
+    #[test]
+    fn test_capture_resolves_entangled_brother() {
+        // 黑量子子在 1,1（board2 上为白、位于 9,9），在 board1 被白提走后 board2 的兄弟子也被移除
+        let position = play_all(&["1,1", "9,9", "5,5", "1,2", "5,6"]);
+        let outcome = position.play("2,1", "white").unwrap();
+
+        assert!(!outcome.position.boards[0].is_occupied(pt("1,1")));
+        assert!(!outcome.position.boards[1].is_occupied(pt("9,9")));
+        assert_eq!(outcome.position.black_lost, 1);
+        assert_eq!(outcome.record.reduce.len(), 1);
+        assert_eq!(outcome.record.reduce[0].brother, "9,9");
+    }
+
+    #[test]
+    fn test_suicide_rejected() {
+        let position = play_all(&["5,5", "1,2", "6,6", "2,1"]);
+        assert_eq!(
+            position.play("1,1", "black").unwrap_err(),
+            RuleError::Suicide("1,1".to_string())
+        );
+    }
+
+    // 在 2,2 / 3,2 形成劫争，量子对落在远处的 9,9 / 9,8
+    const KO_SETUP: [&str; 11] = [
+        "9,9", "9,8", "2,1", "3,1", "1,2", "2,2", "2,3", "3,3", "8,8", "4,2", "3,2",
+    ];
+
+    #[test]
+    fn test_simple_ko_forbids_immediate_recapture() {
+        let position = play_all(&KO_SETUP);
+        assert!(!position.boards[0].is_occupied(pt("2,2")));
+        assert_eq!(position.white_lost, 1);
+
+        assert_eq!(
+            position.play("2,2", "white").unwrap_err(),
+            RuleError::Ko("2,2".to_string())
+        );
+
+        // 找劫材后可以提回
+        let position = position.play("7,7", "white").unwrap().position;
+        let position = position.play("8,7", "black").unwrap().position;
+        let outcome = position.play("2,2", "white").unwrap();
+        assert!(!outcome.position.boards[0].is_occupied(pt("3,2")));
+    }
+
+    #[test]
+    fn test_positional_superko_forbids_any_repetition() {
+        let history = [1, 2, 3, 4];
+        assert!(!KoRule::Simple.forbids(&history, 1));
+        assert!(KoRule::Simple.forbids(&history, 3));
+        assert!(KoRule::PositionalSuperko.forbids(&history, 1));
+        assert!(!KoRule::PositionalSuperko.forbids(&history, 5));
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let position = play_all(&["3,3", "7,7", "5,5"]);
+        assert_eq!(position.history.len(), 4);
+        assert_eq!(parse_history(&position.history_value()), position.history);
+    }
+
+    #[test]
+    fn test_two_passes_end_the_game() {
+        let position = play_all(&["3,3", "7,7"]);
+        let position = position.pass("black").unwrap().position;
+        assert!(!position.both_passed());
+        assert_eq!(position.next_color(), "white");
+
+        let outcome = position.pass("white").unwrap();
+        assert!(outcome.position.both_passed());
+        assert_eq!(outcome.record.action, Some(MoveAction::Pass));
+        assert_eq!(outcome.record.color.as_deref(), Some("white"));
+
+        // 中间有落子则重新计数
+        let position = position.play("5,5", "white").unwrap().position;
+        assert_eq!(position.passes, 0);
+    }
+
+    #[test]
+    fn test_pass_rejected_during_opening() {
+        let position = play_all(&["3,3"]);
+        assert_eq!(position.pass("white").unwrap_err(), RuleError::PassNotAllowed);
+        assert_eq!(
+            play_all(&["3,3", "7,7"]).pass("white").unwrap_err(),
+            RuleError::NotYourTurn { expected: "black".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_additional_quantum_pairs() {
+        let position = QuantumPosition { quantum_pairs: 2, ..play_all(&["3,3", "7,7"]) };
+        assert!(position.can_play_quantum());
+
+        let position = position.play_quantum("5,5", "black").unwrap().position;
+        assert_eq!(position.pending_quantum, Some(pt("5,5")));
+        assert_eq!(position.pass("white").unwrap_err(), RuleError::PassNotAllowed);
+        assert_eq!(position.play_quantum("2,8", "white").unwrap_err(), RuleError::QuantumNotAllowed);
+
+        let outcome = position.play("2,8", "white").unwrap();
+        let position = outcome.position;
+        assert_eq!(position.boards[0].get(pt("5,5")).unwrap().brother, pt("2,8"));
+        assert_eq!(position.boards[1].stone(pt("5,5")), Some(Stone::White));
+        assert_eq!(position.boards[1].stone(pt("2,8")), Some(Stone::Black));
+        assert_eq!(position.pending_quantum, None);
+        // 两对都已用完
+        assert_eq!(position.play_quantum("6,2", "black").unwrap_err(), RuleError::QuantumNotAllowed);
+    }
+
+    #[test]
+    fn test_measure_collapses_to_one_board() {
+        let position = QuantumPosition { quantum_pairs: 2, ..play_all(&["3,3", "7,7"]) };
+        assert_eq!(play_all(&["3,3"]).measure("white", 0).unwrap_err(), RuleError::MeasureNotAllowed);
+        assert!(matches!(position.measure("white", 0), Err(RuleError::NotYourTurn { .. })));
+
+        // 抽中第二个棋盘：开局一对的颜色互换
+        let outcome = position.measure("black", 1).unwrap();
+        let collapsed = outcome.position;
+        assert_eq!(outcome.record.action, Some(MoveAction::Measure));
+        assert_eq!(collapsed.boards.len(), 1);
+        assert_eq!(collapsed.moves, position.moves);
+        assert_eq!(collapsed.boards[0].stone(pt("3,3")), Some(Stone::White));
+        assert_eq!(collapsed.boards[0].get(pt("3,3")).unwrap().brother, pt("3,3"));
+        assert!(!collapsed.can_play_quantum());
+        assert_eq!(collapsed.measure("black", 0).unwrap_err(), RuleError::MeasureNotAllowed);
+
+        // 坍缩后按普通围棋继续，并且能从 board 字段原样恢复
+        let next = collapsed.play("5,5", "black").unwrap().position;
+        assert_eq!(parse_boards(&next.board_value(), 9, 9), next.boards);
+    }
+
+    #[test]
+    fn test_quantum_progress_from_records() {
+        // 早期记录的开局第一手没有 quantum 标记
+        let records = serde_json::json!([
+            { "add": [{ "position": "3,3", "type": "black", "brother": "3,3" }], "reduce": [] },
+            { "add": [{ "position": "7,7", "type": "white", "brother": "3,3" }], "reduce": [] },
+            { "add": [{ "position": "5,5", "type": "black", "brother": "5,5" }], "reduce": [], "quantum": true }
+        ]);
+        assert_eq!(quantum_progress(&records, Variant::Quantum), (2, Some(pt("5,5"))));
+        assert_eq!(quantum_progress(&records, Variant::Classical), (0, None));
+    }
+
+    #[test]
+    fn test_classical_has_no_entanglement() {
+        let mut position = QuantumPosition { variant: Variant::Classical, ..QuantumPosition::with_boards(9, 9, 1) };
+        // 经典围棋开局即可停一手
+        assert!(position.pass("black").is_ok());
+
+        for mv in ["3,3", "7,7"] {
+            let color = position.next_color();
+            position = position.play(mv, color).unwrap().position;
+        }
+        assert_eq!(position.boards.len(), 1);
+        assert_eq!(position.boards[0].get(pt("3,3")).unwrap().brother, pt("3,3"));
+        assert_eq!(position.boards[0].stone(pt("7,7")), Some(Stone::White));
+    }
+
+    #[test]
+    fn test_entanglement_permutes_across_three_boards() {
+        let mut position = QuantumPosition::with_boards(9, 9, 3);
+        for mv in ["3,3", "7,7"] {
+            let color = position.next_color();
+            position = position.play(mv, color).unwrap().position;
+        }
+
+        // 第二个棋盘上两子交换位置，第三个棋盘轮换回原位
+        assert_eq!(position.boards[0].get(pt("3,3")).unwrap().brother, pt("7,7"));
+        assert_eq!(position.boards[1].stone(pt("7,7")), Some(Stone::Black));
+        assert_eq!(position.boards[1].get(pt("7,7")).unwrap().brother, pt("3,3"));
+        assert_eq!(position.boards[2].stone(pt("3,3")), Some(Stone::Black));
+        assert_eq!(position.boards[2].get(pt("3,3")).unwrap().brother, pt("3,3"));
+
+        // 移除黑子时沿 brother 找到它在每个棋盘上的位置
+        let mut removed = position.clone();
+        assert_eq!(removed.remove_stone(pt("3,3")).unwrap().stone, Stone::Black);
+        assert!(!removed.boards[0].is_occupied(pt("3,3")));
+        assert!(!removed.boards[1].is_occupied(pt("7,7")));
+        assert!(!removed.boards[2].is_occupied(pt("3,3")));
+        assert_eq!(removed.boards[1].stone(pt("3,3")), Some(Stone::White));
+
+        let value = position.board_value();
+        assert_eq!(value["version"], BOARD_FORMAT_VERSION);
+        assert_eq!(parse_boards(&value, 9, 9), position.boards);
+    }
+
+    #[test]
+    fn test_trailing_passes_from_records() {
+        let records = serde_json::json!([
+            { "add": [], "reduce": [], "action": "pass", "color": "black" },
+            { "add": [{ "position": "5,5", "type": "white", "brother": "5,5" }], "reduce": [] },
+            { "add": [], "reduce": [], "action": "pass", "color": "black" },
+            { "add": [], "reduce": [], "action": "pass", "color": "white" }
+        ]);
+        assert_eq!(trailing_passes(&records), 2);
+    }
+
+    #[test]
+    fn test_rectangular_board_edges() {
+        let mut position = QuantumPosition::new(7, 5);
+        // 7x5 棋盘上 7,5 是角，白子被 6,5 / 7,4 两子提走
+        for mv in ["1,1", "1,5", "6,5", "7,5", "7,4"] {
+            let color = position.next_color();
+            position = position.play(mv, color).unwrap().position;
+        }
+        assert!(!position.boards[0].is_occupied(pt("7,5")));
+        assert_eq!(position.white_lost, 1);
+        assert_eq!(
+            position.play("5,7", "white").unwrap_err(),
+            RuleError::InvalidPosition("5,7".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fixed_handicap_layouts() {
+        for count in 2..=9 {
+            let points = fixed_handicap_points(19, 19, count).unwrap();
+            assert_eq!(points.len(), count as usize);
+        }
+        assert!(fixed_handicap_points(19, 19, 5).unwrap().contains(&pt("10,10")));
+        assert!(fixed_handicap_points(9, 9, 4).unwrap().contains(&pt("3,3")));
+        assert!(fixed_handicap_points(13, 13, 2).unwrap().contains(&pt("10,4")));
+        assert!(fixed_handicap_points(19, 19, 1).is_none());
+        assert!(fixed_handicap_points(5, 5, 2).is_none());
+        assert!(fixed_handicap_points(13, 9, 2).unwrap().contains(&pt("10,3")));
+        assert!(fixed_handicap_points(19, 19, 10).is_none());
+    }
+
+    #[test]
+    fn test_handicap_game_white_moves_first() {
+        let stones = fixed_handicap_points(9, 9, 2).unwrap();
+        let position = QuantumPosition::new(9, 9).with_handicap(&stones).unwrap();
+        assert_eq!(position.next_color(), "white");
+        assert_eq!(position.history.len(), 1);
+        for p in &stones {
+            assert_eq!(position.boards[0].stone(*p), Some(Stone::Black));
+            assert_eq!(position.boards[1].stone(*p), Some(Stone::Black));
+        }
+
+        // 量子开局为白先黑后，纠缠的是双方的量子子而不是让子
+        let position = position.play("5,5", "white").unwrap().position;
+        let position = position.play("1,1", "black").unwrap().position;
+        assert_eq!(position.boards[0].get(pt("5,5")).unwrap().brother, pt("1,1"));
+        assert_eq!(position.boards[1].stone(pt("5,5")), Some(Stone::Black));
+        assert_eq!(position.boards[0].get(stones[0]).unwrap().brother, stones[0]);
+        assert_eq!(position.next_color(), "white");
+
+        assert_eq!(
+            QuantumPosition::new(9, 9).with_handicap(&[pt("3,3"), pt("3,3")]).unwrap_err(),
+            RuleError::Occupied("3,3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_legacy_board_formats() {
+        let entries = serde_json::json!([
+            ["3,3", { "position": "3,3", "type": "black", "brother": "7,7" }]
+        ]);
+        let boards = parse_boards(&entries, 9, 9);
+        assert_eq!(boards[0].stone(pt("3,3")), Some(Stone::Black));
+        assert_eq!(boards[1].get(pt("7,7")).unwrap().brother, pt("3,3"));
+
+        let flat = serde_json::json!({
+            "4,4": { "position": "4,4", "color": "white", "brother": "4,4" }
+        });
+        let boards = parse_boards(&flat, 9, 9);
+        assert_eq!(boards[0].stone(pt("4,4")), Some(Stone::White));
+        assert_eq!(boards[1].stone(pt("4,4")), Some(Stone::White));
+
+        // 早期双盘房间与版本 2 格式解析结果一致
+        let position = play_all(&["3,3", "7,7", "5,5"]);
+        let legacy = serde_json::json!({ "board1": position.boards[0], "board2": position.boards[1] });
+        assert_eq!(parse_boards(&legacy, 9, 9), position.boards);
+    }
+
+    #[test]
+    fn test_verify_submitted_detects_tampering() {
+        let position = play_all(&["3,3", "7,7", "5,5"]);
+        assert!(position.verify_submitted(&position.board_value()).is_ok());
+
+        let mut forged = position.board_value();
+        forged["boards"][0]["5,5"]["type"] = serde_json::json!("white");
+        assert_eq!(position.verify_submitted(&forged).unwrap_err(), RuleError::BoardMismatch);
+
+        // 旧客户端提交的 board1/board2
+        let legacy = serde_json::json!({ "board1": position.boards[0], "board2": position.boards[1] });
+        assert!(position.verify_submitted(&legacy).is_ok());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::entity::RoomInfo;
+use crate::board::{Board, Point, Stone};
+use crate::rules::QuantumPosition;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 计分规则（随房间保存）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleSet {
+    /// 数子法：棋子 + 围住的空点
+    #[default]
+    Area,
+    /// 数目法：围住的空点 + 提子数
+    Territory,
+}
+
+impl RuleSet {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "area" => Some(RuleSet::Area),
+            "territory" => Some(RuleSet::Territory),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleSet::Area => "area",
+            RuleSet::Territory => "territory",
+        }
+    }
+
+    /// 未指定贴目时的默认值
+    pub fn default_komi(&self) -> f64 {
+        match self {
+            RuleSet::Area => 7.5,
+            RuleSet::Territory => 6.5,
+        }
+    }
+
+    /// 让子局的默认贴目：只贴半目；数子法下让子本身也计入黑方子数，另外补还白方每子一目
+    pub fn handicap_komi(&self, handicap: i32) -> f64 {
+        match self {
+            RuleSet::Area if handicap >= 2 => 0.5 + handicap as f64,
+            _ => 0.5,
+        }
+    }
+}
+
+/// 单个棋盘的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardScore {
+    pub black_stones: i32,
+    pub white_stones: i32,
+    pub black_territory: i32,
+    pub white_territory: i32,
+}
+
+/// 各盘合并后的终局结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreResult {
+    pub rule_set: RuleSet,
+    pub komi: f64,
+    pub black_score: f64,
+    pub white_score: f64, // 已包含贴目
+    pub winner: String,   // "black" / "white" / "draw"
+    pub boards: Vec<BoardScore>, // 各棋盘的统计，顺序与局面的 boards 一致
+}
+
+/// 统计单盘棋子数与只被一方包围的空点
+pub fn score_board(board: &Board) -> BoardScore {
+    let mut score = BoardScore::default();
+    for (_, cell) in board.stones() {
+        match cell.stone {
+            Stone::Black => score.black_stones += 1,
+            Stone::White => score.white_stones += 1,
+        }
+    }
+
+    let mut visited = vec![false; board.area()];
+    for start in board.points() {
+        if board.is_occupied(start) || visited[board.index(start)] {
+            continue;
+        }
+
+        // 洪水填充整块空地，记录边界棋子的颜色
+        let mut region = 0;
+        let (mut touches_black, mut touches_white) = (false, false);
+        let mut stack = vec![start];
+        visited[board.index(start)] = true;
+        while let Some(p) = stack.pop() {
+            region += 1;
+            for neighbor in board.neighbors(p) {
+                match board.stone(neighbor) {
+                    Some(Stone::Black) => touches_black = true,
+                    Some(Stone::White) => touches_white = true,
+                    None if !visited[board.index(neighbor)] => {
+                        visited[board.index(neighbor)] = true;
+                        stack.push(neighbor);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        match (touches_black, touches_white) {
+            (true, false) => score.black_territory += region,
+            (false, true) => score.white_territory += region,
+            _ => {}
+        }
+    }
+    score
+}
+
+/// 分别计算每个棋盘，取各盘平均值，再为白方加上贴目
+pub fn score_position(position: &QuantumPosition, rule_set: RuleSet, komi: f64) -> ScoreResult {
+    let boards: Vec<BoardScore> = position.boards.iter().map(score_board).collect();
+
+    let points = |score: &BoardScore| -> (f64, f64) {
+        match rule_set {
+            RuleSet::Area => (
+                (score.black_stones + score.black_territory) as f64,
+                (score.white_stones + score.white_territory) as f64,
+            ),
+            RuleSet::Territory => (score.black_territory as f64, score.white_territory as f64),
+        }
+    };
+    let count = boards.len() as f64;
+    let (black_total, white_total) = boards
+        .iter()
+        .map(points)
+        .fold((0.0, 0.0), |(black, white), (b, w)| (black + b, white + w));
+
+    let mut black_score = black_total / count;
+    let mut white_score = white_total / count + komi;
+    if rule_set == RuleSet::Territory {
+        // 提子在所有棋盘上同时发生，只计一次
+        black_score += position.white_lost as f64;
+        white_score += position.black_lost as f64;
+    }
+
+    let winner = if black_score > white_score {
+        "black"
+    } else if white_score > black_score {
+        "white"
+    } else {
+        "draw"
+    };
+
+    ScoreResult {
+        rule_set,
+        komi,
+        black_score,
+        white_score,
+        winner: winner.to_string(),
+        boards,
+    }
+}
+
+/// 终局数子阶段的状态（房间 scoring_state 字段）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoringState {
+    #[serde(default)]
+    pub dead_stones: Vec<String>, // 被标记为死子的棋子在第一个棋盘上的坐标
+    #[serde(default)]
+    pub accepted: Vec<String>, // 已同意结果的一方颜色
+}
+
+impl ScoringState {
+    pub fn from_value(value: &Value) -> Self {
+        serde_json::from_value(value.clone()).unwrap_or_default()
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+
+    /// 切换 position 所在整块棋的死活标记；任何修改都会清空双方的确认
+    pub fn toggle_group(&mut self, position: &QuantumPosition, pos: &str) -> bool {
+        let Some(p) = Point::parse(pos).filter(|p| position.boards[0].is_occupied(*p)) else {
+            return false;
+        };
+        let group: Vec<String> = position.boards[0].group(p).iter().map(Point::to_string).collect();
+        if self.dead_stones.iter().any(|dead| group.contains(dead)) {
+            self.dead_stones.retain(|dead| !group.contains(dead));
+        } else {
+            self.dead_stones.extend(group);
+            self.dead_stones.sort();
+        }
+        self.accepted.clear();
+        true
+    }
+
+    /// 记录一方同意，返回双方是否都已同意
+    pub fn accept(&mut self, color: &str) -> bool {
+        if !self.accepted.iter().any(|c| c == color) {
+            self.accepted.push(color.to_string());
+        }
+        self.accepted.iter().any(|c| c == "black") && self.accepted.iter().any(|c| c == "white")
+    }
+}
+
+/// 移除死子（所有棋盘同时移除，并计入提子）后计分
+pub fn score_with_dead_stones(
+    position: &QuantumPosition,
+    dead_stones: &[String],
+    rule_set: RuleSet,
+    komi: f64,
+) -> ScoreResult {
+    let mut position = position.clone();
+    for p in dead_stones.iter().filter_map(|pos| Point::parse(pos)) {
+        if let Some(cell) = position.remove_stone(p) {
+            match cell.stone {
+                Stone::Black => position.black_lost += 1,
+                Stone::White => position.white_lost += 1,
+            }
+        }
+    }
+    score_position(&position, rule_set, komi)
+}
+
+/// 按房间设置（计分规则、贴目、死子标记）对当前局面计分
+pub fn score_room(room_info: &RoomInfo) -> ScoreResult {
+    let rule_set = RuleSet::parse(&room_info.rule_set).unwrap_or_default();
+    let scoring_state = ScoringState::from_value(&room_info.scoring_state);
+    score_with_dead_stones(
+        &QuantumPosition::from_room_info(room_info),
+        &scoring_state.dead_stones,
+        rule_set,
+        room_info.komi,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_all(moves: &[&str]) -> QuantumPosition {
+        let mut position = QuantumPosition::new(9, 9);
+        for mv in moves {
+            let color = position.next_color();
+            position = position.play(mv, color).unwrap().position;
+        }
+        position
+    }
+
+    #[test]
+    fn test_score_board_counts_full_coordinate_range() {
+        // 黑子占满第 5 列、白子占满第 6 列：左侧 36 点归黑，右侧 27 点归白
+        let moves: Vec<String> = (1..=9).flat_map(|y| [format!("5,{y}"), format!("6,{y}")]).collect();
+        let moves: Vec<&str> = moves.iter().map(String::as_str).collect();
+        let position = play_all(&moves);
+
+        let score = score_board(&position.boards[0]);
+        assert_eq!(score.black_stones, 9);
+        assert_eq!(score.white_stones, 9);
+        assert_eq!(score.black_territory, 36);
+        assert_eq!(score.white_territory, 27);
+    }
+
+    #[test]
+    fn test_area_score_applies_komi() {
+        let position = play_all(&["3,3", "7,7", "5,5"]);
+        let result = score_position(&position, RuleSet::Area, 7.5);
+
+        // 两盘上各有黑白棋子，空地边界混色，只计棋子
+        assert_eq!(result.black_score, 2.0);
+        assert_eq!(result.white_score, 1.0 + 7.5);
+        assert_eq!(result.winner, "white");
+    }
+
+    #[test]
+    fn test_territory_score_counts_prisoners_once() {
+        let position = play_all(&["5,5", "6,6", "1,2", "1,1", "2,1"]);
+        let result = score_position(&position, RuleSet::Territory, 0.0);
+        assert_eq!(position.white_lost, 1);
+        // 1,1 被黑方围住（两盘相同）+ 1 颗提子
+        assert_eq!(result.black_score, 1.0 + 1.0);
+    }
+
+    #[test]
+    fn test_dead_stones_removed_from_both_boards() {
+        // 白子 2,2 被黑子三面包围，标记为死子后在两盘上同时移除
+        let position = play_all(&["5,5", "6,6", "1,2", "2,2", "2,1", "8,8", "3,2"]);
+        let mut state = ScoringState::default();
+        assert!(state.toggle_group(&position, "2,2"));
+        assert_eq!(state.dead_stones, vec!["2,2".to_string()]);
+
+        let alive = score_position(&position, RuleSet::Territory, 0.0);
+        let dead = score_with_dead_stones(&position, &state.dead_stones, RuleSet::Territory, 0.0);
+        assert_eq!(dead.boards[0].white_stones, alive.boards[0].white_stones - 1);
+        assert_eq!(dead.boards[1].white_stones, alive.boards[1].white_stones - 1);
+        assert!(dead.black_score >= alive.black_score + 1.0); // 至少多一颗提子
+
+        // 再次切换取消标记
+        assert!(state.toggle_group(&position, "2,2"));
+        assert!(state.dead_stones.is_empty());
+        assert!(!state.toggle_group(&position, "4,4"));
+    }
+
+    #[test]
+    fn test_both_players_must_accept() {
+        let position = play_all(&["5,5", "6,6", "1,2", "2,2"]);
+        let mut state = ScoringState::default();
+        assert!(!state.accept("black"));
+        assert!(!state.accept("black"));
+        state.toggle_group(&position, "2,2");
+        assert!(state.accepted.is_empty());
+        assert!(!state.accept("white"));
+        assert!(state.accept("black"));
+    }
+
+    #[test]
+    fn test_handicap_komi() {
+        assert_eq!(RuleSet::Territory.handicap_komi(4), 0.5);
+        assert_eq!(RuleSet::Area.handicap_komi(4), 4.5);
+        // 让先：不贴目只贴半目
+        assert_eq!(RuleSet::Area.handicap_komi(1), 0.5);
+    }
+
+    #[test]
+    fn test_equal_scores_are_a_draw() {
+        let position = QuantumPosition::new(9, 9);
+        let result = score_position(&position, RuleSet::Area, 0.0);
+        assert_eq!(result.winner, "draw");
+    }
+}
@@ -0,0 +1,226 @@
+use crate::engine::CancelToken;
+use std::collections::HashMap;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use tokio::sync::oneshot;
+use tracing::info;
+use uuid::Uuid;
+
+/// 线程数的环境变量，未设置时取 CPU 核数
+pub const WORKERS_ENV: &str = "AI_WORKERS";
+/// 复盘线程数的环境变量，未设置时为 1
+pub const REVIEW_WORKERS_ENV: &str = "REVIEW_WORKERS";
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// AI 计算任务失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobError {
+    /// 玩家断开、对局结束或同一房间发起了新的请求
+    Cancelled,
+    /// 任务执行中 panic 或线程池已关闭
+    Failed,
+}
+
+/// AI 计算专用的线程池：搜索不占用 Tokio 的工作线程与阻塞线程池。
+/// 每个房间同时只有一个在途任务，可按房间取消
+pub struct AiWorkers {
+    jobs: Mutex<Sender<Job>>,
+    active: Mutex<HashMap<Uuid, CancelToken>>,
+}
+
+impl AiWorkers {
+    pub fn new(threads: usize) -> Self {
+        Self::named("ai-worker", threads)
+    }
+
+    /// 线程名为 `<name>-<序号>` 的线程池
+    pub fn named(name: &str, threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..threads.max(1) {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("{}-{}", name, index))
+                .spawn(move || work(&receiver))
+                .expect("Failed to spawn AI worker");
+        }
+        Self {
+            jobs: Mutex::new(sender),
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let threads = std::env::var(WORKERS_ENV)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .or_else(|| thread::available_parallelism().ok().map(usize::from))
+            .unwrap_or(1);
+        info!("Starting {} AI workers", threads);
+        Self::new(threads)
+    }
+
+    /// 复盘专用的线程池：整局复盘要搜索很久，与 AI 落子分开排队，
+    /// 线程数少于 AI 线程池，对局中的落子总有空闲线程
+    pub fn reviews_from_env() -> Self {
+        let threads = std::env::var(REVIEW_WORKERS_ENV)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1);
+        info!("Starting {} review workers", threads);
+        Self::named("review-worker", threads)
+    }
+
+    /// 在线程池中为 room_id 执行 job，同一房间之前的任务被取消。
+    /// 任务结束前被取消（或等待结果的请求被丢弃）时返回 Cancelled
+    pub async fn run<T, F>(&self, room_id: Uuid, job: F) -> Result<T, JobError>
+    where
+        T: Send + 'static,
+        F: FnOnce(CancelToken) -> T + Send + 'static,
+    {
+        let cancel = CancelToken::default();
+        if let Some(previous) = self.lock_active().insert(room_id, cancel.clone()) {
+            previous.cancel();
+        }
+        let guard = ActiveJob { workers: self, room_id, cancel: cancel.clone() };
+
+        let (sender, receiver) = oneshot::channel();
+        let token = cancel.clone();
+        let job: Job = Box::new(move || {
+            let _ = sender.send(job(token));
+        });
+        let sent = self.jobs.lock().unwrap_or_else(PoisonError::into_inner).send(job);
+        if sent.is_err() {
+            return Err(JobError::Failed);
+        }
+
+        let result = receiver.await.map_err(|_| JobError::Failed);
+        let cancelled = cancel.is_cancelled();
+        drop(guard);
+        if cancelled {
+            return Err(JobError::Cancelled);
+        }
+        result
+    }
+
+    /// 取消房间的在途任务（玩家断开或对局结束）
+    pub fn cancel(&self, room_id: Uuid) {
+        if let Some(cancel) = self.lock_active().remove(&room_id) {
+            info!("Cancelling AI job for room `{room_id}`");
+            cancel.cancel();
+        }
+    }
+
+    fn lock_active(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, CancelToken>> {
+        self.active.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = receiver.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        let Ok(job) = job else {
+            break;
+        };
+        // 任务 panic 时结果通道被丢弃，请求方得到 Failed，线程继续服务
+        let _ = catch_unwind(AssertUnwindSafe(job));
+    }
+}
+
+/// 在途任务的登记：结束或请求被丢弃（如 HTTP 连接断开）时注销，未完成的任务随之取消
+struct ActiveJob<'a> {
+    workers: &'a AiWorkers,
+    room_id: Uuid,
+    cancel: CancelToken,
+}
+
+impl Drop for ActiveJob<'_> {
+    fn drop(&mut self) {
+        let mut active = self.workers.lock_active();
+        // 同一房间的新任务可能已替换了登记
+        if active.get(&self.room_id).is_some_and(|cancel| cancel.same(&self.cancel)) {
+            active.remove(&self.room_id);
+        }
+        self.cancel.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// 直到被取消才返回，最长 5 秒
+    fn wait_for_cancel(cancel: CancelToken) -> bool {
+        let started = Instant::now();
+        while !cancel.is_cancelled() && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        cancel.is_cancelled()
+    }
+
+    #[tokio::test]
+    async fn test_run_returns_result_off_runtime() {
+        let workers = AiWorkers::new(2);
+        let name = workers
+            .run(Uuid::new_v4(), |_| thread::current().name().map(str::to_string))
+            .await
+            .unwrap();
+        assert!(name.unwrap().starts_with("ai-worker-"));
+        assert!(workers.lock_active().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_room_job() {
+        let workers = Arc::new(AiWorkers::new(1));
+        let room_id = Uuid::new_v4();
+        let running = {
+            let workers = Arc::clone(&workers);
+            tokio::spawn(async move { workers.run(room_id, wait_for_cancel).await })
+        };
+        while !workers.lock_active().contains_key(&room_id) {
+            tokio::task::yield_now().await;
+        }
+        workers.cancel(room_id);
+        assert_eq!(running.await.unwrap(), Err(JobError::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn test_new_job_replaces_previous_and_panics_are_contained() {
+        let workers = Arc::new(AiWorkers::new(2));
+        let room_id = Uuid::new_v4();
+        let first = {
+            let workers = Arc::clone(&workers);
+            tokio::spawn(async move { workers.run(room_id, wait_for_cancel).await })
+        };
+        while !workers.lock_active().contains_key(&room_id) {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(workers.run(room_id, |_| 7).await, Ok(7));
+        assert_eq!(first.await.unwrap(), Err(JobError::Cancelled));
+
+        let failed: Result<(), JobError> = workers.run(room_id, |_| panic!("search failed")).await;
+        assert_eq!(failed, Err(JobError::Failed));
+        assert_eq!(workers.run(room_id, |_| 8).await, Ok(8));
+    }
+
+    #[tokio::test]
+    async fn test_busy_review_pool_does_not_delay_ai_moves() {
+        let (workers, reviews) = (AiWorkers::new(1), Arc::new(AiWorkers::named("review-worker", 1)));
+        let review_id = Uuid::new_v4();
+        let review = {
+            let reviews = Arc::clone(&reviews);
+            tokio::spawn(async move { reviews.run(review_id, wait_for_cancel).await })
+        };
+        while !reviews.lock_active().contains_key(&review_id) {
+            tokio::task::yield_now().await;
+        }
+        let name = workers.run(Uuid::new_v4(), |_| thread::current().name().map(str::to_string)).await;
+        assert!(name.unwrap().unwrap().starts_with("ai-worker-"));
+        reviews.cancel(review_id);
+        assert_eq!(review.await.unwrap(), Err(JobError::Cancelled));
+    }
+}
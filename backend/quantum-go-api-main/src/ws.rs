@@ -1,8 +1,18 @@
+use crate::ai::{MoveHint, Opponent, accepts_dead_stones};
+use crate::bots::BotAccounts;
+use crate::collapse::{CollapseRule, draw_board};
 use crate::db::Database;
+use crate::engine::EngineRegistry;
 use crate::entity::Room;
 use crate::entity::WsSender;
 use crate::entity::{Chessman, RoomInfo, GameResult};
-use crate::rating::RatingSystem;
+use crate::hints::{HintLimiter, room_hints};
+use crate::ponder::Ponderer;
+use crate::rating::{RatingSystem, rating_pool};
+use crate::reviewer::spawn_review;
+use crate::rules::{ChessmanRecord, MoveAction, MoveOutcome, QuantumPosition, RuleError};
+use crate::scoring::{RuleSet, ScoreResult, ScoringState, score_room};
+use crate::worker::AiWorkers;
 use axum::{
     extract::{
         Path, State,
@@ -24,6 +34,12 @@ use uuid::Uuid;
 pub struct AppState {
     pub rooms: Arc<Mutex<HashMap<Uuid, Room>>>,
     pub db: Arc<Database>,
+    pub engines: Arc<EngineRegistry>, // 可供房间选用的 AI 引擎
+    pub ai_workers: Arc<AiWorkers>,   // AI 计算线程池
+    pub review_workers: Arc<AiWorkers>, // 复盘线程池，不占用 AI 落子的线程
+    pub ponderer: Arc<Ponderer>,      // AI 在玩家回合的后台思考
+    pub hint_limiter: Arc<HintLimiter>, // 每局提示的频率限制
+    pub bots: Arc<BotAccounts>,       // AI 机器人账号
 }
 
 pub async fn ws_handler(
@@ -62,7 +78,12 @@ async fn handle_user_connection(
     user_id: Uuid,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     let is_owner = user_id == room_info.owner_id;
-    let is_visitor = room_info.visitor_id.map_or(true, |vid| vid == user_id);
+    let is_visitor = room_info.visitor_id.is_none_or(|vid| vid == user_id);
+
+    // AI 对战房间的对手由服务端驱动，其他人不能以访客身份加入
+    if !is_owner && Opponent::from_room_info(room_info) != Opponent::Human {
+        return Err("This is an AI battle room".into());
+    }
 
     if is_owner {
         room.user1 = Some(ws_sender.clone());
@@ -153,21 +174,8 @@ async fn update_room_visitor(
     state
         .db
         .update_room(&RoomInfo {
-            id: room_info.id,
-            room_id: room_info.room_id,
-            owner_id: room_info.owner_id,
             visitor_id: Some(user_id),
-            status: room_info.status.clone(),
-            round: room_info.round.clone(),
-            winner: room_info.winner.clone(),
-            board: room_info.board.clone(),
-            countdown: room_info.countdown,
-            moves: room_info.moves,
-            black_lost: room_info.black_lost,
-            white_lost: room_info.white_lost,
-            model: room_info.model,
-            chessman_records: room_info.chessman_records.clone(),
-            phase: room_info.phase.clone(), // 新增：复制phase字段
+            ..room_info.clone()
         })
         .await
 }
@@ -186,130 +194,614 @@ async fn process_messages(
             Err(_) => continue,
         };
 
+        // 提示只回复请求方（AI 对战没有对手连接），分析耗时，不持有房间锁
+        if msg.mode == "hint" {
+            handle_hint(&msg, state, room_id, user_id).await;
+            continue;
+        }
+
         let mut rooms = state.rooms.lock().await;
         if let Some(room) = rooms.get_mut(&room_id) {
             // Get room info from database
             let room_info = match state.db.get_room_by_room_id(room_id).await {
                 Ok(info) => info,
                 Err(_) => {
-                    send_error_message(&room.user1.as_ref().unwrap(), "Room not found").await;
-                    send_error_message(&room.user2.as_ref().unwrap(), "Room not found").await;
+                    for tx in [&room.user1, &room.user2].into_iter().flatten() {
+                        send_error_message(tx, "Room not found").await;
+                    }
                     return;
                 }
             };
 
-            let target = if user_id == room_info.owner_id {
-                &mut room.user2
+            let (sender, target) = if user_id == room_info.owner_id {
+                (room.user1.clone(), room.user2.clone())
             } else {
-                &mut room.user1
+                (room.user2.clone(), room.user1.clone())
             };
 
-            if let Some(target_tx) = target {
-                handle_message(&msg, target_tx, state, &room_info, &text).await;
+            // AI 对战没有对手连接，房主的消息照常处理
+            let ai_room = matches!(Opponent::from_room_info(&room_info), Opponent::Ai { .. });
+            if let Some(sender_tx) = sender.filter(|_| target.is_some() || ai_room) {
+                handle_message(&msg, &sender_tx, target.as_ref(), state, &room_info, user_id, &text).await;
             }
         }
     }
 }
 
+/// target_tx 为对手的连接，AI 对战中为空：停一手、认输与数子照常处理，
+/// 落子与测量走 AI 对战的 HTTP 接口，其余消息无人可转发
 async fn handle_message(
     msg: &Data<Value>,
-    target_tx: &mut WsSender,
+    sender_tx: &WsSender,
+    target_tx: Option<&WsSender>,
     state: &AppState,
     room_info: &RoomInfo,
+    user_id: Uuid,
     text: &str,
 ) {
-    match msg.mode.as_str() {
-        "updateChess" => handle_update_chess(msg, target_tx, state, room_info).await,
-        "setWinner" => handle_set_winner(msg, target_tx, state, room_info, text).await,
-        _ => {
+    match (msg.mode.as_str(), target_tx) {
+        ("updateChess", Some(target_tx)) => handle_update_chess(msg, sender_tx, target_tx, state, room_info, user_id).await,
+        ("pass", _) => handle_pass(sender_tx, target_tx, state, room_info, user_id).await,
+        ("resign", _) => handle_resign(sender_tx, target_tx, state, room_info, user_id).await,
+        ("setWinner", _) => handle_set_winner(msg, sender_tx, target_tx, state, room_info, user_id).await,
+        ("toggleDead", _) => handle_toggle_dead(msg, sender_tx, target_tx, state, room_info).await,
+        ("acceptScore", _) => handle_accept_score(sender_tx, target_tx, state, room_info, user_id).await,
+        ("disputeScore", _) => handle_dispute_score(sender_tx, target_tx, state, room_info, user_id).await,
+        ("measure", Some(target_tx)) => handle_measure(sender_tx, target_tx, state, room_info, user_id).await,
+        (_, Some(target_tx)) => {
             let _ = target_tx
                 .lock()
                 .await
                 .send(Message::Text(text.to_string().into()))
                 .await;
         }
+        (_, None) => {}
+    }
+}
+
+async fn handle_hint(msg: &Data<Value>, state: &AppState, room_id: Uuid, user_id: Uuid) {
+    let Ok(room_info) = state.db.get_room_by_room_id(room_id).await else {
+        return;
+    };
+    let sender_tx = {
+        let rooms = state.rooms.lock().await;
+        let room = rooms.get(&room_id);
+        if user_id == room_info.owner_id {
+            room.and_then(|room| room.user1.clone())
+        } else {
+            room.and_then(|room| room.user2.clone())
+        }
+    };
+    let Some(sender_tx) = sender_tx else {
+        return;
+    };
+
+    let query = serde_json::from_value::<HintQuery>(msg.data.clone()).unwrap_or_default();
+    match room_hints(state, &room_info, user_id, query.count, query.board_state.as_ref()).await {
+        Ok(hints) => send_to(&[&sender_tx], "hint", HintResponse { hints }).await,
+        Err((_, message)) => {
+            info!("Rejected hint request from {}: {}", user_id, message);
+            send_error_message(&sender_tx, &message).await;
+        }
+    }
+}
+
+/// 落子、停一手前的状态检查
+fn check_playing(room_info: &RoomInfo) -> Result<(), RuleError> {
+    match room_info.status.as_str() {
+        "finished" => Err(RuleError::GameFinished),
+        "scoring" => Err(RuleError::Scoring),
+        _ => Ok(()),
+    }
+}
+
+/// 房主默认执黑、访客执白；AI 对战中 AI 执黑时房主执白
+fn player_color(room_info: &RoomInfo, user_id: Uuid) -> &'static str {
+    let owner_color = room_info.owner_color();
+    if user_id == room_info.owner_id {
+        owner_color
+    } else if owner_color == "black" {
+        "white"
+    } else {
+        "black"
+    }
+}
+
+/// 对局双方的连接，AI 对战中只有房主
+fn players<'a>(sender_tx: &'a WsSender, target_tx: Option<&'a WsSender>) -> Vec<&'a WsSender> {
+    std::iter::once(sender_tx).chain(target_tx).collect()
+}
+
+async fn send_to<T: Serialize>(txs: &[&WsSender], mode: &str, data: T) {
+    let msg = Data::<T> {
+        mode: mode.to_string(),
+        data,
+    };
+    let text = to_string(&msg).unwrap();
+    for tx in txs {
+        let _ = tx
+            .lock()
+            .await
+            .send(Message::Text(text.clone().into()))
+            .await;
     }
 }
 
 async fn handle_update_chess(
     msg: &Data<Value>,
-    target_tx: &mut WsSender,
+    sender_tx: &WsSender,
+    target_tx: &WsSender,
     state: &AppState,
     room_info: &RoomInfo,
+    user_id: Uuid,
 ) {
     if let Ok(data) = serde_json::from_value::<UpdataChess>(msg.data.clone()) {
-        let resp = Data::<UpdataChessResponse> {
-            mode: "updateChess".to_string(),
-            data: UpdataChessResponse {
-                put_chess: data.put_chess.clone(),
-            },
+        let resp = UpdataChessResponse {
+            put_chess: data.put_chess.clone(),
+            quantum: data.quantum,
         };
 
-        if data.put_chess.position != "0,0" {
-            if let Err(err) = update_game_state(state, room_info, &data).await {
-                info!("Failed to update room state: {}", err);
+        // 旧客户端以 "0,0" 表示停一手
+        if data.put_chess.position == "0,0" {
+            if let Some(updated_room) = apply_pass(sender_tx, state, room_info, user_id).await {
+                send_to(&[target_tx], "updateChess", resp).await;
+                end_if_both_passed(&[sender_tx, target_tx], state, &updated_room).await;
+            }
+            return;
+        }
+
+        // 由服务端规则引擎计算落子结果，非法或被篡改的落子直接拒绝
+        let outcome = match validate_move(room_info, user_id, &data) {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                info!("Rejected move from {}: {}", user_id, err);
+                send_error_message(sender_tx, &err.to_string()).await;
                 return;
             }
+        };
+
+        if let Err(err) = update_game_state(state, room_info, &outcome).await {
+            info!("Failed to update room state: {}", err);
+            return;
         }
 
-        let _ = target_tx
-            .lock()
-            .await
-            .send(Message::Text(to_string(&resp).unwrap().into()))
-            .await;
+        send_to(&[target_tx], "updateChess", resp).await;
+    }
+}
+
+fn validate_move(
+    room_info: &RoomInfo,
+    user_id: Uuid,
+    data: &UpdataChess,
+) -> Result<MoveOutcome, RuleError> {
+    check_playing(room_info)?;
+    let color = player_color(room_info, user_id);
+    if data.put_chess.color != color {
+        return Err(RuleError::InvalidColor(data.put_chess.color.clone()));
+    }
+
+    let position = QuantumPosition::from_room_info(room_info);
+    let outcome = if data.quantum {
+        position.play_quantum(&data.put_chess.position, color)?
+    } else {
+        position.play(&data.put_chess.position, color)?
+    };
+
+    outcome.position.verify_submitted(&data.board)?;
+    if data.black_lost != outcome.position.black_lost || data.white_lost != outcome.position.white_lost {
+        return Err(RuleError::BoardMismatch);
     }
+    Ok(outcome)
 }
 
 async fn update_game_state(
     state: &AppState,
     room_info: &RoomInfo,
-    data: &UpdataChess,
+    outcome: &MoveOutcome,
 ) -> Result<RoomInfo, sqlx::Error> {
+    let mut chessman_records = room_info.chessman_records.as_array().cloned().unwrap_or_default();
+    chessman_records.push(serde_json::to_value(&outcome.record).unwrap_or(Value::Null));
+
     state
         .db
         .update_room(&RoomInfo {
-            id: room_info.id,
-            room_id: room_info.room_id,
-            owner_id: room_info.owner_id,
-            visitor_id: room_info.visitor_id,
-            status: room_info.status.clone(),
-            round: if room_info.round == "black" {
-                "white".to_string()
-            } else {
-                "black".to_string()
-            },
-            winner: room_info.winner.clone(),
-            board: data.board.clone(),
+            round: outcome.position.next_color().to_string(),
+            board: outcome.position.board_value(),
             countdown: 30,
-            moves: room_info.moves + 1,
-            black_lost: data.black_lost,
-            white_lost: data.white_lost,
-            model: room_info.model.clone(),
-            chessman_records: data.chessman_records.clone(),
-            phase: room_info.phase.clone(), // 新增：复制phase字段
+            moves: outcome.position.moves,
+            black_lost: outcome.position.black_lost,
+            white_lost: outcome.position.white_lost,
+            chessman_records: Value::Array(chessman_records),
+            position_history: outcome.position.history_value(),
+            ..room_info.clone()
+        })
+        .await
+}
+
+async fn handle_pass(
+    sender_tx: &WsSender,
+    target_tx: Option<&WsSender>,
+    state: &AppState,
+    room_info: &RoomInfo,
+    user_id: Uuid,
+) {
+    if let Some(updated_room) = apply_pass(sender_tx, state, room_info, user_id).await {
+        let color = player_color(room_info, user_id).to_string();
+        send_to(target_tx.as_slice(), "pass", PlayerAction { color }).await;
+        end_if_both_passed(&players(sender_tx, target_tx), state, &updated_room).await;
+    }
+}
+
+/// 校验并记录停一手，失败时把错误发回给发送方
+async fn apply_pass(
+    sender_tx: &WsSender,
+    state: &AppState,
+    room_info: &RoomInfo,
+    user_id: Uuid,
+) -> Option<RoomInfo> {
+    let outcome = check_playing(room_info)
+        .and_then(|_| QuantumPosition::from_room_info(room_info).pass(player_color(room_info, user_id)));
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            info!("Rejected pass from {}: {}", user_id, err);
+            send_error_message(sender_tx, &err.to_string()).await;
+            return None;
+        }
+    };
+
+    match update_game_state(state, room_info, &outcome).await {
+        Ok(updated_room) => Some(updated_room),
+        Err(err) => {
+            info!("Failed to record pass: {}", err);
+            None
+        }
+    }
+}
+
+/// 双方连续停一手后进入数子阶段，由双方标记死子并确认结果；结果发给 txs，返回数子阶段的房间
+pub async fn end_if_both_passed(
+    txs: &[&WsSender],
+    state: &AppState,
+    room_info: &RoomInfo,
+) -> Option<RoomInfo> {
+    if !QuantumPosition::from_room_info(room_info).both_passed() {
+        return None;
+    }
+
+    // 启用坍缩规则且对局中没有测量过：先抽取棋盘，只计该棋盘的得分
+    let mut room_info = room_info.clone();
+    let collapse_rule = CollapseRule::parse(&room_info.collapse_rule).unwrap_or_default();
+    if collapse_rule != CollapseRule::None && room_info.collapsed_board.is_none() {
+        match apply_collapse(state, &room_info, None).await {
+            Ok((updated_room, result)) => {
+                room_info = updated_room;
+                send_to(txs, "collapse", result).await;
+            }
+            Err(err) => {
+                info!("Failed to collapse room {}: {}", room_info.room_id, err);
+                return None;
+            }
+        }
+    }
+
+    let scoring_room = RoomInfo {
+        status: "scoring".to_string(),
+        scoring_state: ScoringState::default().to_value(),
+        ..room_info
+    };
+    match state.db.update_room(&scoring_room).await {
+        Ok(updated_room) => {
+            send_score_update(txs, &updated_room).await;
+            Some(updated_room)
+        }
+        Err(err) => {
+            info!("Failed to enter scoring phase: {}", err);
+            None
+        }
+    }
+}
+
+/// 测量：轮到的一方发起，按建房时承诺的种子抽取一个棋盘，叠加态坍缩到该棋盘
+async fn handle_measure(
+    sender_tx: &WsSender,
+    target_tx: &WsSender,
+    state: &AppState,
+    room_info: &RoomInfo,
+    user_id: Uuid,
+) {
+    if room_info.collapse_rule != CollapseRule::Measure.as_str() {
+        send_error_message(sender_tx, "Measurement is not enabled in this room").await;
+        return;
+    }
+    if let Err(err) = check_playing(room_info) {
+        send_error_message(sender_tx, &err.to_string()).await;
+        return;
+    }
+
+    let color = player_color(room_info, user_id);
+    match apply_collapse(state, room_info, Some(color)).await {
+        Ok((_, result)) => send_to(&[sender_tx, target_tx], "collapse", result).await,
+        Err(err) => {
+            info!("Rejected measurement from {}: {}", user_id, err);
+            send_error_message(sender_tx, &err).await;
+        }
+    }
+}
+
+/// 抽取并记录坍缩，同时公开种子；color 为发起测量的一方，终局坍缩时为空
+async fn apply_collapse(
+    state: &AppState,
+    room_info: &RoomInfo,
+    color: Option<&str>,
+) -> Result<(RoomInfo, CollapseResult), String> {
+    let seed = match (&room_info.collapse_seed, room_info.collapsed_board) {
+        (Some(seed), None) => seed.clone(),
+        _ => return Err(RuleError::MeasureNotAllowed.to_string()),
+    };
+    let position = QuantumPosition::from_room_info(room_info);
+    let board = draw_board(&seed, position.boards.len());
+    let outcome = match color {
+        Some(color) => position.measure(color, board).map_err(|err| err.to_string())?,
+        None => MoveOutcome {
+            position: position.collapse(board),
+            record: ChessmanRecord {
+                action: Some(MoveAction::Measure),
+                ..ChessmanRecord::default()
+            },
+        },
+    };
+
+    let revealed_room = RoomInfo {
+        revealed_seed: Some(seed.clone()),
+        collapsed_board: Some(board as i32),
+        ..room_info.clone()
+    };
+    let updated_room = update_game_state(state, &revealed_room, &outcome)
+        .await
+        .map_err(|err| err.to_string())?;
+    let result = CollapseResult {
+        board,
+        seed,
+        commitment: room_info.seed_commitment.clone(),
+    };
+    Ok((updated_room, result))
+}
+
+/// 把当前死子标记与重新计算的结果发给双方
+async fn send_score_update(txs: &[&WsSender], room_info: &RoomInfo) {
+    let scoring_state = ScoringState::from_value(&room_info.scoring_state);
+    let update = ScoreUpdate {
+        dead_stones: scoring_state.dead_stones,
+        accepted: scoring_state.accepted,
+        score: score_room(room_info),
+    };
+    send_to(txs, "scoreUpdate", update).await;
+}
+
+/// 数子阶段的前置检查，失败时把错误发回给发送方
+async fn check_scoring(sender_tx: &WsSender, room_info: &RoomInfo) -> bool {
+    if room_info.status != "scoring" {
+        send_error_message(sender_tx, "Game is not in the scoring phase").await;
+        return false;
+    }
+    true
+}
+
+async fn handle_toggle_dead(
+    msg: &Data<Value>,
+    sender_tx: &WsSender,
+    target_tx: Option<&WsSender>,
+    state: &AppState,
+    room_info: &RoomInfo,
+) {
+    if !check_scoring(sender_tx, room_info).await {
+        return;
+    }
+    let Ok(data) = serde_json::from_value::<ToggleDead>(msg.data.clone()) else {
+        return;
+    };
+
+    // 以第一个棋盘的坐标标记整块棋，计分时所有棋盘上的对应棋子一起移除
+    let mut scoring_state = ScoringState::from_value(&room_info.scoring_state);
+    let position = QuantumPosition::from_room_info(room_info);
+    if !scoring_state.toggle_group(&position, &data.position) {
+        send_error_message(sender_tx, &format!("No stone at {}", data.position)).await;
+        return;
+    }
+
+    let updated_room = RoomInfo {
+        scoring_state: scoring_state.to_value(),
+        ..room_info.clone()
+    };
+    match state.db.update_room(&updated_room).await {
+        Ok(updated_room) => send_score_update(&players(sender_tx, target_tx), &updated_room).await,
+        Err(err) => info!("Failed to update dead stones: {}", err),
+    }
+}
+
+async fn handle_accept_score(
+    sender_tx: &WsSender,
+    target_tx: Option<&WsSender>,
+    state: &AppState,
+    room_info: &RoomInfo,
+    user_id: Uuid,
+) {
+    if !check_scoring(sender_tx, room_info).await {
+        return;
+    }
+
+    let txs = players(sender_tx, target_tx);
+    match accept_score(state, room_info, user_id).await {
+        Ok(ScoreAcceptance::Pending(updated_room)) => send_score_update(&txs, &updated_room).await,
+        Ok(ScoreAcceptance::Disputed(color)) => {
+            send_to(&txs, "resumeGame", PlayerAction { color: color.to_string() }).await
+        }
+        Ok(ScoreAcceptance::Finished(result)) => send_to(&txs, "setWinner", result).await,
+        Err(err) => info!("Failed to record score acceptance: {}", err),
+    }
+}
+
+/// 一方同意数子结果后的去向
+enum ScoreAcceptance {
+    Pending(Box<RoomInfo>),  // 还在等另一方同意
+    Disputed(&'static str),  // AI 不同意死子标记，对局恢复
+    Finished(SetWinner),     // 双方都已同意，对局结束
+}
+
+/// 记录 user_id 一方的同意；AI 对战中由 AI 核对死子标记后代为同意或提出异议
+async fn accept_score(
+    state: &AppState,
+    room_info: &RoomInfo,
+    user_id: Uuid,
+) -> Result<ScoreAcceptance, sqlx::Error> {
+    let mut scoring_state = ScoringState::from_value(&room_info.scoring_state);
+    if let Opponent::Ai { color, .. } = Opponent::from_room_info(room_info) {
+        let accepted = accepts_dead_stones(
+            &QuantumPosition::from_room_info(room_info),
+            &scoring_state.dead_stones,
+            color,
+            RuleSet::parse(&room_info.rule_set).unwrap_or_default(),
+            room_info.komi,
+        );
+        if !accepted {
+            info!("AI disputes the dead stones marked in room {}", room_info.room_id);
+            resume_game(state, room_info, color.as_str()).await?;
+            return Ok(ScoreAcceptance::Disputed(color.as_str()));
+        }
+        scoring_state.accept(color.as_str());
+    }
+    let both_accepted = scoring_state.accept(player_color(room_info, user_id));
+    let room_info = RoomInfo {
+        scoring_state: scoring_state.to_value(),
+        ..room_info.clone()
+    };
+
+    if !both_accepted {
+        let updated_room = state.db.update_room(&room_info).await?;
+        return Ok(ScoreAcceptance::Pending(Box::new(updated_room)));
+    }
+
+    // 双方都同意后才写入胜负并更新评分
+    let score = score_room(&room_info);
+    info!(
+        "Scored room {}: black {} / white {} (komi {}), winner {}",
+        room_info.room_id, score.black_score, score.white_score, score.komi, score.winner
+    );
+    let result = SetWinner {
+        winner: score.winner,
+        reason: Some("score".to_string()),
+        black_score: Some(score.black_score),
+        white_score: Some(score.white_score),
+    };
+    update_winner(state, &room_info, &result).await?;
+    Ok(ScoreAcceptance::Finished(result))
+}
+
+/// 对死子判定有争议时恢复对局，之后需要重新连续停一手才会再次数子
+async fn handle_dispute_score(
+    sender_tx: &WsSender,
+    target_tx: Option<&WsSender>,
+    state: &AppState,
+    room_info: &RoomInfo,
+    user_id: Uuid,
+) {
+    if !check_scoring(sender_tx, room_info).await {
+        return;
+    }
+
+    let color = player_color(room_info, user_id);
+    if let Err(err) = resume_game(state, room_info, color).await {
+        info!("Failed to resume game: {}", err);
+        return;
+    }
+    send_to(&players(sender_tx, target_tx), "resumeGame", PlayerAction { color: color.to_string() }).await;
+}
+
+/// 记录 color 一方的异议并回到对局阶段，清空死子标记
+async fn resume_game(state: &AppState, room_info: &RoomInfo, color: &str) -> Result<RoomInfo, sqlx::Error> {
+    let mut chessman_records = room_info.chessman_records.as_array().cloned().unwrap_or_default();
+    let record = ChessmanRecord::action(MoveAction::Resume, color);
+    chessman_records.push(serde_json::to_value(&record).unwrap_or(Value::Null));
+
+    state
+        .db
+        .update_room(&RoomInfo {
+            status: "playing".to_string(),
+            chessman_records: Value::Array(chessman_records),
+            scoring_state: ScoringState::default().to_value(),
+            ..room_info.clone()
         })
         .await
 }
 
+async fn handle_resign(
+    sender_tx: &WsSender,
+    target_tx: Option<&WsSender>,
+    state: &AppState,
+    room_info: &RoomInfo,
+    user_id: Uuid,
+) {
+    if room_info.status == "finished" {
+        send_error_message(sender_tx, &RuleError::GameFinished.to_string()).await;
+        return;
+    }
+
+    let color = player_color(room_info, user_id);
+    let mut chessman_records = room_info.chessman_records.as_array().cloned().unwrap_or_default();
+    let record = ChessmanRecord::action(MoveAction::Resign, color);
+    chessman_records.push(serde_json::to_value(&record).unwrap_or(Value::Null));
+    let room_info = RoomInfo {
+        chessman_records: Value::Array(chessman_records),
+        ..room_info.clone()
+    };
+
+    let result = SetWinner {
+        winner: if color == "black" { "white" } else { "black" }.to_string(),
+        reason: Some("resign".to_string()),
+        black_score: None,
+        white_score: None,
+    };
+    finish_game(sender_tx, target_tx, state, &room_info, result).await;
+}
+
 async fn handle_set_winner(
     msg: &Data<Value>,
-    target_tx: &mut WsSender,
+    sender_tx: &WsSender,
+    target_tx: Option<&WsSender>,
     state: &AppState,
     room_info: &RoomInfo,
-    text: &str,
+    user_id: Uuid,
 ) {
     if let Ok(data) = serde_json::from_value::<SetWinner>(msg.data.clone()) {
-        if let Err(err) = update_winner(state, room_info, &data).await {
-            info!("Failed to update room winner: {}", err);
-            return;
+        // 旧客户端通过宣布对方获胜来认输；胜负不再采信客户端，其余情况只能通过双方停一手数子决定
+        let color = player_color(room_info, user_id);
+        let conceded = (data.winner == "black" || data.winner == "white") && data.winner != color;
+        if conceded {
+            handle_resign(sender_tx, target_tx, state, room_info, user_id).await;
+        } else {
+            send_error_message(
+                sender_tx,
+                "The winner is decided by resignation or by scoring after two passes",
+            )
+            .await;
         }
+    }
+}
 
-        let _ = target_tx
-            .lock()
-            .await
-            .send(Message::Text(text.to_string().into()))
-            .await;
+/// 写入胜者并通知双方
+async fn finish_game(
+    sender_tx: &WsSender,
+    target_tx: Option<&WsSender>,
+    state: &AppState,
+    room_info: &RoomInfo,
+    result: SetWinner,
+) {
+    if let Err(err) = update_winner(state, room_info, &result).await {
+        info!("Failed to update room winner: {}", err);
+        return;
     }
+    send_to(&players(sender_tx, target_tx), "setWinner", result).await;
 }
 
 async fn update_winner(
@@ -317,48 +809,58 @@ async fn update_winner(
     room_info: &RoomInfo,
     data: &SetWinner,
 ) -> Result<RoomInfo, sqlx::Error> {
+    // 对局结束，AI 不必再思考
+    state.ai_workers.cancel(room_info.room_id);
+    state.ponderer.stop(room_info.room_id);
+
     let updated_room = state
         .db
         .update_room(&RoomInfo {
-            id: room_info.id,
-            room_id: room_info.room_id,
-            owner_id: room_info.owner_id,
-            visitor_id: room_info.visitor_id,
             status: "finished".to_string(),
-            round: room_info.round.clone(),
             winner: Some(data.winner.clone()),
-            board: room_info.board.clone(),
-            countdown: room_info.countdown,
-            moves: room_info.moves,
-            black_lost: room_info.black_lost,
-            white_lost: room_info.white_lost,
-            model: room_info.model.clone(),
-            chessman_records: room_info.chessman_records.clone(),
-            phase: room_info.phase.clone(), // 新增：复制phase字段
+            ..room_info.clone()
         })
         .await?;
 
+    // 在后台生成复盘
+    spawn_review(state, updated_room.clone());
+
     // 游戏结束后更新评分
     let rating_system = RatingSystem::new();
+    let (width, height) = room_info.board_size();
     let game_result = GameResult {
-        winner: Some(data.winner.clone()),
-        black_score: room_info.black_lost,
-        white_score: room_info.white_lost,
-        model: room_info.model,
+        winner: Some(data.winner.clone()).filter(|winner| winner != "draw"),
+        black_score: data.black_score.unwrap_or_default(),
+        white_score: data.white_score.unwrap_or_default(),
+        model: rating_pool(width, height),
+        variant: room_info.variant.clone(),
+        handicap: room_info.handicap,
     };
 
     // 在后台更新评分，不阻塞响应
     let db_clone = state.db.clone();
     let owner_id = room_info.owner_id;
     
-    // 如果有访客，更新双方评分
-    if let Some(visitor_id) = room_info.visitor_id {
+    // 人人对战且有访客时更新双方评分；AI 对战只在访客是机器人账号时计分（外部引擎的访客是虚拟玩家），
+    // 机器人的评分固定，只更新玩家一方
+    let rated_visitor = match Opponent::from_room_info(room_info) {
+        Opponent::Human => room_info.visitor_id,
+        Opponent::Ai { .. } => room_info.visitor_id.filter(|&id| state.bots.is_bot(id)),
+    };
+    let anchored = rated_visitor.filter(|&id| state.bots.is_bot(id));
+    if let Some(visitor_id) = rated_visitor {
+        let (black_id, white_id) = if room_info.owner_color() == "black" {
+            (owner_id, visitor_id)
+        } else {
+            (visitor_id, owner_id)
+        };
         tokio::spawn(async move {
             if let Err(err) = rating_system.update_ratings(
                 &db_clone,
                 &game_result,
-                owner_id,
-                visitor_id,
+                black_id,
+                white_id,
+                anchored,
             ).await {
                 info!("Failed to update ratings: {}", err);
             }
@@ -373,6 +875,9 @@ async fn cleanup_connection(state: &AppState, room_id: Uuid, user_id: Uuid, room
     if let Some(room) = rooms.get_mut(&room_id) {
         if user_id == room_info.owner_id {
             room.user1 = None;
+            // AI 对战的房主断开后，不再为其计算 AI 落子
+            state.ai_workers.cancel(room_id);
+            state.ponderer.stop(room_id);
         } else {
             room.user2 = None;
         }
@@ -394,20 +899,211 @@ struct UpdataChess {
     black_lost: i32,
     white_lost: i32,
     chessman_records: serde_json::Value,
+    #[serde(default)]
+    quantum: bool, // 量子落子，对方下一手与之纠缠
 }
 
 #[derive(Serialize, Deserialize)]
 struct UpdataChessResponse {
     #[serde(rename(serialize = "putChess", deserialize = "putChess"))]
     put_chess: Chessman,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    quantum: bool,
 }
 
 #[derive(Serialize, Deserialize)]
 struct SetWinner {
     winner: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>, // "resign" / "score"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    black_score: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    white_score: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ToggleDead {
+    position: String, // 第一个棋盘上的坐标
+}
+
+#[derive(Serialize)]
+struct ScoreUpdate {
+    dead_stones: Vec<String>,
+    accepted: Vec<String>,
+    score: ScoreResult,
+}
+
+/// 坍缩结果：抽中的棋盘下标与公开的种子，可用建房时公布的承诺验证
+#[derive(Serialize)]
+struct CollapseResult {
+    board: usize,
+    seed: String,
+    commitment: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct HintQuery {
+    #[serde(default)]
+    count: Option<usize>,
+    #[serde(default)]
+    board_state: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct HintResponse {
+    hints: Vec<MoveHint>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlayerAction {
+    color: String,
 }
 
 #[derive(Serialize, Deserialize)]
 struct SendMessage {
     message: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{AIDifficulty, AIEngine, GameType};
+    use crate::engine::EngineChoice;
+    use crate::rules::Variant;
+    use std::time::Duration;
+
+    /// 需要数据库的测试从这个环境变量读取连接地址，未设置时跳过
+    const TEST_DATABASE_ENV: &str = "TEST_DATABASE_URL";
+
+    async fn test_state() -> Option<AppState> {
+        let database_url = std::env::var(TEST_DATABASE_ENV).ok()?;
+        let db = Database::new(&database_url).await.expect("Failed to connect to test database");
+        let bots = BotAccounts::ensure(&db).await.unwrap();
+        Some(AppState {
+            rooms: Arc::new(Mutex::new(HashMap::new())),
+            db: Arc::new(db),
+            engines: Arc::new(EngineRegistry::default()),
+            ai_workers: Arc::new(AiWorkers::new(1)),
+            review_workers: Arc::new(AiWorkers::named("review-worker", 1)),
+            ponderer: Arc::new(Ponderer::default()),
+            hint_limiter: Arc::new(HintLimiter::default()),
+            bots: Arc::new(bots),
+        })
+    }
+
+    /// 房主执黑、机器人执白的 9 路经典围棋 AI 对局
+    fn bot_room(owner_id: Uuid, bot: Uuid) -> RoomInfo {
+        let (engine, difficulty) = (AIEngine::Heuristic, AIDifficulty::Beginner);
+        RoomInfo {
+            room_id: Uuid::new_v4(),
+            owner_id,
+            visitor_id: Some(bot),
+            status: "playing".to_string(),
+            round: "black".to_string(),
+            model: 9,
+            ko_rule: "simple".to_string(),
+            komi: 7.5,
+            rule_set: "area".to_string(),
+            variant: Variant::Classical.as_str().to_string(),
+            collapse_rule: "none".to_string(),
+            game_type: GameType::Ai.as_str().to_string(),
+            ai_engine: Some(engine.as_str().to_string()),
+            ai_difficulty: Some(difficulty.as_str().to_string()),
+            ai_color: Some("white".to_string()),
+            ..RoomInfo::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_finished_ai_game_counts_for_the_bot() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let (engine, difficulty) = (AIEngine::Heuristic, AIDifficulty::Beginner);
+        let bot = state.bots.bot_for(&EngineChoice::Builtin(engine), difficulty).unwrap();
+        let owner = state.db.create_user(&format!("player-{}", Uuid::new_v4().simple()), "secret").await.unwrap();
+        let (model, variant) = (9, Variant::Classical.as_str());
+        let before = state.db.get_user_ranking(&bot, model, variant).await.unwrap();
+
+        // 机器人没有对局时也在排行榜上
+        let leaderboard = state.db.get_leaderboard(model, variant, 1000).await.unwrap();
+        let bot_name = crate::bots::bot_username(engine, difficulty);
+        assert!(leaderboard.iter().any(|entry| entry.username == bot_name && entry.is_bot));
+
+        let room_info = state.db.create_room(&bot_room(owner.user_id, bot)).await.unwrap();
+        let result = SetWinner {
+            winner: "white".to_string(),
+            reason: Some("resign".to_string()),
+            black_score: None,
+            white_score: None,
+        };
+        let finished = update_winner(&state, &room_info, &result).await.unwrap();
+        assert_eq!(finished.status, "finished");
+
+        // 评分在后台更新
+        let mut after = before.clone();
+        for _ in 0..50 {
+            after = state.db.get_user_ranking(&bot, model, variant).await.unwrap();
+            if after.games_played > before.games_played {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(after.games_played, before.games_played + 1);
+        assert_eq!(after.wins, before.wins + 1);
+        assert_eq!(after.rating, before.rating);
+        let player = state.db.get_user_ranking(&owner.user_id, model, variant).await.unwrap();
+        assert_eq!((player.games_played, player.losses), (1, 1));
+        assert!(player.rating < 1500.0);
+    }
+
+    #[tokio::test]
+    async fn test_ai_disputes_its_live_stones_marked_dead() {
+        let Some(state) = test_state().await else {
+            return;
+        };
+        let bot = state.bots.bot_for(&EngineChoice::Builtin(AIEngine::Heuristic), AIDifficulty::Beginner).unwrap();
+        let owner = state.db.create_user(&format!("player-{}", Uuid::new_v4().simple()), "secret").await.unwrap();
+
+        // 白棋活得好好的，黑方却把白棋全部标成死子
+        let mut position = QuantumPosition { variant: Variant::Classical, quantum_pairs: 0, ..QuantumPosition::with_boards(9, 9, 1) };
+        for mv in ["1,1", "5,5", "1,2", "5,6", "pass", "pass"] {
+            let color = position.next_color();
+            position = if mv == "pass" { position.pass(color) } else { position.play(mv, color) }.unwrap().position;
+        }
+        let mut scoring_state = ScoringState::default();
+        assert!(scoring_state.toggle_group(&position, "5,5"));
+        let room_info = state
+            .db
+            .create_room(&RoomInfo {
+                board: position.board_value(),
+                moves: position.moves,
+                quantum_pairs: 0,
+                ..bot_room(owner.user_id, bot)
+            })
+            .await
+            .unwrap();
+        let room_info = state
+            .db
+            .update_room(&RoomInfo {
+                status: "scoring".to_string(),
+                scoring_state: scoring_state.to_value(),
+                ..room_info
+            })
+            .await
+            .unwrap();
+
+        let acceptance = accept_score(&state, &room_info, owner.user_id).await.unwrap();
+        assert!(matches!(acceptance, ScoreAcceptance::Disputed("white")));
+        let resumed = state.db.get_room_by_room_id(room_info.room_id).await.unwrap();
+        assert_eq!(resumed.status, "playing");
+        assert_eq!(resumed.winner, None);
+        assert!(ScoringState::from_value(&resumed.scoring_state).dead_stones.is_empty());
+
+        // 对局没有结束，不会在后台更新评分
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let player = state.db.get_user_ranking(&owner.user_id, 9, Variant::Classical.as_str()).await;
+        assert_eq!(player.map_or(0, |ranking| ranking.games_played), 0);
+    }
+}
@@ -95,6 +95,9 @@ const actions = {
       // 如果 board 是对象，尝试转换为 Map
       if (board.board1 && typeof board.board1 === 'object') {
         boardMap = new Map(Object.entries(board.board1));
+      } else if (Array.isArray(board.boards) && board.boards[0] && typeof board.boards[0] === 'object') {
+        // 版本 2 格式：{ version: 2, boards: [...] }，这里只使用第一个棋盘
+        boardMap = new Map(Object.entries(board.boards[0]));
       } else if (Array.isArray(board)) {
         boardMap = new Map(board);
       } else {